@@ -20,12 +20,16 @@ pub struct AbstractProcess {
     terminate: Option<syn::ImplItemMethod>,
     /// Handle link died method.
     handle_link_death: Option<syn::ImplItemMethod>,
+    /// Handle monitored process died method.
+    handle_process_death: Option<syn::ImplItemMethod>,
     /// Message handler methods.
     message_handlers: Vec<syn::ImplItemMethod>,
     /// Request handler methods.
     request_handlers: Vec<syn::ImplItemMethod>,
     /// Deferred request handler methods.
     deferred_request_handlers: Vec<syn::ImplItemMethod>,
+    /// Names of handler methods marked with `priority = high`.
+    high_priority_handlers: Vec<syn::Ident>,
     /// Name of trait wrapping messages
     message_trait_name: syn::Ident,
     /// Name of trait wrapping requests
@@ -58,9 +62,11 @@ impl AbstractProcess {
             init,
             terminate,
             handle_link_death,
+            handle_process_death,
             message_handlers,
             request_handlers,
             deferred_request_handlers,
+            high_priority_handlers,
         ) = item_impl
             .items
             .clone()
@@ -84,26 +90,44 @@ impl AbstractProcess {
                                 .map(|item_attr| (i, item_attr))
                         })?;
                 // We found an attribute, we should remove it from the original item_impl
-                impl_item_method.attrs.remove(j);
+                let attr = impl_item_method.attrs.remove(j);
                 if let syn::ImplItem::Method(impl_item_method) = item_impl.items.get_mut(i).unwrap()
                 {
                     impl_item_method.attrs.remove(j);
                 }
 
-                Some((item_attr, impl_item_method))
+                Some((item_attr, attr, impl_item_method))
             })
             .fold(
-                Ok((None, None, None, Vec::new(), Vec::new(), Vec::new())),
-                |acc, (item_attr, impl_item_method)| {
+                Ok((
+                    None,
+                    None,
+                    None,
+                    None,
+                    Vec::new(),
+                    Vec::new(),
+                    Vec::new(),
+                    Vec::new(),
+                )),
+                |acc, (item_attr, attr, impl_item_method)| {
                     let (
                         mut init,
                         mut terminate,
                         mut handle_link_death,
+                        mut handle_process_death,
                         mut message_handlers,
                         mut request_handlers,
                         mut deferred_request_handlers,
+                        mut high_priority_handlers,
                     ) = acc?;
 
+                    if item_attr.is_handler() && !attr.tokens.is_empty() {
+                        let handler_args: HandlerArgs = attr.parse_args()?;
+                        if handler_args.is_high_priority() {
+                            high_priority_handlers.push(impl_item_method.sig.ident.clone());
+                        }
+                    }
+
                     match item_attr {
                         ItemAttr::Init => {
                             if init.is_some() {
@@ -135,6 +159,16 @@ impl AbstractProcess {
 
                             handle_link_death = Some(impl_item_method);
                         }
+                        ItemAttr::HandleProcessDeath => {
+                            if handle_process_death.is_some() {
+                                return Err(syn::Error::new(
+                                    impl_item_method.sig.ident.span(),
+                                    "handle_process_death method already defined",
+                                ));
+                            }
+
+                            handle_process_death = Some(impl_item_method);
+                        }
                         ItemAttr::HandleMessage => {
                             message_handlers.push(impl_item_method);
                         }
@@ -150,9 +184,11 @@ impl AbstractProcess {
                         init,
                         terminate,
                         handle_link_death,
+                        handle_process_death,
                         message_handlers,
                         request_handlers,
                         deferred_request_handlers,
+                        high_priority_handlers,
                     ))
                 },
             )?;
@@ -189,9 +225,11 @@ impl AbstractProcess {
             init,
             terminate,
             handle_link_death,
+            handle_process_death,
             message_handlers,
             request_handlers,
             deferred_request_handlers,
+            high_priority_handlers,
             message_trait_name,
             request_trait_name,
         })
@@ -322,6 +360,7 @@ impl AbstractProcess {
         let (init_impl, startup_error) = self.expand_init_impl();
         let terminate_impl = self.expand_terminate_impl();
         let handle_link_death_impl = self.expand_handle_link_death_impl();
+        let handle_process_death_impl = self.expand_handle_process_death_impl();
 
         quote! {
             impl #impl_generics lunatic::ap::AbstractProcess for #self_ty #where_clause {
@@ -334,6 +373,7 @@ impl AbstractProcess {
                 #init_impl
                 #terminate_impl
                 #handle_link_death_impl
+                #handle_process_death_impl
             }
         }
     }
@@ -432,6 +472,23 @@ impl AbstractProcess {
             .unwrap_or_default()
     }
 
+    /// Expands the `handle_process_death` method in the abstract process
+    /// implementation.
+    fn expand_handle_process_death_impl(&self) -> TokenStream {
+        self.handle_process_death
+            .as_ref()
+            .map(|handle_process_death| {
+                let ident = &handle_process_death.sig.ident;
+
+                quote! {
+                    fn handle_process_death(mut state: lunatic::ap::State<Self>, process_id: u64) {
+                        state.#ident(process_id);
+                    }
+                }
+            })
+            .unwrap_or_default()
+    }
+
     /// Expands the `MessageHandler` implementations for the message handler
     /// wrapper types.
     fn expand_message_handler_impls(&self) -> TokenStream {
@@ -444,6 +501,7 @@ impl AbstractProcess {
             let self_ty = &self.item_impl.self_ty;
             let message_type = Self::handler_wrapper_ident(&sig.ident);
             let fn_ident = &sig.ident;
+            let priority = self.expand_priority(fn_ident);
             let (impl_generics, ty_generics, where_clause) = self.item_impl.generics.split_for_impl();
             let args = filter_typed_args(sig.inputs.iter());
             let offset = usize::from(!self.item_impl.generics.params.is_empty());
@@ -455,6 +513,8 @@ impl AbstractProcess {
             quote! {
                 #( #attrs )*
                 impl #impl_generics lunatic::ap::MessageHandler<#message_type #ty_generics> for #self_ty #where_clause {
+                    #priority
+
                     fn handle(mut state: lunatic::ap::State<Self>, message: #message_type #ty_generics) {
                         state.#fn_ident(#( #message_fields ),*)
                     }
@@ -485,6 +545,7 @@ impl AbstractProcess {
                 }
             };
             let fn_ident = &sig.ident;
+            let priority = self.expand_priority(fn_ident);
             let (impl_generics, ty_generics, where_clause) = self.item_impl.generics.split_for_impl();
             let args = filter_typed_args(sig.inputs.iter());
             let offset = usize::from(!self.item_impl.generics.params.is_empty());
@@ -496,6 +557,7 @@ impl AbstractProcess {
             quote! {
                 #( #attrs )*
                 impl #impl_generics lunatic::ap::RequestHandler<#request_type #ty_generics> for #self_ty #where_clause {
+                    #priority
                     type Response = #response_type;
 
                     fn handle(mut state: lunatic::ap::State<Self>, request: #request_type #ty_generics) -> Self::Response {
@@ -539,6 +601,7 @@ impl AbstractProcess {
                 _ => quote!{()},
             };
             let fn_ident = &sig.ident;
+            let priority = self.expand_priority(fn_ident);
             let (impl_generics, ty_generics, where_clause) = self.item_impl.generics.split_for_impl();
             let args = filter_typed_args(sig.inputs.iter());
             let offset = usize::from(!self.item_impl.generics.params.is_empty());
@@ -551,6 +614,7 @@ impl AbstractProcess {
             quote! {
                 #( #attrs )*
                 impl #impl_generics lunatic::ap::DeferredRequestHandler<#request_type #ty_generics> for #self_ty #where_clause {
+                    #priority
                     type Response = #response_type;
 
                     fn handle(
@@ -568,6 +632,15 @@ impl AbstractProcess {
         }
     }
 
+    /// Expands the `PRIORITY` constant of a handler implementation.
+    ///
+    /// Normal priority handlers use the default value of the trait.
+    fn expand_priority(&self, fn_ident: &syn::Ident) -> Option<TokenStream> {
+        self.high_priority_handlers.contains(fn_ident).then(
+            || quote! { const PRIORITY: lunatic::ap::Priority = lunatic::ap::Priority::High; },
+        )
+    }
+
     /// Expands the new `Handler` trait.
     fn expand_handler_trait(&self) -> TokenStream {
         let Self {
@@ -944,21 +1017,72 @@ impl Parse for Args {
     }
 }
 
+/// Arguments passed to the `#[handle_message(...)]`, `#[handle_request(...)]`
+/// and `#[handle_deferred_request(...)]` attributes.
+#[derive(Default)]
+struct HandlerArgs {
+    priority: Option<syn::Ident>,
+}
+
+impl HandlerArgs {
+    fn is_high_priority(&self) -> bool {
+        matches!(&self.priority, Some(priority) if priority == "high")
+    }
+}
+
+impl Parse for HandlerArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut args = HandlerArgs::default();
+        while !input.is_empty() {
+            let ident: syn::Ident = input.parse()?;
+            let _: syn::Token![=] = input.parse()?;
+            if ident == "priority" {
+                if args.priority.is_some() {
+                    return Err(syn::Error::new(ident.span(), "priority already specified"));
+                }
+
+                let priority: syn::Ident = input.parse()?;
+                if priority != "high" && priority != "normal" {
+                    return Err(syn::Error::new(
+                        priority.span(),
+                        "priority must be `high` or `normal`",
+                    ));
+                }
+                args.priority = Some(priority);
+            } else {
+                return Err(syn::Error::new(ident.span(), "unknown argument"));
+            }
+            let _: Option<Token![,]> = input.parse()?;
+        }
+        Ok(args)
+    }
+}
+
 enum ItemAttr {
     Init,
     Terminate,
     HandleLinkTrapped,
+    HandleProcessDeath,
     HandleMessage,
     HandleRequest,
     HandleDeferredRequest,
 }
 
 impl ItemAttr {
+    /// Returns `true` for message and request handler attributes.
+    fn is_handler(&self) -> bool {
+        matches!(
+            self,
+            ItemAttr::HandleMessage | ItemAttr::HandleRequest | ItemAttr::HandleDeferredRequest
+        )
+    }
+
     fn from_str(s: &str) -> Option<ItemAttr> {
         match s {
             "init" => Some(ItemAttr::Init),
             "terminate" => Some(ItemAttr::Terminate),
             "handle_link_death" => Some(ItemAttr::HandleLinkTrapped),
+            "handle_process_death" => Some(ItemAttr::HandleProcessDeath),
             "handle_message" => Some(ItemAttr::HandleMessage),
             "handle_request" => Some(ItemAttr::HandleRequest),
             "handle_deferred_request" => Some(ItemAttr::HandleDeferredRequest),
@@ -53,8 +53,9 @@ pub fn main(_args: TokenStream, item: TokenStream) -> TokenStream {
 /// Add [`AbstractProcess`] behavior to the given struct implementation with
 /// minimum boilerplate code.
 ///
-/// - Use `#[init]`, `#[terminate]`, and `#[handle_link_trapped]` attributes to
-/// specify methods for implementing [`AbstractProcess`].
+/// - Use `#[init]`, `#[terminate]`, `#[handle_link_trapped]` and
+///   `#[handle_process_death]` attributes to specify methods for implementing
+///   [`AbstractProcess`].
 /// - Use `#[handle_message]`, `#[handle_request]` and
 ///   `#[handle_deferred_request]` attributes to specify message and request
 ///   handlers.
@@ -64,6 +65,10 @@ pub fn main(_args: TokenStream, item: TokenStream) -> TokenStream {
 /// number of parameters and invoking them works the same as directly calling
 /// the method on the struct without spawning it as a process.
 ///
+/// Handlers can be given a high priority with `#[handle_message(priority =
+/// high)]`. Messages and requests for them are handled before all other
+/// messages waiting in the mailbox.
+///
 /// A trait is generated and defaults to private and follows the name of your
 /// type with `Handler` added as a suffix. To rename or change the visibility of
 /// the generated trait, you can use the `trait_name` and `visbility` arguments
@@ -1,8 +1,10 @@
 use std::marker::PhantomData;
 
 use super::{lifecycles, AbstractProcess, ProcessRef, StartupError};
+use crate::distributed::{monitor, placement, Placement};
 use crate::function::process::{process_name, ProcessType};
-use crate::{LunaticError, Mailbox, Process, ProcessConfig, ProcessName, Tag};
+use crate::registry::{self, Registry};
+use crate::{host, LunaticError, Mailbox, Process, ProcessConfig, ProcessName, Tag};
 
 trait IntoAbstractProcessBuilder<T> {}
 
@@ -18,6 +20,8 @@ pub struct AbstractProcessBuilder<'a, T: ?Sized> {
     link: Option<Tag>,
     config: Option<&'a ProcessConfig>,
     node: Option<u64>,
+    registry: Option<&'a dyn Registry>,
+    placement: Option<Placement>,
     phantom: PhantomData<T>,
 }
 
@@ -30,16 +34,23 @@ where
             link: None,
             config: None,
             node: None,
+            registry: None,
+            placement: None,
             phantom: PhantomData,
         }
     }
 
     /// Links the to be spawned process to the parent.
+    ///
+    /// Links to processes started on other nodes only break when the node
+    /// goes down, see [`Process::link`].
     pub fn link(self) -> AbstractProcessBuilder<'a, T> {
         AbstractProcessBuilder {
             link: Some(Tag::new()),
             config: self.config,
             node: self.node,
+            registry: self.registry,
+            placement: self.placement,
             phantom: PhantomData,
         }
     }
@@ -50,6 +61,8 @@ where
             link: Some(tag),
             config: self.config,
             node: self.node,
+            registry: self.registry,
+            placement: self.placement,
             phantom: PhantomData,
         }
     }
@@ -60,6 +73,8 @@ where
             link: self.link,
             config: Some(config),
             node: self.node,
+            registry: self.registry,
+            placement: self.placement,
             phantom: PhantomData,
         }
     }
@@ -70,6 +85,38 @@ where
             link: self.link,
             config: self.config,
             node: Some(node),
+            registry: self.registry,
+            placement: None,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Chooses the node on which the process will be spawned with
+    /// `placement`.
+    ///
+    /// The node is chosen again each time a process is started. If no node is
+    /// available, starting the process fails with
+    /// [`StartupError::NoNodeAvailable`].
+    pub fn on_nodes(self, placement: Placement) -> AbstractProcessBuilder<'a, T> {
+        AbstractProcessBuilder {
+            link: self.link,
+            config: self.config,
+            node: None,
+            registry: self.registry,
+            placement: Some(placement),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Uses `registry` instead of the built-in registry to register the name
+    /// when starting the process with [`start_as`](Self::start_as).
+    pub fn via(self, registry: &'a dyn Registry) -> AbstractProcessBuilder<'a, T> {
+        AbstractProcessBuilder {
+            link: self.link,
+            config: self.config,
+            node: self.node,
+            registry: Some(registry),
+            placement: self.placement,
             phantom: PhantomData,
         }
     }
@@ -85,9 +132,25 @@ where
         let init_tag = Tag::new();
         let this = unsafe { Process::<Result<(), StartupError<T>>, T::Serializer>::this() };
         let entry_data = (this, init_tag, arg);
-        let process = match (self.link, &self.config, self.node) {
-            (Some(_), _, Some(_node)) => {
-                unimplemented!("Linking across nodes is not supported yet");
+        let node = self.target_node()?;
+        let process = match (self.link, &self.config, node) {
+            (Some(tag), config, Some(node)) => {
+                let process = match config {
+                    Some(config) => Process::<(), T::Serializer>::spawn_node_config(
+                        node,
+                        config,
+                        entry_data,
+                        lifecycles::entry::<T>,
+                    ),
+                    None => Process::<(), T::Serializer>::spawn_node(
+                        node,
+                        entry_data,
+                        lifecycles::entry::<T>,
+                    ),
+                };
+                // The host only links local processes, see `Process::link`.
+                monitor::link(tag, node, process.id());
+                process
             }
             (Some(tag), Some(config), None) => Process::<(), T::Serializer>::spawn_link_config_tag(
                 config,
@@ -123,8 +186,19 @@ where
         let mailbox: Mailbox<Result<(), StartupError<T>>, T::Serializer> =
             unsafe { Mailbox::new() };
         match mailbox.tag_receive(&[init_tag]) {
-            Ok(()) => Ok(ProcessRef { process }),
-            Err(err) => Err(err),
+            Ok(()) => {
+                if self.placement.is_some() {
+                    placement::track(process.node_id(), process.id());
+                }
+                Ok(ProcessRef { process })
+            }
+            Err(err) => {
+                // The process is gone, but a remote link would stay around.
+                if let (Some(_), Some(node)) = (self.link, node) {
+                    monitor::unlink(node, process.id());
+                }
+                Err(err)
+            }
         }
     }
 
@@ -141,6 +215,9 @@ where
     /// If used in combination with the [`on_node`](Self::on_node) option, the
     /// name registration will be performed on the local node and not the remote
     /// one.
+    ///
+    /// If used in combination with the [`via`](Self::via) option, the name is
+    /// registered in the given registry once `init` finishes.
     #[track_caller]
     pub fn start_as<N: ProcessName>(
         &self,
@@ -149,12 +226,32 @@ where
     ) -> Result<ProcessRef<T>, StartupError<T>> {
         let name: &str = name.process_name();
         let name = process_name::<T, T::Serializer>(ProcessType::ProcessRef, name);
+        if let Some(registry) = self.registry {
+            return self.start_via(registry, &name, arg);
+        }
         let init_tag = Tag::new();
         let this = unsafe { Process::<Result<(), StartupError<T>>, T::Serializer>::this() };
         let entry_data = (this, init_tag, arg);
-        let process = match (self.link, &self.config, self.node) {
-            (Some(_), _, Some(_node)) => {
-                unimplemented!("Linking across nodes is not supported yet");
+        let node = self.target_node()?;
+        let process = match (self.link, &self.config, node) {
+            (Some(tag), config, Some(node)) => {
+                let process = match config {
+                    Some(config) => Process::<(), T::Serializer>::name_spawn_node_config(
+                        &name,
+                        node,
+                        config,
+                        entry_data,
+                        lifecycles::entry::<T>,
+                    ),
+                    None => Process::<(), T::Serializer>::name_spawn_node(
+                        &name,
+                        node,
+                        entry_data,
+                        lifecycles::entry::<T>,
+                    ),
+                };
+                // The host only links local processes, see `Process::link`.
+                process.inspect(|process| monitor::link(tag, node, process.id()))
             }
             (Some(tag), Some(config), None) => {
                 Process::<(), T::Serializer>::name_spawn_link_config_tag(
@@ -212,8 +309,62 @@ where
         let mailbox: Mailbox<Result<(), StartupError<T>>, T::Serializer> =
             unsafe { Mailbox::new() };
         match mailbox.tag_receive(&[init_tag]) {
-            Ok(()) => Ok(ProcessRef { process }),
-            Err(err) => Err(err),
+            Ok(()) => {
+                if self.placement.is_some() {
+                    placement::track(process.node_id(), process.id());
+                }
+                registry::watch(&name, process.node_id(), process.id());
+                Ok(ProcessRef { process })
+            }
+            Err(err) => {
+                // The process is gone, but a remote link would stay around.
+                if let (Some(_), Some(node)) = (self.link, node) {
+                    monitor::unlink(node, process.id());
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Returns the node to spawn the process on, `None` for the local node.
+    fn target_node(&self) -> Result<Option<u64>, StartupError<T>> {
+        let placement = match &self.placement {
+            Some(placement) => placement,
+            None => return Ok(self.node),
+        };
+        match placement.select_node() {
+            // Spawning locally also allows linking.
+            Some(node) if node == host::node_id() => Ok(None),
+            Some(node) => Ok(Some(node)),
+            None => Err(StartupError::NoNodeAvailable),
+        }
+    }
+
+    #[track_caller]
+    fn start_via(
+        &self,
+        registry: &dyn Registry,
+        name: &str,
+        arg: T::Arg,
+    ) -> Result<ProcessRef<T>, StartupError<T>> {
+        if let Some((node_id, process_id)) = registry.lookup(name) {
+            return Err(StartupError::NameAlreadyRegistered(unsafe {
+                ProcessRef::new(node_id, process_id)
+            }));
+        }
+        let process = self.start(arg)?;
+        match registry.register(name, process.node_id(), process.id()) {
+            Ok(()) => Ok(process),
+            Err((node_id, process_id)) => {
+                // Another process was registered while this one was starting.
+                if self.link.is_some() {
+                    process.unlink();
+                }
+                process.kill();
+                Err(StartupError::NameAlreadyRegistered(unsafe {
+                    ProcessRef::new(node_id, process_id)
+                }))
+            }
         }
     }
 }
@@ -4,7 +4,8 @@ use std::any::{type_name, TypeId};
 use std::marker::PhantomData;
 
 use super::messages::RequestMessage;
-use super::{AbstractProcess, DeferredRequestHandler, MessageHandler, RequestHandler};
+use super::tag::AbstractProcessTag;
+use super::{AbstractProcess, DeferredRequestHandler, MessageHandler, Priority, RequestHandler};
 use crate::serializer::CanSerialize;
 use crate::Tag;
 
@@ -13,6 +14,9 @@ pub struct Request<T>(PhantomData<T>);
 pub struct DeferredRequest<T>(PhantomData<T>);
 
 pub trait Handler<AP: AbstractProcess> {
+    /// Priority of the handler.
+    const PRIORITY: Priority = Priority::Normal;
+
     fn handle(response_tag: Tag, state: &mut AP::State);
 }
 
@@ -21,6 +25,8 @@ where
     AP: MessageHandler<T>,
     AP::Serializer: CanSerialize<T>,
 {
+    const PRIORITY: Priority = <AP as MessageHandler<T>>::PRIORITY;
+
     fn handle(_: Tag, state: &mut <AP as AbstractProcess>::State) {
         let state = super::State { state };
         let message = AP::Serializer::decode().unwrap();
@@ -35,6 +41,8 @@ where
     AP::Serializer: CanSerialize<AP::Response>,
     AP::Serializer: CanSerialize<RequestMessage<T, AP::Response, AP::Serializer>>,
 {
+    const PRIORITY: Priority = <AP as RequestHandler<T>>::PRIORITY;
+
     fn handle(response_tag: Tag, state: &mut <AP as AbstractProcess>::State) {
         let state = super::State { state };
         let request: RequestMessage<T, AP::Response, AP::Serializer> =
@@ -51,6 +59,8 @@ where
     AP::Serializer: CanSerialize<AP::Response>,
     AP::Serializer: CanSerialize<RequestMessage<T, AP::Response, AP::Serializer>>,
 {
+    const PRIORITY: Priority = <AP as DeferredRequestHandler<T>>::PRIORITY;
+
     fn handle(response_tag: Tag, state: &mut <AP as AbstractProcess>::State) {
         let state = super::State { state };
         let request: RequestMessage<T, AP::Response, AP::Serializer> =
@@ -68,6 +78,8 @@ where
 
 pub trait Handlers<AP: AbstractProcess> {
     fn handler_id<Handler: 'static>() -> u8;
+    fn priority(id: u8) -> Priority;
+    fn priority_tags() -> Vec<Tag>;
     fn handle(response_tag: Tag, id: u8, state: &mut AP::State);
 }
 
@@ -110,6 +122,25 @@ mod macros {
                     }
                 }
 
+                #[allow(unused_variables)]
+                fn priority(id: u8) -> Priority {
+                    match id {
+                        $($i => $args::PRIORITY,)*
+                        _ => Priority::Normal,
+                    }
+                }
+
+                #[allow(unused_mut)]
+                fn priority_tags() -> Vec<Tag> {
+                    let mut tags = Vec::new();
+                    $(
+                        if $args::PRIORITY == Priority::High {
+                            tags.push(AbstractProcessTag::priority_u6($i));
+                        }
+                    )*
+                    tags
+                }
+
                 #[allow(unused_variables)]
                 fn handle(response_tag: Tag, id: u8, state: &mut <AP as AbstractProcess>::State) {
                     match id {
@@ -7,7 +7,8 @@ use super::handlers::Handlers;
 use super::messages::{ShutdownMessage, SHUTDOWN_HANDLER};
 use super::tag::AbstractProcessTag;
 use super::{AbstractProcess, Config, StartupError};
-use crate::mailbox::LINK_DIED;
+use crate::distributed::monitor;
+use crate::mailbox::{LINK_DIED, PROCESS_DIED, TIMEOUT};
 use crate::panic::{catch_panic, Panicked};
 use crate::serializer::CanSerialize;
 use crate::{host, Mailbox, Process, Tag};
@@ -61,15 +62,43 @@ fn startup<AP: AbstractProcess>(arg: AP::Arg) -> Result<AP::State, StartupError<
 
 /// Extracts the handler out of the tag for each incoming message, until
 /// shutdown message is received.
+///
+/// Messages for high priority handlers are looked up first, before waiting on
+/// any other message.
 fn loop_and_handle<AP: AbstractProcess>(state: &mut AP::State) -> Tag {
+    let priority_tags: Vec<i64> = AP::Handlers::priority_tags()
+        .iter()
+        .map(|tag| tag.id())
+        .collect();
     loop {
-        // Wait for next message & handle link died if result matches constant.
-        if unsafe { host::api::message::receive(null(), 0, u64::MAX) } == LINK_DIED {
+        // Check if a high priority message is waiting, without blocking.
+        if !priority_tags.is_empty() {
+            let message_type = unsafe {
+                host::api::message::receive(priority_tags.as_ptr(), priority_tags.len(), 0)
+            };
+            if message_type != TIMEOUT {
+                let tag = unsafe { host::api::message::get_tag() };
+                let (response_tag, data) = AbstractProcessTag::extract_u6_data(Tag::from(tag));
+                AP::Handlers::handle(response_tag, data, state);
+                continue;
+            }
+        }
+
+        // Wait for next message & handle link or monitored process deaths if result
+        // matches constants.
+        let message_type = unsafe { host::api::message::receive(null(), 0, u64::MAX) };
+        if message_type == LINK_DIED {
             let tag = unsafe { host::api::message::get_tag() };
             let tag = Tag::from(tag);
             AP::handle_link_death(super::State { state }, tag);
             continue;
         }
+        if message_type == PROCESS_DIED {
+            let process_id = unsafe { host::api::message::get_process_id() };
+            let process_id = monitor::dead_process_id(process_id);
+            AP::handle_process_death(super::State { state }, process_id);
+            continue;
+        }
 
         // Extract `data` from tag
         let tag = unsafe { host::api::message::get_tag() };
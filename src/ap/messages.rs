@@ -7,6 +7,9 @@ use crate::{Process, Tag};
 #[serde(bound = "")]
 pub(crate) struct ReturnAddress<Response, Serializer> {
     process: Process<Response, Serializer>,
+    // Messages sent to high priority handlers don't carry an unique tag, so
+    // the response tag needs to be sent together with the return address.
+    tag: Option<Tag>,
 }
 
 impl<Response, Serializer> ReturnAddress<Response, Serializer>
@@ -15,14 +18,25 @@ where
 {
     pub(crate) fn from_self() -> Self {
         let process = unsafe { Process::this() };
-        ReturnAddress { process }
+        ReturnAddress { process, tag: None }
+    }
+
+    /// Creates a return address that will always respond with `tag`.
+    pub(crate) fn from_self_with_tag(tag: Tag) -> Self {
+        let process = unsafe { Process::this() };
+        ReturnAddress {
+            process,
+            tag: Some(tag),
+        }
     }
 
     /// Sends response back to a process.
     ///
     /// The tag should be provided by the sender and should be extracted from
-    /// the incoming message.
+    /// the incoming message. If the return address was created with a tag, it
+    /// will be used instead.
     pub(crate) fn send_response(self, response: Response, tag: Tag) {
+        let tag = self.tag.unwrap_or(tag);
         self.process.tag_send(tag, response);
     }
 }
@@ -18,9 +18,12 @@ use self::builder::AbstractProcessBuilder;
 use self::handlers::{DeferredRequest, Handlers, Message, Request};
 use self::messages::{RequestMessage, ReturnAddress, ShutdownMessage, SHUTDOWN_HANDLER};
 use self::tag::AbstractProcessTag;
+use crate::distributed::global::{self, Resolver};
+use crate::distributed::{monitor, Placement};
 use crate::function::process::{process_name, ProcessType};
 use crate::mailbox::{MailboxError, MessageSignal};
 use crate::protocol::ProtocolCapture;
+use crate::registry::{self, Metadata, Registry};
 use crate::serializer::CanSerialize;
 use crate::time::{Timeout, TimerRef, WithDelay, WithTimeout};
 use crate::{host, MailboxResult, Process, ProcessConfig, ProcessName, Tag};
@@ -91,6 +94,24 @@ use crate::{host, MailboxResult, Process, ProcessConfig, ProcessName, Tag};
 /// }
 /// ```
 ///
+/// ### Priorities
+///
+/// Handlers can set their [`Priority`] to [`Priority::High`]. Messages and
+/// requests for high priority handlers are always processed before any other
+/// messages waiting in the mailbox. This is useful for control messages (e.g.
+/// health checks or configuration reloads) that shouldn't wait behind a long
+/// queue of regular messages.
+///
+/// ```rust
+/// impl RequestHandler<HealthCheck> for Counter {
+///     const PRIORITY: Priority = Priority::High;
+///     type Response = bool;
+///     fn handle(_: State<Self>, _: HealthCheck) -> Self::Response {
+///         true
+///     }
+/// }
+/// ```
+///
 /// _It is not enough just to define the handlers, they also need to be
 /// associated with the `AbstractProcess` using the [`Self::Handlers`] type:_
 ///
@@ -185,6 +206,11 @@ where
     /// This function will be called if another linked process dies.
     fn handle_link_death(_state: State<Self>, _tag: Tag) {}
 
+    /// This function will be called if a monitored process dies.
+    ///
+    /// Processes can be monitored with [`State::monitor`].
+    fn handle_process_death(_state: State<Self>, _process_id: u64) {}
+
     /// Starts a new `AbstractProcess` and returns a reference to it.
     ///
     /// This call will block until the `init` function finishes. If the `init`
@@ -236,6 +262,16 @@ where
     fn on_node(node: u64) -> AbstractProcessBuilder<'static, Self> {
         AbstractProcessBuilder::new().on_node(node)
     }
+
+    /// Chooses the node on which the process will be spawned with `placement`.
+    fn on_nodes(placement: Placement) -> AbstractProcessBuilder<'static, Self> {
+        AbstractProcessBuilder::new().on_nodes(placement)
+    }
+
+    /// Registers the process started with `start_as` in a custom `registry`.
+    fn via(registry: &dyn Registry) -> AbstractProcessBuilder<'_, Self> {
+        AbstractProcessBuilder::new().via(registry)
+    }
 }
 
 /// [`AbstractProcess`] startup configuration.
@@ -277,10 +313,26 @@ impl<AP: AbstractProcess> Config<AP> {
     }
 }
 
+/// Priority of a handler.
+///
+/// Messages for [`Priority::High`] handlers are taken out of the mailbox
+/// before all other messages, even if they arrived later.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Priority {
+    /// Messages are handled in the order they arrived.
+    #[default]
+    Normal,
+    /// Messages are handled before all normal priority messages.
+    High,
+}
+
 pub trait MessageHandler<Message>: AbstractProcess
 where
     Self::Serializer: CanSerialize<Message>,
 {
+    /// Priority of the handler.
+    const PRIORITY: Priority = Priority::Normal;
+
     fn handle(state: State<Self>, message: Message);
 }
 
@@ -289,6 +341,9 @@ where
     Self::Serializer: CanSerialize<Request>,
     Self::Serializer: CanSerialize<Self::Response>,
 {
+    /// Priority of the handler.
+    const PRIORITY: Priority = Priority::Normal;
+
     type Response;
 
     fn handle(state: State<Self>, request: Request) -> Self::Response;
@@ -299,6 +354,9 @@ where
     Self::Serializer: CanSerialize<Request>,
     Self::Serializer: CanSerialize<Self::Response>,
 {
+    /// Priority of the handler.
+    const PRIORITY: Priority = Priority::Normal;
+
     type Response;
 
     fn handle(
@@ -319,6 +377,21 @@ impl<'a, AP: AbstractProcess> State<'a, AP> {
         let process = unsafe { Process::this() };
         ProcessRef { process }
     }
+
+    /// Starts monitoring a process.
+    ///
+    /// When the process dies, the
+    /// [`handle_process_death`](AbstractProcess::handle_process_death) handler
+    /// will be called. For processes on other nodes, it's only called when
+    /// the node goes down.
+    pub fn monitor<M, S>(&self, process: Process<M, S>) {
+        monitor::monitor(process.node_id(), process.id());
+    }
+
+    /// Stop monitoring a process.
+    pub fn stop_monitoring<M, S>(&self, process: Process<M, S>) {
+        monitor::stop_monitoring(process.node_id(), process.id());
+    }
 }
 
 impl<'a, AP: AbstractProcess> Deref for State<'a, AP> {
@@ -375,7 +448,7 @@ where
     T: AbstractProcess,
 {
     /// Construct a process from a raw ID.
-    unsafe fn new(node_id: u64, process_id: u64) -> Self {
+    pub(crate) unsafe fn new(node_id: u64, process_id: u64) -> Self {
         let process = Process::new(node_id, process_id);
         ProcessRef { process }
     }
@@ -405,10 +478,123 @@ where
         }
     }
 
+    /// Returns a process registered under `name` in `registry` if it exists
+    /// and the signature matches.
+    pub fn lookup_via<N: ProcessName + ?Sized>(registry: &dyn Registry, name: &N) -> Option<Self> {
+        let name = process_name::<T, T::Serializer>(ProcessType::ProcessRef, name.process_name());
+        let (node_id, id) = registry.lookup(&name)?;
+        unsafe { Some(Self::new(node_id, id)) }
+    }
+
+    /// Registers process under `name` in `registry`.
+    ///
+    /// If another process is already registered under `name`, it returns
+    /// `Err(process)` with the existing process.
+    pub fn register_via<N: ProcessName + ?Sized>(
+        &self,
+        registry: &dyn Registry,
+        name: &N,
+    ) -> Result<(), Self> {
+        let name = process_name::<T, T::Serializer>(ProcessType::ProcessRef, name.process_name());
+        registry
+            .register(&name, self.node_id(), self.id())
+            .map_err(|(node_id, id)| unsafe { Self::new(node_id, id) })
+    }
+
+    /// Registers process under `name` on all connected nodes.
+    ///
+    /// If another process is already registered under `name` on any
+    /// reachable node, it returns `Err(process)` with the existing process.
+    /// See [`distributed::global`](crate::distributed::global) for details.
+    pub fn register_global<N: ProcessName + ?Sized>(&self, name: &N) -> Result<(), Self> {
+        self.register_global_with(name, Resolver::default())
+    }
+
+    /// Same as [`register_global`](Self::register_global), but uses
+    /// `resolver` to resolve conflicts after a network partition.
+    pub fn register_global_with<N: ProcessName + ?Sized>(
+        &self,
+        name: &N,
+        resolver: Resolver,
+    ) -> Result<(), Self> {
+        let key = process_name::<T, T::Serializer>(ProcessType::ProcessRef, name.process_name());
+        global::register(key, self.node_id(), self.id(), resolver)
+            .map_err(|(node_id, id)| unsafe { Self::new(node_id, id) })
+    }
+
+    /// Returns a process registered under `name` with
+    /// [`register_global`](Self::register_global), if it exists and the
+    /// signature matches.
+    pub fn lookup_global<N: ProcessName + ?Sized>(name: &N) -> Option<Self> {
+        let key = process_name::<T, T::Serializer>(ProcessType::ProcessRef, name.process_name());
+        let entry = global::lookup(key)?;
+        Some(unsafe { Self::new(entry.node_id(), entry.process_id()) })
+    }
+
+    /// Removes the process registered under `name` from the global registry.
+    ///
+    /// Returns the removed process, if one was registered.
+    pub fn unregister_global<N: ProcessName + ?Sized>(name: &N) -> Option<Self> {
+        let key = process_name::<T, T::Serializer>(ProcessType::ProcessRef, name.process_name());
+        let entry = global::unregister(key)?;
+        Some(unsafe { Self::new(entry.node_id(), entry.process_id()) })
+    }
+
     /// Registers process under `name`.
+    ///
+    /// The name is removed from the registry when the process dies.
     pub fn register<N: ProcessName>(&self, name: &N) {
         let name = process_name::<T, T::Serializer>(ProcessType::ProcessRef, name.process_name());
-        unsafe { host::api::registry::put(name.as_ptr(), name.len(), self.node_id(), self.id()) };
+        registry::register_plain(&name, self.node_id(), self.id());
+    }
+
+    /// Registers process under `name`, together with `metadata`.
+    ///
+    /// The metadata can be retrieved with
+    /// [`lookup_with_metadata`](Self::lookup_with_metadata) or
+    /// [`registry::list`](crate::registry::list).
+    pub fn register_with_metadata<N: ProcessName>(&self, name: &N, metadata: Metadata) {
+        let name = name.process_name();
+        let key = process_name::<T, T::Serializer>(ProcessType::ProcessRef, name);
+        registry::register(name, key, self.node_id(), self.id(), metadata);
+    }
+
+    /// Removes the process registered under `name` from the registry.
+    ///
+    /// Returns the removed process, if one was registered.
+    pub fn unregister<N: ProcessName + ?Sized>(name: &N) -> Option<Self> {
+        let key = process_name::<T, T::Serializer>(ProcessType::ProcessRef, name.process_name());
+        let (node_id, id) = registry::unregister(&key)?;
+        Some(unsafe { Self::new(node_id, id) })
+    }
+
+    /// Waits until a process is registered under `name` and returns it.
+    ///
+    /// Returns immediately if the process is already registered. If no
+    /// process is registered before the `timeout` expires, `Err(Timeout)` is
+    /// returned.
+    ///
+    /// Processes started with [`start_as`](AbstractProcess::start_as) are
+    /// registered when they are spawned and can be returned while their `init`
+    /// function is still running. Requests sent to them are handled once
+    /// `init` finished.
+    pub fn await_registered<N: ProcessName + ?Sized>(
+        name: &N,
+        timeout: Duration,
+    ) -> Result<Self, Timeout> {
+        let key = process_name::<T, T::Serializer>(ProcessType::ProcessRef, name.process_name());
+        let (node_id, id) = registry::await_registered(&key, timeout)?;
+        Ok(unsafe { Self::new(node_id, id) })
+    }
+
+    /// Returns a process registered under `name` together with its metadata,
+    /// if it exists and the signature matches.
+    ///
+    /// The metadata is empty if the process was registered without it.
+    pub fn lookup_with_metadata<N: ProcessName + ?Sized>(name: &N) -> Option<(Self, Metadata)> {
+        let key = process_name::<T, T::Serializer>(ProcessType::ProcessRef, name.process_name());
+        let ((node_id, id), metadata) = registry::lookup_with_metadata(key)?;
+        Some((unsafe { Self::new(node_id, id) }, metadata))
     }
 
     /// Returns `true` for processes on the local node that are running.
@@ -430,13 +616,16 @@ where
     }
 
     /// Link process to the one currently running with tag.
+    ///
+    /// Links to processes on other nodes only break when the node goes down,
+    /// see [`monitor_nodes`](crate::distributed::monitor_nodes).
     pub fn link_with(&self, tag: Tag) {
-        unsafe { host::api::process::link(tag.id(), self.process.id()) };
+        monitor::link(tag, self.process.node_id(), self.process.id());
     }
 
     /// Unlink processes from the caller.
     pub fn unlink(&self) {
-        unsafe { host::api::process::unlink(self.process.id()) };
+        monitor::unlink(self.process.node_id(), self.process.id());
     }
 
     /// Kill process
@@ -490,8 +679,7 @@ where
     where
         T::Serializer: CanSerialize<M>,
     {
-        let handler_id = T::Handlers::handler_id::<Message<M>>();
-        let tag = AbstractProcessTag::from_u6(handler_id);
+        let (tag, _) = Self::handler_tags::<Message<M>>();
         // Cast into the right type for sending.
         let process: Process<M, T::Serializer> = unsafe { std::mem::transmute(self.process) };
         process.tag_send(tag, message);
@@ -503,8 +691,7 @@ where
     where
         T::Serializer: CanSerialize<M>,
     {
-        let handler_id = T::Handlers::handler_id::<Message<M>>();
-        let tag = AbstractProcessTag::from_u6(handler_id);
+        let (tag, _) = Self::handler_tags::<Message<M>>();
         // Cast into the right type for sending.
         let process: Process<M, T::Serializer> = unsafe { std::mem::transmute(self.process) };
         process.tag_send_after(tag, message, duration)
@@ -538,11 +725,9 @@ where
         T::Serializer: CanSerialize<T::Response>,
         T::Serializer: CanSerialize<RequestMessage<R, T::Response, T::Serializer>>,
     {
-        let return_address = ReturnAddress::from_self();
+        let (send_tag, receive_tag) = Self::handler_tags::<Request<R>>();
+        let return_address = ReturnAddress::from_self_with_tag(receive_tag);
         let message = RequestMessage(request, return_address);
-        let handler_id = T::Handlers::handler_id::<Request<R>>();
-        let send_tag = AbstractProcessTag::from_u6(handler_id);
-        let (receive_tag, _) = AbstractProcessTag::extract_u6_data(send_tag);
         unsafe {
             // Cast into the right type for sending.
             let process: Process<RequestMessage<R, T::Response, T::Serializer>, T::Serializer> =
@@ -583,11 +768,9 @@ where
         T::Serializer: CanSerialize<T::Response>,
         T::Serializer: CanSerialize<RequestMessage<R, T::Response, T::Serializer>>,
     {
-        let return_address = ReturnAddress::from_self();
+        let (send_tag, receive_tag) = Self::handler_tags::<DeferredRequest<R>>();
+        let return_address = ReturnAddress::from_self_with_tag(receive_tag);
         let message = RequestMessage(request, return_address);
-        let handler_id = T::Handlers::handler_id::<DeferredRequest<R>>();
-        let send_tag = AbstractProcessTag::from_u6(handler_id);
-        let (receive_tag, _) = AbstractProcessTag::extract_u6_data(send_tag);
         unsafe {
             // Cast into the right type for sending.
             let process: Process<RequestMessage<R, T::Response, T::Serializer>, T::Serializer> =
@@ -600,6 +783,23 @@ where
         }
     }
 
+    /// Returns the tag used to send a message to the handler `H` and the tag
+    /// the response is going to be received with.
+    #[track_caller]
+    fn handler_tags<H: 'static>() -> (Tag, Tag) {
+        let handler_id = T::Handlers::handler_id::<H>();
+        match T::Handlers::priority(handler_id) {
+            Priority::Normal => {
+                let send_tag = AbstractProcessTag::from_u6(handler_id);
+                let (receive_tag, _) = AbstractProcessTag::extract_u6_data(send_tag);
+                (send_tag, receive_tag)
+            }
+            // All high priority messages for one handler share the same tag,
+            // so the response needs a separate unique one.
+            Priority::High => (AbstractProcessTag::priority_u6(handler_id), Tag::new()),
+        }
+    }
+
     /// Set a timeout on the next action performed on this process.
     ///
     /// Timeouts affect [`ProcessRef::shutdown`], [`ProcessRef::request`] and
@@ -662,6 +862,9 @@ pub enum StartupError<AP: AbstractProcess> {
     NameAlreadyRegistered(ProcessRef<AP>),
     /// A custom error.
     Custom(AP::StartupError),
+    /// The placement strategy of [`on_nodes`](AbstractProcess::on_nodes)
+    /// didn't find a node to start the process on.
+    NoNodeAvailable,
 }
 
 impl<AP: AbstractProcess> Debug for StartupError<AP>
@@ -675,6 +878,7 @@ where
                 f.debug_tuple("NameAlreadyRegistered").field(arg0).finish()
             }
             Self::Custom(arg0) => f.debug_tuple("Custom").field(arg0).finish(),
+            Self::NoNodeAvailable => write!(f, "NoNodeAvailable"),
         }
     }
 }
@@ -688,6 +892,7 @@ where
             Self::InitPanicked => Self::InitPanicked,
             Self::NameAlreadyRegistered(arg0) => Self::NameAlreadyRegistered(*arg0),
             Self::Custom(arg0) => Self::Custom(arg0.clone()),
+            Self::NoNodeAvailable => Self::NoNodeAvailable,
         }
     }
 }
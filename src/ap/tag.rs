@@ -1,11 +1,16 @@
 use crate::Tag;
 
+/// Bit marking tags of messages sent to high priority handlers.
+const PRIORITY_BIT: i64 = 1 << 62;
+
 /// Unique tags that also hold additional `u6` data used to dispatch to the
 /// correct handler function.
 ///
 /// The reason only `u6` is used is that the first 2 bits are reserved for
-/// future use cases. `AbstractProcesses` can have at most 16 handler functions
-/// and this should be enough space to encode all of them.
+/// other use cases. The second bit marks messages for high priority handlers
+/// and the first one is reserved for the future. `AbstractProcesses` can have
+/// at most 16 handler functions and this should be enough space to encode all
+/// of them.
 pub(crate) struct AbstractProcessTag;
 
 impl AbstractProcessTag {
@@ -18,11 +23,23 @@ impl AbstractProcessTag {
         Tag::from(id)
     }
 
+    /// Returns the [`Tag`] used for all messages sent to the high priority
+    /// handler `data`.
+    ///
+    /// Contrary to [`from_u6`](Self::from_u6), the tag is not unique. This
+    /// allows the receiver to look for high priority messages with a tag
+    /// filtered receive.
+    #[track_caller]
+    pub(crate) fn priority_u6(data: u8) -> Tag {
+        assert!(data < 64, "Only values less than 64 can fit into a `u6`");
+        Tag::from(PRIORITY_BIT | ((data as i64) << 56))
+    }
+
     /// Extracts `u6` data encoded into the [`Tag`].
     ///
     /// The returned `Tag` doesn't contain the data anymore.
     pub(crate) fn extract_u6_data(tag: Tag) -> (Tag, u8) {
-        let data = (tag.id() >> 56) as u8; // extract data
+        let data = ((tag.id() >> 56) & 0x3F) as u8; // extract data
         let tag = tag.id() & 0xFFFFFFFFFFFFFF; // remove data from first byte
         (Tag::from(tag), data)
     }
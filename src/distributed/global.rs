@@ -0,0 +1,493 @@
+//! Cluster-wide process registry.
+//!
+//! Names registered with
+//! [`Process::register_global`](crate::Process::register_global) or
+//! [`ProcessRef::register_global`](crate::ap::ProcessRef::register_global) are
+//! unique across all connected nodes. Each node keeps a copy of the registry,
+//! so lookups don't need to contact other nodes.
+//!
+//! Registering a name is only successful if the name is free on all
+//! reachable nodes. During a network partition, the same name can still end
+//! up registered on both sides. Once the partition heals, the registries are
+//! merged and the [`Resolver`] picked during registration decides which of the
+//! conflicting processes keeps the name. The other processes are killed.
+//!
+//! Entries are removed once the registered process dies or its node
+//! disconnects. While the registry has entries, it periodically checks for
+//! new nodes and exchanges entries with them. Nodes that couldn't be reached
+//! during a registration are synchronized the same way.
+
+use std::collections::{BTreeSet, HashMap};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use super::rpc::{self, RpcError};
+use crate::ap::handlers::{Message, Request};
+use crate::ap::{
+    AbstractProcess, Config, MessageHandler, ProcessRef, RequestHandler, StartupError, State,
+};
+use crate::function::FuncRef;
+use crate::serializer::Bincode;
+use crate::{host, Mailbox, Process};
+
+/// Name under which the global registry server of each node is registered.
+const SERVER_NAME: &str = "lunatic::global";
+
+/// How often the server checks for new or disconnected nodes while it has
+/// entries.
+const SYNC_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long to wait on other nodes to respond.
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A function resolving a name conflict, see [`Resolver::Custom`].
+pub type ResolveFn = fn(String, GlobalEntry, GlobalEntry) -> Option<GlobalEntry>;
+
+/// Decides which process keeps a name if it was registered on multiple nodes
+/// during a network partition.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub enum Resolver {
+    /// The process that registered the name first keeps it.
+    #[default]
+    KeepOldest,
+    /// Both processes are killed and the name is freed.
+    KillBoth,
+    /// Calls the function with the name and both entries. The returned entry
+    /// keeps the name. If `None` is returned, both processes are killed.
+    ///
+    /// The function is called on each node involved in the conflict, so it
+    /// needs to return the same result independent of the argument order.
+    Custom(FuncRef<ResolveFn>),
+}
+
+impl Resolver {
+    /// Creates a [`Resolver::Custom`] calling `resolve`.
+    pub fn custom(resolve: ResolveFn) -> Self {
+        Resolver::Custom(FuncRef::new(resolve))
+    }
+
+    fn resolve(&self, name: &str, a: &GlobalEntry, b: &GlobalEntry) -> Option<GlobalEntry> {
+        match self {
+            Resolver::KeepOldest => {
+                let age =
+                    |entry: &GlobalEntry| (entry.registered_at, entry.node_id, entry.process_id);
+                Some(if age(a) <= age(b) {
+                    a.clone()
+                } else {
+                    b.clone()
+                })
+            }
+            Resolver::KillBoth => None,
+            Resolver::Custom(resolve) => resolve(name.to_owned(), a.clone(), b.clone()),
+        }
+    }
+}
+
+/// An entry in the global registry.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GlobalEntry {
+    node_id: u64,
+    process_id: u64,
+    /// Milliseconds since the UNIX epoch.
+    registered_at: u64,
+    resolver: Resolver,
+}
+
+impl GlobalEntry {
+    /// Returns the node ID of the registered process.
+    pub fn node_id(&self) -> u64 {
+        self.node_id
+    }
+
+    /// Returns the process ID of the registered process.
+    pub fn process_id(&self) -> u64 {
+        self.process_id
+    }
+
+    /// Returns when the process was registered, in milliseconds since the
+    /// UNIX epoch.
+    pub fn registered_at(&self) -> u64 {
+        self.registered_at
+    }
+
+    fn is(&self, other: &GlobalEntry) -> bool {
+        (self.node_id, self.process_id) == (other.node_id, other.process_id)
+    }
+}
+
+/// Registers the process under `key` on all reachable nodes.
+///
+/// If the name is already taken on any node, the registration is rolled back
+/// and the existing process is returned.
+pub(crate) fn register(
+    key: String,
+    node_id: u64,
+    process_id: u64,
+    resolver: Resolver,
+) -> Result<(), (u64, u64)> {
+    let registered_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_millis() as u64)
+        .unwrap_or_default();
+    let entry = GlobalEntry {
+        node_id,
+        process_id,
+        registered_at,
+        resolver,
+    };
+
+    let mut inserted = Vec::new();
+    let mut conflict = None;
+    for node in all_nodes() {
+        match on_node(node, (key.clone(), entry.clone()), |(key, entry)| {
+            server().request(Insert(key, entry))
+        }) {
+            Ok(Ok(())) => inserted.push(node),
+            Ok(Err(existing)) => {
+                conflict = Some(existing);
+                break;
+            }
+            // Retry with a full synchronization, in case the node is still
+            // connected.
+            Err(_) => server().send(Resync(node)),
+        }
+    }
+
+    match conflict {
+        None => Ok(()),
+        Some(existing) => {
+            for node in inserted {
+                let _ = on_node(node, (key.clone(), entry.clone()), |(key, entry)| {
+                    server().request(Remove(key, entry))
+                });
+            }
+            Err((existing.node_id, existing.process_id))
+        }
+    }
+}
+
+/// Returns the entry registered under `key`.
+pub(crate) fn lookup(key: String) -> Option<GlobalEntry> {
+    server().request(Lookup(key))
+}
+
+/// Removes `key` from the registry on all reachable nodes.
+pub(crate) fn unregister(key: String) -> Option<GlobalEntry> {
+    let entry = lookup(key.clone())?;
+    broadcast_remove(key, entry.clone());
+    Some(entry)
+}
+
+/// Removes `key` from the registry on all reachable nodes, if it's still
+/// registered to the process.
+///
+/// Used to drop the entry of a process known to be dead before the registry
+/// noticed it.
+pub(crate) fn remove(key: String, node_id: u64, process_id: u64) {
+    if let Some(entry) = lookup(key.clone()) {
+        if (entry.node_id, entry.process_id) == (node_id, process_id) {
+            broadcast_remove(key, entry);
+        }
+    }
+}
+
+/// Removes the entry from the registries of all reachable nodes.
+fn broadcast_remove(key: String, entry: GlobalEntry) {
+    for node in all_nodes() {
+        let _ = on_node(node, (key.clone(), entry.clone()), |(key, entry)| {
+            server().request(Remove(key, entry))
+        });
+    }
+}
+
+/// Runs `function` on the node `node_id`, directly if it's the local node.
+fn on_node<C, R>(node_id: u64, capture: C, function: fn(C) -> R) -> Result<R, RpcError>
+where
+    C: Serialize + for<'de> Deserialize<'de>,
+    R: Serialize + for<'de> Deserialize<'de>,
+{
+    if node_id == host::node_id() {
+        Ok(function(capture))
+    } else {
+        rpc::call(node_id, capture, function, TIMEOUT)
+    }
+}
+
+/// Returns all connected nodes, including the local one.
+fn all_nodes() -> BTreeSet<u64> {
+    let mut nodes: BTreeSet<u64> = super::nodes().into_iter().collect();
+    nodes.insert(host::node_id());
+    nodes
+}
+
+/// Returns the global registry server of this node, starting it if
+/// necessary.
+fn server() -> ProcessRef<GlobalRegistry> {
+    if let Some(server) = ProcessRef::lookup(SERVER_NAME) {
+        return server;
+    }
+    match GlobalRegistry::start_as(&SERVER_NAME, ()) {
+        // Another process could have started the server in the meantime.
+        Ok(server) | Err(StartupError::NameAlreadyRegistered(server)) => server,
+        Err(err) => panic!("failed to start the global registry server: {err:?}"),
+    }
+}
+
+/// The global registry server, holding this node's copy of the registry.
+struct GlobalRegistry;
+
+struct GlobalRegistryState {
+    entries: HashMap<String, GlobalEntry>,
+    /// Remote nodes the registry was synchronized with.
+    nodes: BTreeSet<u64>,
+    /// Whether a [`Sync`] message is scheduled.
+    syncing: bool,
+}
+
+impl AbstractProcess for GlobalRegistry {
+    type State = GlobalRegistryState;
+    type Serializer = Bincode;
+    type Arg = ();
+    type Handlers = (
+        Message<Sync>,
+        Message<Resync>,
+        Request<Insert>,
+        Request<Remove>,
+        Request<Lookup>,
+        Request<Entries>,
+        Request<Merge>,
+    );
+    type StartupError = ();
+
+    fn init(_: Config<Self>, _: ()) -> Result<Self::State, ()> {
+        Ok(GlobalRegistryState {
+            entries: HashMap::new(),
+            nodes: BTreeSet::new(),
+            syncing: false,
+        })
+    }
+
+    fn handle_process_death(mut state: State<Self>, process_id: u64) {
+        let node_id = host::node_id();
+        let dead: Vec<(String, GlobalEntry)> = state
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.node_id == node_id && entry.process_id == process_id)
+            .map(|(key, entry)| (key.clone(), entry.clone()))
+            .collect();
+        for (key, entry) in dead {
+            state.entries.remove(&key);
+            // Remove the entry on other nodes without blocking the server.
+            Process::spawn((key, entry), |(key, entry), _: Mailbox<()>| {
+                broadcast_remove(key, entry)
+            });
+        }
+    }
+}
+
+fn monitored(entries: &HashMap<String, GlobalEntry>, process_id: u64) -> bool {
+    let node_id = host::node_id();
+    entries
+        .values()
+        .any(|entry| entry.node_id == node_id && entry.process_id == process_id)
+}
+
+/// Adds the entry and monitors the process if it's local.
+fn insert(state: &mut State<GlobalRegistry>, key: String, entry: GlobalEntry) {
+    if entry.node_id == host::node_id() && !monitored(&state.entries, entry.process_id) {
+        state.monitor(unsafe { Process::<()>::new(entry.node_id, entry.process_id) });
+    }
+    if let Some(replaced) = state.entries.insert(key, entry) {
+        stop_monitoring_if_unused(state, &replaced);
+    }
+    start_syncing(state);
+}
+
+/// Schedules a [`Sync`] if none is scheduled yet.
+fn start_syncing(state: &mut State<GlobalRegistry>) {
+    if !state.syncing {
+        state.syncing = true;
+        state.self_ref().send(Sync);
+    }
+}
+
+fn stop_monitoring_if_unused(state: &State<GlobalRegistry>, entry: &GlobalEntry) {
+    if entry.node_id == host::node_id() && !monitored(&state.entries, entry.process_id) {
+        state.stop_monitoring(unsafe { Process::<()>::new(entry.node_id, entry.process_id) });
+    }
+}
+
+/// Kills the process if it's running on this node.
+fn kill_if_local(entry: &GlobalEntry) {
+    if entry.node_id == host::node_id() {
+        unsafe { host::api::process::kill(entry.process_id) };
+    }
+}
+
+/// Checks for new and disconnected nodes.
+///
+/// New nodes get a copy of this node's entries and their entries are merged
+/// into this node's registry.
+#[derive(Serialize, Deserialize)]
+struct Sync;
+
+impl MessageHandler<Sync> for GlobalRegistry {
+    fn handle(mut state: State<Self>, _: Sync) {
+        let this_node = host::node_id();
+        let nodes: BTreeSet<u64> = super::nodes()
+            .into_iter()
+            .filter(|node| *node != this_node)
+            .collect();
+
+        // Processes on disconnected nodes are considered dead.
+        let disconnected: BTreeSet<u64> = state.nodes.difference(&nodes).copied().collect();
+        state
+            .entries
+            .retain(|_, entry| !disconnected.contains(&entry.node_id));
+
+        // Exchange entries with new nodes without blocking the server.
+        let entries: Vec<(String, GlobalEntry)> = state
+            .entries
+            .iter()
+            .map(|(key, entry)| (key.clone(), entry.clone()))
+            .collect();
+        for node in nodes.difference(&state.nodes) {
+            Process::spawn(
+                (state.self_ref(), *node, entries.clone()),
+                |(registry, node, entries), _: Mailbox<()>| {
+                    let merged = rpc::call(node, entries, |e| server().request(Merge(e)), TIMEOUT);
+                    match rpc::call(node, (), |_| server().request(Entries), TIMEOUT) {
+                        Ok(entries) if merged.is_ok() => registry.request(Merge(entries)),
+                        // Try again during the next synchronization.
+                        _ => registry.send(Resync(node)),
+                    }
+                },
+            );
+        }
+        state.nodes = nodes;
+
+        // Stop syncing once there is nothing to synchronize.
+        if state.entries.is_empty() {
+            state.syncing = false;
+        } else {
+            state.self_ref().with_delay(SYNC_INTERVAL).send(Sync);
+        }
+    }
+}
+
+/// Synchronizes with the node again during the next [`Sync`].
+#[derive(Serialize, Deserialize)]
+struct Resync(u64);
+
+impl MessageHandler<Resync> for GlobalRegistry {
+    fn handle(mut state: State<Self>, Resync(node): Resync) {
+        state.nodes.remove(&node);
+        start_syncing(&mut state);
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Insert(String, GlobalEntry);
+
+impl RequestHandler<Insert> for GlobalRegistry {
+    type Response = Result<(), GlobalEntry>;
+
+    fn handle(mut state: State<Self>, Insert(key, entry): Insert) -> Result<(), GlobalEntry> {
+        if let Some(existing) = state.entries.get(&key) {
+            if !existing.is(&entry) {
+                return Err(existing.clone());
+            }
+        }
+        insert(&mut state, key, entry);
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Remove(String, GlobalEntry);
+
+impl RequestHandler<Remove> for GlobalRegistry {
+    type Response = ();
+
+    fn handle(mut state: State<Self>, Remove(key, entry): Remove) {
+        if state.entries.get(&key).is_some_and(|e| e.is(&entry)) {
+            if let Some(removed) = state.entries.remove(&key) {
+                stop_monitoring_if_unused(&state, &removed);
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Lookup(String);
+
+impl RequestHandler<Lookup> for GlobalRegistry {
+    type Response = Option<GlobalEntry>;
+
+    fn handle(state: State<Self>, Lookup(key): Lookup) -> Option<GlobalEntry> {
+        state.entries.get(&key).cloned()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entries;
+
+impl RequestHandler<Entries> for GlobalRegistry {
+    type Response = Vec<(String, GlobalEntry)>;
+
+    fn handle(state: State<Self>, _: Entries) -> Vec<(String, GlobalEntry)> {
+        state
+            .entries
+            .iter()
+            .map(|(key, entry)| (key.clone(), entry.clone()))
+            .collect()
+    }
+}
+
+/// Merges the entries of another node, resolving conflicts.
+#[derive(Serialize, Deserialize)]
+struct Merge(Vec<(String, GlobalEntry)>);
+
+impl RequestHandler<Merge> for GlobalRegistry {
+    type Response = ();
+
+    fn handle(mut state: State<Self>, Merge(entries): Merge) {
+        let this_node = host::node_id();
+        for (key, remote) in entries {
+            // Local entries of the other node could be outdated.
+            if remote.node_id == this_node {
+                continue;
+            }
+            let local = match state.entries.get(&key) {
+                Some(local) if local.is(&remote) => continue,
+                Some(local) => local.clone(),
+                None => {
+                    insert(&mut state, key, remote);
+                    continue;
+                }
+            };
+            // Both sides of the conflict use the resolver of the older entry.
+            let resolver =
+                if (local.registered_at, local.node_id) <= (remote.registered_at, remote.node_id) {
+                    local.resolver
+                } else {
+                    remote.resolver
+                };
+            match resolver.resolve(&key, &local, &remote) {
+                // The other node kills its own processes.
+                Some(winner) if winner.is(&local) => {}
+                Some(winner) => {
+                    kill_if_local(&local);
+                    insert(&mut state, key, winner);
+                }
+                None => {
+                    kill_if_local(&local);
+                    if let Some(removed) = state.entries.remove(&key) {
+                        stop_monitoring_if_unused(&state, &removed);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,3 +1,15 @@
+pub mod global;
+pub(crate) mod monitor;
+pub(crate) mod placement;
+mod query;
+pub mod rpc;
+mod singleton;
+
+pub use monitor::{monitor_nodes, NodeEvent, NodeMonitor};
+pub use placement::Placement;
+pub use query::{node_info, NodeInfo, NodeQuery};
+pub use singleton::Singleton;
+
 use crate::host::api::distributed::{
     copy_lookup_nodes_results, exec_lookup_nodes, get_nodes, module_id, nodes_count,
 };
@@ -21,7 +33,8 @@ pub fn nodes() -> Vec<u64> {
 /// ids.
 ///
 /// Query is defined like an URL Query string, e.g. `name=node01&group=workers`.
-pub fn lookup_nodes(query: &'static str) -> Result<Vec<u64>, LunaticError> {
+/// Queries can also be built with [`NodeQuery`].
+pub fn lookup_nodes(query: &str) -> Result<Vec<u64>, LunaticError> {
     let mut query_id = 0;
     let mut nodes_len = 0;
     let mut error_id = 0;
@@ -0,0 +1,416 @@
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ap::handlers::{Message, Request};
+use crate::ap::{
+    AbstractProcess, Config, MessageHandler, ProcessRef, RequestHandler, StartupError, State,
+};
+use crate::serializer::Bincode;
+use crate::time::Timeout;
+use crate::{host, process_local, Mailbox, Process, Tag};
+
+/// Name under which the node monitor server of each node is registered.
+const SERVER_NAME: &str = "lunatic::node_monitor";
+
+/// How often the node monitor server checks for connected nodes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A change in the set of connected nodes.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeEvent {
+    /// A node with this ID connected.
+    NodeUp(u64),
+    /// A node with this ID disconnected.
+    NodeDown(u64),
+}
+
+/// A subscription to [`NodeEvent`]s, created with [`monitor_nodes`].
+///
+/// Events are delivered to the mailbox of the process that created the
+/// subscription, tagged with a unique [`Tag`], so they don't interfere with
+/// other messages. Dropping the `NodeMonitor` ends the subscription.
+#[derive(Debug)]
+pub struct NodeMonitor {
+    tag: Tag,
+}
+
+/// Starts delivering [`NodeEvent`]s to the current process whenever a node
+/// joins or leaves the cluster.
+///
+/// Only changes after the call are reported, [`nodes`](super::nodes) can be
+/// used to get the currently connected nodes. The set of nodes is checked
+/// periodically, so events can arrive with a short delay.
+///
+/// Processes running on a node that went down can be considered dead once
+/// [`NodeEvent::NodeDown`] is received. Links and monitors of such processes
+/// fire at the same time, see [`Process::link`].
+///
+/// # Example
+///
+/// ```no_run
+/// use lunatic::distributed::{monitor_nodes, NodeEvent};
+///
+/// let monitor = monitor_nodes();
+/// loop {
+///     match monitor.receive() {
+///         NodeEvent::NodeUp(node) => println!("node {node} joined"),
+///         NodeEvent::NodeDown(node) => println!("node {node} left"),
+///     }
+/// }
+/// ```
+pub fn monitor_nodes() -> NodeMonitor {
+    let tag = Tag::new();
+    server().request(Subscribe(host::process_id(), tag));
+    NodeMonitor { tag }
+}
+
+impl NodeMonitor {
+    /// Waits for the next event.
+    pub fn receive(&self) -> NodeEvent {
+        // Temporarily cast to right mailbox type.
+        let mailbox: Mailbox<NodeEvent> = unsafe { Mailbox::new() };
+        mailbox.tag_receive(&[self.tag])
+    }
+
+    /// Waits for the next event, returning `Err(Timeout)` if none arrives
+    /// before the `timeout` expires.
+    pub fn receive_timeout(&self, timeout: Duration) -> Result<NodeEvent, Timeout> {
+        // Temporarily cast to right mailbox type.
+        let mailbox: Mailbox<NodeEvent> = unsafe { Mailbox::new() };
+        mailbox
+            .tag_receive_timeout(&[self.tag], timeout)
+            .map_err(|_| Timeout)
+    }
+
+    /// Returns the tag that events of this subscription are sent with.
+    pub fn tag(&self) -> Tag {
+        self.tag
+    }
+}
+
+impl Drop for NodeMonitor {
+    fn drop(&mut self) {
+        server().request(Unsubscribe(host::process_id(), self.tag));
+    }
+}
+
+/// Links the current process to a process, which can be on another node.
+///
+/// The host only links local processes. A process on another node is
+/// represented by a local proxy process that the current process is linked
+/// to. The node monitor server kills the proxy when the node goes down, so
+/// that the link fires like a local one.
+pub(crate) fn link(tag: Tag, node_id: u64, process_id: u64) {
+    if node_id == host::node_id() {
+        unsafe { host::api::process::link(tag.id(), process_id) };
+        return;
+    }
+    // Like local links, linking twice replaces the tag.
+    match find_proxy(ProxyKind::Link, node_id, process_id) {
+        Some(proxy) => unsafe { host::api::process::link(tag.id(), proxy) },
+        None => {
+            let proxy = spawn_proxy(ProxyKind::Link, node_id, process_id);
+            unsafe { host::api::process::link(tag.id(), proxy) };
+            server().request(AddProxy(proxy, node_id));
+        }
+    }
+}
+
+/// Removes a link created with [`link`].
+pub(crate) fn unlink(node_id: u64, process_id: u64) {
+    if node_id == host::node_id() {
+        unsafe { host::api::process::unlink(process_id) };
+    } else if let Some(proxy) = remove_proxy(ProxyKind::Link, node_id, process_id) {
+        unsafe { host::api::process::unlink(proxy) };
+        stop_proxy(proxy);
+    }
+}
+
+/// Monitors a process from the current process, which can be on another
+/// node.
+///
+/// Like with links, a process on another node is represented by a local proxy
+/// process that is monitored instead. [`dead_process_id`] translates the
+/// proxy back to the remote process once it dies.
+pub(crate) fn monitor(node_id: u64, process_id: u64) {
+    if node_id == host::node_id() {
+        unsafe { host::api::process::monitor(process_id) };
+    } else if find_proxy(ProxyKind::Monitor, node_id, process_id).is_none() {
+        let proxy = spawn_proxy(ProxyKind::Monitor, node_id, process_id);
+        unsafe { host::api::process::monitor(proxy) };
+        server().request(AddProxy(proxy, node_id));
+    }
+}
+
+/// Removes a monitor created with [`monitor`].
+pub(crate) fn stop_monitoring(node_id: u64, process_id: u64) {
+    if node_id == host::node_id() {
+        unsafe { host::api::process::stop_monitoring(process_id) };
+    } else if let Some(proxy) = remove_proxy(ProxyKind::Monitor, node_id, process_id) {
+        unsafe { host::api::process::stop_monitoring(proxy) };
+        stop_proxy(proxy);
+    }
+}
+
+/// Returns the ID of a monitored process that died, given the ID the host
+/// reported.
+///
+/// If the process was on another node, the host reports its proxy.
+pub(crate) fn dead_process_id(process_id: u64) -> u64 {
+    PROXIES
+        .with(|proxies| proxies.borrow_mut().remove(&process_id))
+        .map(|proxy| proxy.process_id)
+        .unwrap_or(process_id)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProxyKind {
+    Link,
+    Monitor,
+}
+
+/// A remote process the current process is linked to or monitors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Proxy {
+    kind: ProxyKind,
+    node_id: u64,
+    process_id: u64,
+}
+
+process_local! {
+    /// Proxies of the current process by their process ID.
+    static PROXIES: RefCell<HashMap<u64, Proxy>> = RefCell::new(HashMap::new());
+}
+
+fn find_proxy(kind: ProxyKind, node_id: u64, process_id: u64) -> Option<u64> {
+    let remote = Proxy {
+        kind,
+        node_id,
+        process_id,
+    };
+    PROXIES.with(|proxies| {
+        proxies
+            .borrow()
+            .iter()
+            .find(|(_, proxy)| **proxy == remote)
+            .map(|(id, _)| *id)
+    })
+}
+
+fn remove_proxy(kind: ProxyKind, node_id: u64, process_id: u64) -> Option<u64> {
+    let proxy = find_proxy(kind, node_id, process_id)?;
+    PROXIES.with(|proxies| proxies.borrow_mut().remove(&proxy));
+    Some(proxy)
+}
+
+/// Spawns a proxy for the remote process that lives as long as the current
+/// process, until it's stopped or killed.
+fn spawn_proxy(kind: ProxyKind, node_id: u64, process_id: u64) -> u64 {
+    let proxy = Process::spawn(host::process_id(), run_proxy).id();
+    let remote = Proxy {
+        kind,
+        node_id,
+        process_id,
+    };
+    PROXIES.with(|proxies| proxies.borrow_mut().insert(proxy, remote));
+    proxy
+}
+
+/// Lets a proxy finish normally, without firing the link or monitor.
+fn stop_proxy(proxy: u64) {
+    unsafe { Process::<()>::new(host::node_id(), proxy) }.send(());
+}
+
+/// Entry function of proxies, which finish once stopped or once the process
+/// that spawned them died.
+fn run_proxy(owner: u64, mailbox: Mailbox<()>) {
+    let mailbox = mailbox.monitorable();
+    mailbox.monitor(unsafe { Process::<()>::new(host::node_id(), owner) });
+    mailbox.receive();
+}
+
+/// Returns the node monitor server of this node, starting it if necessary.
+fn server() -> ProcessRef<NodeMonitors> {
+    if let Some(server) = ProcessRef::lookup(SERVER_NAME) {
+        return server;
+    }
+    match NodeMonitors::start_as(&SERVER_NAME, ()) {
+        // Another process could have started the server in the meantime.
+        Ok(server) | Err(StartupError::NameAlreadyRegistered(server)) => server,
+        Err(err) => panic!("failed to start the node monitor server: {err:?}"),
+    }
+}
+
+/// Returns all connected nodes, except the local one.
+fn remote_nodes() -> BTreeSet<u64> {
+    let this_node = host::node_id();
+    super::nodes()
+        .into_iter()
+        .filter(|node| *node != this_node)
+        .collect()
+}
+
+/// The node monitor server, notifying subscribers about node changes and
+/// killing proxies of processes on nodes that went down.
+struct NodeMonitors;
+
+struct NodeMonitorsState {
+    nodes: BTreeSet<u64>,
+    /// Whether a [`Poll`] message is scheduled.
+    polling: bool,
+    /// Subscribed local processes and the tags of their subscriptions.
+    subscribers: Vec<(u64, Tag)>,
+    /// Running proxies and the nodes of the processes they represent.
+    proxies: Vec<(u64, u64)>,
+}
+
+impl NodeMonitorsState {
+    /// Returns `true` if the local process is subscribed or a proxy.
+    fn watches(&self, process_id: u64) -> bool {
+        self.subscribers.iter().any(|(id, _)| *id == process_id)
+            || self.proxies.iter().any(|(id, _)| *id == process_id)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.subscribers.is_empty() && self.proxies.is_empty()
+    }
+}
+
+impl AbstractProcess for NodeMonitors {
+    type State = NodeMonitorsState;
+    type Serializer = Bincode;
+    type Arg = ();
+    type Handlers = (
+        Message<Poll>,
+        Request<Subscribe>,
+        Request<Unsubscribe>,
+        Request<AddProxy>,
+    );
+    type StartupError = ();
+
+    fn init(_: Config<Self>, _: ()) -> Result<Self::State, ()> {
+        Ok(NodeMonitorsState {
+            nodes: BTreeSet::new(),
+            polling: false,
+            subscribers: Vec::new(),
+            proxies: Vec::new(),
+        })
+    }
+
+    fn handle_process_death(mut state: State<Self>, process_id: u64) {
+        state.subscribers.retain(|(id, _)| *id != process_id);
+        state.proxies.retain(|(id, _)| *id != process_id);
+    }
+}
+
+/// Starts watching `process_id` and polling for node changes, if not done
+/// already.
+fn watch(state: &mut State<NodeMonitors>, process_id: u64) {
+    if !state.watches(process_id) {
+        state.monitor(unsafe { Process::<()>::new(host::node_id(), process_id) });
+    }
+    if !state.polling {
+        // Only changes from now on are reported.
+        state.nodes = remote_nodes();
+        state.polling = true;
+        state.self_ref().with_delay(POLL_INTERVAL).send(Poll);
+    }
+}
+
+/// Stops watching `process_id` if it doesn't need to be watched anymore.
+fn unwatch(state: &State<NodeMonitors>, process_id: u64) {
+    if !state.watches(process_id) {
+        state.stop_monitoring(unsafe { Process::<()>::new(host::node_id(), process_id) });
+    }
+}
+
+/// Kills all proxies of processes on the node that went down, so that the host
+/// fires their links and monitors.
+fn node_down(state: &mut State<NodeMonitors>, node: u64) {
+    state.proxies.retain(|&(proxy, node_id)| {
+        if node_id == node {
+            unsafe { host::api::process::kill(proxy) };
+        }
+        node_id != node
+    });
+}
+
+#[derive(Serialize, Deserialize)]
+struct Poll;
+
+impl MessageHandler<Poll> for NodeMonitors {
+    fn handle(mut state: State<Self>, _: Poll) {
+        let nodes = remote_nodes();
+        let up = nodes
+            .difference(&state.nodes)
+            .map(|n| NodeEvent::NodeUp(*n));
+        let down = state
+            .nodes
+            .difference(&nodes)
+            .map(|n| NodeEvent::NodeDown(*n));
+        let events: Vec<NodeEvent> = up.chain(down).collect();
+        for event in events {
+            for (process_id, tag) in state.subscribers.iter() {
+                let subscriber = unsafe { Process::<NodeEvent>::new(host::node_id(), *process_id) };
+                subscriber.tag_send(*tag, event);
+            }
+            if let NodeEvent::NodeDown(node) = event {
+                node_down(&mut state, node);
+            }
+        }
+        state.nodes = nodes;
+        // Stop polling once nobody is interested in node changes.
+        if state.is_empty() {
+            state.polling = false;
+        } else {
+            state.self_ref().with_delay(POLL_INTERVAL).send(Poll);
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Subscribe(u64, Tag);
+
+impl RequestHandler<Subscribe> for NodeMonitors {
+    type Response = ();
+
+    fn handle(mut state: State<Self>, Subscribe(process_id, tag): Subscribe) {
+        watch(&mut state, process_id);
+        state.subscribers.push((process_id, tag));
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Unsubscribe(u64, Tag);
+
+impl RequestHandler<Unsubscribe> for NodeMonitors {
+    type Response = ();
+
+    fn handle(mut state: State<Self>, Unsubscribe(process_id, tag): Unsubscribe) {
+        state
+            .subscribers
+            .retain(|subscriber| *subscriber != (process_id, tag));
+        unwatch(&state, process_id);
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct AddProxy(u64, u64);
+
+impl RequestHandler<AddProxy> for NodeMonitors {
+    type Response = ();
+
+    fn handle(mut state: State<Self>, AddProxy(proxy, node_id): AddProxy) {
+        watch(&mut state, proxy);
+        // Like for local processes, links and monitors of processes that are
+        // already gone fire right away.
+        if !remote_nodes().contains(&node_id) {
+            unsafe { host::api::process::kill(proxy) };
+            return;
+        }
+        state.proxies.push((proxy, node_id));
+    }
+}
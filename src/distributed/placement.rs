@@ -0,0 +1,181 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashSet;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::{rpc, NodeQuery};
+use crate::ap::handlers::Request;
+use crate::ap::{AbstractProcess, Config, ProcessRef, RequestHandler, StartupError, State};
+use crate::serializer::Bincode;
+use crate::{host, Process};
+
+/// Name under which the load server of each node is registered.
+const SERVER_NAME: &str = "lunatic::node_load";
+
+/// How long to wait for other nodes to report their load.
+const LOAD_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Index of the next node used by [`Placement::RoundRobin`] in this process.
+static NEXT_NODE: AtomicUsize = AtomicUsize::new(0);
+
+/// Strategy used to pick the node a process is started on.
+///
+/// It's used with [`AbstractProcess::on_nodes`] to let the node be chosen
+/// automatically each time a process is started.
+///
+/// # Example
+///
+/// ```no_run
+/// use lunatic::distributed::Placement;
+/// # use lunatic::ap::{AbstractProcess, Config};
+/// # struct Worker;
+/// # impl AbstractProcess for Worker {
+/// #     type State = ();
+/// #     type Serializer = lunatic::serializer::Bincode;
+/// #     type Arg = ();
+/// #     type Handlers = ();
+/// #     type StartupError = ();
+/// #     fn init(_: Config<Self>, _: ()) -> Result<(), ()> { Ok(()) }
+/// # }
+///
+/// let workers: Vec<_> = (0..10)
+///     .map(|_| Worker::on_nodes(Placement::RoundRobin).start(()).unwrap())
+///     .collect();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Placement {
+    /// Cycles through all connected nodes, including the local one.
+    RoundRobin,
+    /// Picks a random node out of all connected nodes, including the local
+    /// one.
+    Random,
+    /// Picks the node with the fewest running processes started with
+    /// [`on_nodes`](AbstractProcess::on_nodes).
+    ///
+    /// Nodes that don't report their load in time are skipped.
+    LeastLoaded,
+    /// Picks a random node out of the nodes matching the query.
+    Query(NodeQuery),
+}
+
+impl Placement {
+    /// Returns the node to start the next process on, or `None` if no node
+    /// is available.
+    pub(crate) fn select_node(&self) -> Option<u64> {
+        match self {
+            Placement::RoundRobin => {
+                let nodes = all_nodes();
+                let next = NEXT_NODE.fetch_add(1, Ordering::Relaxed);
+                nodes.get(next % nodes.len()).copied()
+            }
+            Placement::Random => pick_random(&all_nodes()),
+            Placement::LeastLoaded => {
+                let nodes = all_nodes();
+                let loads = rpc::multicall(&nodes, (), |_| load(), LOAD_TIMEOUT);
+                nodes
+                    .into_iter()
+                    .zip(loads)
+                    .filter_map(|(node, load)| Some((load.ok()?, node)))
+                    .min()
+                    .map(|(_, node)| node)
+            }
+            Placement::Query(query) => pick_random(&query.lookup().ok()?),
+        }
+    }
+}
+
+/// Starts counting the process towards the load of its node, until it dies.
+pub(crate) fn track(node_id: u64, process_id: u64) {
+    if node_id == host::node_id() {
+        server().request(Track(process_id));
+    } else {
+        // The load is only an estimate, a failed update can be ignored.
+        let _ = rpc::call(
+            node_id,
+            process_id,
+            |process_id| server().request(Track(process_id)),
+            LOAD_TIMEOUT,
+        );
+    }
+}
+
+/// Returns all connected nodes, including the local one, ordered by ID.
+fn all_nodes() -> Vec<u64> {
+    let mut nodes = super::nodes();
+    nodes.push(host::node_id());
+    nodes.sort_unstable();
+    nodes.dedup();
+    nodes
+}
+
+fn pick_random(nodes: &[u64]) -> Option<u64> {
+    if nodes.is_empty() {
+        return None;
+    }
+    // `RandomState` is seeded from the host's source of randomness.
+    let random = RandomState::new().build_hasher().finish() as usize;
+    Some(nodes[random % nodes.len()])
+}
+
+/// Returns the number of tracked processes on this node.
+fn load() -> usize {
+    server().request(Load)
+}
+
+/// Returns the load server of this node, starting it if necessary.
+fn server() -> ProcessRef<NodeLoad> {
+    if let Some(server) = ProcessRef::lookup(SERVER_NAME) {
+        return server;
+    }
+    match NodeLoad::start_as(&SERVER_NAME, ()) {
+        // Another process could have started the server in the meantime.
+        Ok(server) | Err(StartupError::NameAlreadyRegistered(server)) => server,
+        Err(err) => panic!("failed to start the load server: {err:?}"),
+    }
+}
+
+/// The load server, keeping track of running processes on a node.
+struct NodeLoad;
+
+impl AbstractProcess for NodeLoad {
+    type State = HashSet<u64>;
+    type Serializer = Bincode;
+    type Arg = ();
+    type Handlers = (Request<Track>, Request<Load>);
+    type StartupError = ();
+
+    fn init(_: Config<Self>, _: ()) -> Result<Self::State, ()> {
+        Ok(HashSet::new())
+    }
+
+    fn handle_process_death(mut state: State<Self>, process_id: u64) {
+        state.remove(&process_id);
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Track(u64);
+
+impl RequestHandler<Track> for NodeLoad {
+    type Response = ();
+
+    fn handle(mut state: State<Self>, Track(process_id): Track) {
+        if state.insert(process_id) {
+            state.monitor(unsafe { Process::<()>::new(host::node_id(), process_id) });
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Load;
+
+impl RequestHandler<Load> for NodeLoad {
+    type Response = usize;
+
+    fn handle(state: State<Self>, _: Load) -> usize {
+        state.len()
+    }
+}
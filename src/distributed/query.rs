@@ -0,0 +1,182 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::host::api::distributed::{copy_node_info_results, exec_node_info};
+use crate::http::parse::percent_decode;
+use crate::LunaticError;
+
+/// A query selecting nodes by their attributes.
+///
+/// A node matches the query if it matches all keys. For keys with multiple
+/// values, any of the values needs to match. Keys and values are
+/// percent-encoded, so they can contain any characters.
+///
+/// # Example
+///
+/// ```no_run
+/// use lunatic::distributed::NodeQuery;
+///
+/// let region = "eu".to_owned();
+/// let nodes = NodeQuery::new()
+///     .attribute("role", "worker")
+///     .any_of("region", [region, "us".to_owned()])
+///     .lookup()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NodeQuery {
+    attributes: Vec<(String, Vec<String>)>,
+}
+
+impl NodeQuery {
+    /// Creates a query matching all nodes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only matches nodes where the attribute `key` is `value`.
+    #[must_use]
+    pub fn attribute(self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.any_of(key, [value.into()])
+    }
+
+    /// Only matches nodes where the attribute `key` is one of the `values`.
+    #[must_use]
+    pub fn any_of<I, V>(mut self, key: impl Into<String>, values: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<String>,
+    {
+        let key = key.into();
+        let values = values.into_iter().map(Into::into);
+        match self.attributes.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, existing)) => existing.extend(values),
+            None => self.attributes.push((key, values.collect())),
+        }
+        self
+    }
+
+    /// Returns the IDs of all nodes matching the query, ordered by ID.
+    ///
+    /// The control node is asked once for each value, so the number of host
+    /// calls grows linearly with the number of values.
+    pub fn lookup(&self) -> Result<Vec<u64>, LunaticError> {
+        if self.attributes.is_empty() {
+            let mut nodes = super::lookup_nodes("")?;
+            nodes.sort_unstable();
+            nodes.dedup();
+            return Ok(nodes);
+        }
+        let mut matching: Option<BTreeSet<u64>> = None;
+        for (key, values) in self.attributes.iter() {
+            // Any of the values can match.
+            let mut any = BTreeSet::new();
+            for value in values {
+                let query = format!("{}={}", encode(key), encode(value));
+                any.extend(super::lookup_nodes(&query)?);
+            }
+            // All of the keys need to match.
+            let nodes = match matching {
+                Some(nodes) => nodes.intersection(&any).copied().collect(),
+                None => any,
+            };
+            if nodes.is_empty() {
+                return Ok(Vec::new());
+            }
+            matching = Some(nodes);
+        }
+        Ok(matching.unwrap_or_default().into_iter().collect())
+    }
+}
+
+/// Formats the query as an URL query string.
+///
+/// If a key has multiple values, it's repeated for each of them. As with
+/// [`NodeQuery::lookup`], a repeated key matches any of its values, while
+/// different keys all need to match.
+impl fmt::Display for NodeQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut separator = "";
+        for (key, values) in self.attributes.iter() {
+            for value in values {
+                write!(f, "{separator}{}={}", encode(key), encode(value))?;
+                separator = "&";
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Information about a node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeInfo {
+    id: u64,
+    attributes: BTreeMap<String, String>,
+}
+
+impl NodeInfo {
+    /// Returns the ID of the node.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns all attributes of the node.
+    pub fn attributes(&self) -> &BTreeMap<String, String> {
+        &self.attributes
+    }
+
+    /// Returns the value of the attribute `key`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.attributes.get(key).map(String::as_str)
+    }
+}
+
+/// Returns the attributes of the node `node_id`, as known to the control
+/// node.
+pub fn node_info(node_id: u64) -> Result<NodeInfo, LunaticError> {
+    let mut info_id = 0;
+    let mut info_len = 0;
+    let mut error_id = 0;
+    let result = unsafe { exec_node_info(node_id, &mut info_id, &mut info_len, &mut error_id) };
+    if result == 1 {
+        return Err(LunaticError::Error(error_id));
+    }
+    let mut info = vec![0; info_len as usize];
+    let copied_len =
+        unsafe { copy_node_info_results(info_id, info.as_mut_ptr(), info_len, &mut error_id) };
+    if copied_len < 0 {
+        return Err(LunaticError::Error(error_id));
+    }
+    info.truncate(copied_len as usize);
+    // Attributes are encoded as a query string, where `+` stands for a space.
+    let attributes = String::from_utf8_lossy(&info)
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (decode(key), decode(value))
+        })
+        .collect();
+    Ok(NodeInfo {
+        id: node_id,
+        attributes,
+    })
+}
+
+/// Percent-encodes all characters except unreserved ones.
+fn encode(input: &str) -> String {
+    let mut encoded = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+fn decode(input: &str) -> String {
+    percent_decode(&input.replace('+', " "))
+}
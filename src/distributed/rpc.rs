@@ -0,0 +1,219 @@
+//! Remote procedure calls on other nodes.
+//!
+//! [`call`] runs a function on a node and waits for its result, [`multicall`]
+//! does the same for multiple nodes in parallel. Each call is executed in a
+//! new process on the target node. The capture and the result are sent
+//! between the nodes with the [`Bincode`] serializer, or any other serializer
+//! when using [`call_with`] and [`multicall_with`].
+//!
+//! A panic inside of the function is returned as [`RpcError::Panicked`] and a
+//! missing result as [`RpcError::TimedOut`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use lunatic::distributed::{self, rpc};
+//!
+//! let sum = rpc::call(1, (2, 3), |(a, b)| a + b, Duration::from_secs(1)).unwrap();
+//! assert_eq!(sum, 5);
+//!
+//! let nodes = distributed::nodes();
+//! let hosts = rpc::multicall(&nodes, (), |_| distributed::node_id(), Duration::from_secs(1));
+//! ```
+
+use std::time::{Duration, Instant};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::function::process::IntoProcess;
+use crate::function::FuncRef;
+use crate::host::api::message;
+use crate::mailbox::MailboxError;
+use crate::panic::catch_panic;
+use crate::protocol::ProtocolCapture;
+use crate::serializer::{Bincode, CanSerialize, DecodeError};
+use crate::{LunaticError, Mailbox, Process, Tag};
+
+/// Data sent to the process executing the call.
+type Capture<C, R, S> = (Process<Option<R>, S>, Tag, C, FuncRef<fn(C) -> R>);
+
+/// Error returned when a remote call fails.
+#[derive(Error, Debug)]
+pub enum RpcError {
+    /// The process executing the call couldn't be spawned on the node.
+    #[error("spawning on the node failed: {0}")]
+    SpawnFailed(#[source] LunaticError),
+    /// The function panicked.
+    #[error("remote function panicked")]
+    Panicked,
+    /// No result arrived before the timeout expired.
+    #[error("timed out")]
+    TimedOut,
+    /// Result failed to be deserialized.
+    #[error("deserialization failed: {0}")]
+    DeserializationFailed(#[from] DecodeError),
+}
+
+impl RpcError {
+    /// Returns true if the error is a [`RpcError::TimedOut`].
+    pub fn is_timed_out(&self) -> bool {
+        matches!(self, RpcError::TimedOut)
+    }
+
+    /// Returns true if the error is a [`RpcError::Panicked`].
+    pub fn is_panicked(&self) -> bool {
+        matches!(self, RpcError::Panicked)
+    }
+}
+
+/// Runs `function` with `capture` on the node `node_id` and returns its
+/// result.
+///
+/// If the result doesn't arrive before the `timeout` expires,
+/// `Err(RpcError::TimedOut)` is returned. The remote process is not stopped in
+/// this case.
+pub fn call<C, R>(
+    node_id: u64,
+    capture: C,
+    function: fn(C) -> R,
+    timeout: Duration,
+) -> Result<R, RpcError>
+where
+    C: Serialize + DeserializeOwned,
+    R: Serialize + DeserializeOwned,
+{
+    call_with::<C, R, Bincode>(node_id, capture, function, timeout)
+}
+
+/// Runs `function` with `capture` on all `nodes` in parallel and returns
+/// their results, in the same order as `nodes`.
+///
+/// All calls share the same `timeout`.
+pub fn multicall<C, R>(
+    nodes: &[u64],
+    capture: C,
+    function: fn(C) -> R,
+    timeout: Duration,
+) -> Vec<Result<R, RpcError>>
+where
+    C: Serialize + DeserializeOwned + Clone,
+    R: Serialize + DeserializeOwned,
+{
+    multicall_with::<C, R, Bincode>(nodes, capture, function, timeout)
+}
+
+/// Same as [`call`], but uses the serializer `S`.
+pub fn call_with<C, R, S>(
+    node_id: u64,
+    capture: C,
+    function: fn(C) -> R,
+    timeout: Duration,
+) -> Result<R, RpcError>
+where
+    S: CanSerialize<()>
+        + CanSerialize<Option<R>>
+        + CanSerialize<Capture<C, R, S>>
+        + CanSerialize<ProtocolCapture<Capture<C, R, S>>>,
+{
+    let tag = Tag::new();
+    spawn::<C, R, S>(node_id, tag, capture, function)?;
+    // Temporarily cast to right mailbox type.
+    let mailbox: Mailbox<Option<R>, S> = unsafe { Mailbox::new() };
+    into_result(mailbox.tag_receive_timeout(&[tag], timeout))
+}
+
+/// Same as [`multicall`], but uses the serializer `S`.
+pub fn multicall_with<C, R, S>(
+    nodes: &[u64],
+    capture: C,
+    function: fn(C) -> R,
+    timeout: Duration,
+) -> Vec<Result<R, RpcError>>
+where
+    C: Clone,
+    S: CanSerialize<()>
+        + CanSerialize<Option<R>>
+        + CanSerialize<Capture<C, R, S>>
+        + CanSerialize<ProtocolCapture<Capture<C, R, S>>>,
+{
+    let mut results: Vec<Option<Result<R, RpcError>>> = Vec::with_capacity(nodes.len());
+    let mut pending = Vec::new();
+    for (index, node_id) in nodes.iter().enumerate() {
+        let tag = Tag::new();
+        match spawn::<C, R, S>(*node_id, tag, capture.clone(), function) {
+            Ok(()) => {
+                results.push(None);
+                pending.push((tag, index));
+            }
+            Err(err) => results.push(Some(Err(err))),
+        }
+    }
+
+    let deadline = Instant::now() + timeout;
+    // Temporarily cast to right mailbox type.
+    let mailbox: Mailbox<Option<R>, S> = unsafe { Mailbox::new() };
+    while !pending.is_empty() {
+        let tags: Vec<Tag> = pending.iter().map(|(tag, _)| *tag).collect();
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let result = mailbox.tag_receive_timeout(&tags, remaining);
+        if matches!(result, Err(MailboxError::TimedOut)) {
+            break;
+        }
+        let tag = unsafe { Tag::from(message::get_tag()) };
+        let position = pending
+            .iter()
+            .position(|(pending_tag, _)| *pending_tag == tag)
+            .expect("result for a pending call");
+        let (_, index) = pending.swap_remove(position);
+        results[index] = Some(into_result(result));
+    }
+
+    results
+        .into_iter()
+        .map(|result| result.unwrap_or(Err(RpcError::TimedOut)))
+        .collect()
+}
+
+/// Spawns the process executing the call on the node `node_id`.
+fn spawn<C, R, S>(node_id: u64, tag: Tag, capture: C, function: fn(C) -> R) -> Result<(), RpcError>
+where
+    S: CanSerialize<()>
+        + CanSerialize<Option<R>>
+        + CanSerialize<Capture<C, R, S>>
+        + CanSerialize<ProtocolCapture<Capture<C, R, S>>>,
+{
+    let parent = unsafe { Process::<Option<R>, S>::this() };
+    let capture = (parent, tag, capture, FuncRef::new(function));
+    <Mailbox<(), S> as IntoProcess<(), S>>::spawn(
+        capture,
+        execute::<C, R, S>,
+        None,
+        None,
+        None,
+        Some(node_id),
+    )
+    .map_err(RpcError::SpawnFailed)?;
+    Ok(())
+}
+
+/// Entry point of the process executing the call.
+fn execute<C, R, S>((parent, tag, capture, function): Capture<C, R, S>, _: Mailbox<(), S>)
+where
+    S: CanSerialize<()> + CanSerialize<Option<R>>,
+{
+    let result = catch_panic(|| function(capture)).ok();
+    parent.tag_send(tag, result);
+}
+
+fn into_result<R>(result: Result<Option<R>, MailboxError>) -> Result<R, RpcError> {
+    match result {
+        Ok(Some(result)) => Ok(result),
+        Ok(None) => Err(RpcError::Panicked),
+        Err(MailboxError::TimedOut) => Err(RpcError::TimedOut),
+        Err(MailboxError::DeserializationFailed(err)) => Err(RpcError::DeserializationFailed(err)),
+    }
+}
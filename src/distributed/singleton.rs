@@ -0,0 +1,158 @@
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::global;
+use crate::ap::{AbstractProcess, ProcessRef};
+use crate::function::process::{process_name, ProcessType};
+use crate::{host, Mailbox, MessageSignal, Process, ProcessDiedSignal, Tag};
+
+/// An [`AbstractProcess`] with only one instance across the cluster.
+///
+/// [`start`](Singleton::start) needs to be called on each node that can run
+/// the instance. It spawns a watcher process on the node. If no instance is
+/// registered, the watchers start one on their own node and try to register
+/// it globally under the singleton's name. Only one of them succeeds, the
+/// other instances are killed right away.
+///
+/// Each watcher monitors the instance. The watcher on the instance's node
+/// notices when it dies, the watchers on other nodes notice when the node goes
+/// down. The instance's entry is then removed from the
+/// [`global`](super::global) registry and the watchers start a new instance.
+/// Callers always find the current instance with
+/// [`lookup`](Singleton::lookup), under the same name.
+///
+/// The state of the instance is not preserved when it's restarted.
+///
+/// # Example
+///
+/// ```no_run
+/// use lunatic::ap::{AbstractProcess, Config};
+/// use lunatic::distributed::Singleton;
+/// # struct Scheduler;
+/// # impl AbstractProcess for Scheduler {
+/// #     type State = ();
+/// #     type Serializer = lunatic::serializer::Bincode;
+/// #     type Arg = ();
+/// #     type Handlers = ();
+/// #     type StartupError = ();
+/// #     fn init(_: Config<Self>, _: ()) -> Result<(), ()> { Ok(()) }
+/// # }
+///
+/// // On each node that can run the scheduler.
+/// let _watcher = Singleton::<Scheduler>::start("scheduler", ());
+///
+/// // Anywhere in the cluster.
+/// if let Some(scheduler) = Singleton::<Scheduler>::lookup("scheduler") {
+///     // ...
+/// }
+/// ```
+pub struct Singleton<T> {
+    name: String,
+    watcher: Process<()>,
+    phantom: PhantomData<T>,
+}
+
+impl<T> Singleton<T>
+where
+    T: AbstractProcess,
+    T::Arg: Serialize + DeserializeOwned + Clone,
+{
+    /// Spawns a watcher on this node, keeping an instance of `T` started with
+    /// `arg` running under `name`.
+    ///
+    /// Returns once the watcher found or started an instance. If the instance
+    /// fails to start on this node, the watcher stops and this node won't
+    /// take over.
+    pub fn start(name: &str, arg: T::Arg) -> Self {
+        let tag = Tag::new();
+        let parent = unsafe { Process::<()>::new(host::node_id(), host::process_id()) };
+        let watcher = Process::spawn((name.to_owned(), arg, parent, tag), watch::<T>);
+        // Temporarily cast to right mailbox type.
+        let mailbox: Mailbox<()> = unsafe { Mailbox::new() };
+        mailbox.tag_receive(&[tag]);
+        Self {
+            name: name.to_owned(),
+            watcher,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: AbstractProcess> Singleton<T> {
+    /// Returns the running instance registered under `name`.
+    pub fn lookup(name: &str) -> Option<ProcessRef<T>> {
+        ProcessRef::lookup_global(name)
+    }
+
+    /// Returns the running instance.
+    pub fn get(&self) -> Option<ProcessRef<T>> {
+        Self::lookup(&self.name)
+    }
+
+    /// Returns the name of the singleton.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Stops the watcher on this node.
+    ///
+    /// A running instance is not affected, but this node won't take over if
+    /// the instance stops.
+    pub fn stop(self) {
+        self.watcher.send(());
+    }
+}
+
+/// Entry point of the watcher process.
+fn watch<T>((name, arg, parent, tag): (String, T::Arg, Process<()>, Tag), mailbox: Mailbox<()>)
+where
+    T: AbstractProcess,
+    T::Arg: Clone,
+{
+    let mailbox = mailbox.monitorable();
+    let mut started = false;
+    loop {
+        let instance = match ProcessRef::<T>::lookup_global(&name) {
+            Some(instance) => instance,
+            None => match T::start(arg.clone()) {
+                Ok(instance) => match instance.register_global(&name) {
+                    Ok(()) => instance,
+                    // Another node was faster.
+                    Err(existing) => {
+                        instance.kill();
+                        existing
+                    }
+                },
+                Err(_) => {
+                    // This node can't run the instance.
+                    if !started {
+                        parent.tag_send(tag, ());
+                    }
+                    break;
+                }
+            },
+        };
+        if !started {
+            started = true;
+            parent.tag_send(tag, ());
+        }
+
+        // Fires when the instance dies or its node goes down.
+        let process = unsafe { Process::<()>::new(instance.node_id(), instance.id()) };
+        mailbox.monitor(process);
+        match mailbox.receive() {
+            MessageSignal::Message(()) => {
+                mailbox.stop_monitoring(process);
+                break;
+            }
+            MessageSignal::Signal(ProcessDiedSignal(_)) => {
+                // The registry could still have the entry of the dead
+                // instance.
+                let key = process_name::<T, T::Serializer>(ProcessType::ProcessRef, &name);
+                global::remove(key, instance.node_id(), instance.id());
+            }
+        }
+    }
+}
@@ -7,9 +7,12 @@ use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
 
+use crate::distributed::global::{self, Resolver};
+use crate::distributed::monitor;
 use crate::host::{self, node_id, process_id};
 use crate::mailbox::{MailboxError, MessageSignal, TIMEOUT};
 use crate::protocol::ProtocolCapture;
+use crate::registry::{self, Metadata, Registry};
 use crate::serializer::{Bincode, CanSerialize};
 use crate::time::TimerRef;
 use crate::{LunaticError, MailboxResult, ProcessConfig, ProcessName, Tag};
@@ -375,16 +378,19 @@ impl<M, S> Process<M, S> {
     }
 
     /// Link process to the one currently running.
+    ///
+    /// Links to processes on other nodes only break when the node goes down,
+    /// see [`monitor_nodes`](crate::distributed::monitor_nodes).
     pub fn link(&self) {
         // Don't use tags because a process' [`Mailbox`] can't differentiate between
         // regular messages and signals. Both processes should almost always die
         // when a link is broken.
-        unsafe { host::api::process::link(0, self.id) };
+        monitor::link(Tag::none(), self.node_id, self.id);
     }
 
     /// Unlink processes from the caller.
     pub fn unlink(&self) {
-        unsafe { host::api::process::unlink(self.id) };
+        monitor::unlink(self.node_id, self.id);
     }
 
     /// Kill this process
@@ -393,10 +399,102 @@ impl<M, S> Process<M, S> {
     }
 
     /// Register process under a name.
+    ///
+    /// The name is removed from the registry when the process dies.
     pub fn register<N: ProcessName>(&self, name: &N) {
         // Encode type information in name
         let name = process_name::<M, S>(ProcessType::Process, name.process_name());
-        unsafe { host::api::registry::put(name.as_ptr(), name.len(), self.node_id, self.id) };
+        registry::register_plain(&name, self.node_id, self.id);
+    }
+
+    /// Register process under a name, together with `metadata`.
+    ///
+    /// The metadata can be retrieved with
+    /// [`lookup_with_metadata`](Self::lookup_with_metadata) or
+    /// [`registry::list`](crate::registry::list).
+    pub fn register_with_metadata<N: ProcessName>(&self, name: &N, metadata: Metadata) {
+        let name = name.process_name();
+        // Encode type information in name
+        let key = process_name::<M, S>(ProcessType::Process, name);
+        registry::register(name, key, self.node_id, self.id, metadata);
+    }
+
+    /// Removes the process registered under `name` from the registry.
+    ///
+    /// Returns the removed process, if one was registered.
+    pub fn unregister<N: ProcessName + ?Sized>(name: &N) -> Option<Self> {
+        let key = process_name::<M, S>(ProcessType::Process, name.process_name());
+        let (node_id, id) = registry::unregister(&key)?;
+        Some(unsafe { Self::new(node_id, id) })
+    }
+
+    /// Look up a process together with the metadata it was registered with.
+    ///
+    /// The metadata is empty if the process was registered without it.
+    pub fn lookup_with_metadata<N: ProcessName + ?Sized>(name: &N) -> Option<(Self, Metadata)> {
+        let key = process_name::<M, S>(ProcessType::Process, name.process_name());
+        let ((node_id, id), metadata) = registry::lookup_with_metadata(key)?;
+        Some((unsafe { Self::new(node_id, id) }, metadata))
+    }
+
+    /// Register process under a name in `registry`.
+    ///
+    /// If another process is already registered under the name, it returns
+    /// `Err(process)` with the existing process.
+    pub fn register_via<N: ProcessName + ?Sized>(
+        &self,
+        registry: &dyn Registry,
+        name: &N,
+    ) -> Result<(), Self> {
+        let name = process_name::<M, S>(ProcessType::Process, name.process_name());
+        registry
+            .register(&name, self.node_id, self.id)
+            .map_err(|(node_id, id)| unsafe { Self::new(node_id, id) })
+    }
+
+    /// Look up a process in `registry`.
+    pub fn lookup_via<N: ProcessName + ?Sized>(registry: &dyn Registry, name: &N) -> Option<Self> {
+        let name = process_name::<M, S>(ProcessType::Process, name.process_name());
+        let (node_id, id) = registry.lookup(&name)?;
+        Some(unsafe { Self::new(node_id, id) })
+    }
+
+    /// Register process under a name on all connected nodes.
+    ///
+    /// If another process is already registered under the name on any
+    /// reachable node, it returns `Err(process)` with the existing process.
+    /// See [`distributed::global`](crate::distributed::global) for details.
+    pub fn register_global<N: ProcessName + ?Sized>(&self, name: &N) -> Result<(), Self> {
+        self.register_global_with(name, Resolver::default())
+    }
+
+    /// Same as [`register_global`](Self::register_global), but uses
+    /// `resolver` to resolve conflicts after a network partition.
+    pub fn register_global_with<N: ProcessName + ?Sized>(
+        &self,
+        name: &N,
+        resolver: Resolver,
+    ) -> Result<(), Self> {
+        let key = process_name::<M, S>(ProcessType::Process, name.process_name());
+        global::register(key, self.node_id, self.id, resolver)
+            .map_err(|(node_id, id)| unsafe { Self::new(node_id, id) })
+    }
+
+    /// Look up a process registered with
+    /// [`register_global`](Self::register_global).
+    pub fn lookup_global<N: ProcessName + ?Sized>(name: &N) -> Option<Self> {
+        let key = process_name::<M, S>(ProcessType::Process, name.process_name());
+        let entry = global::lookup(key)?;
+        Some(unsafe { Self::new(entry.node_id(), entry.process_id()) })
+    }
+
+    /// Removes the process registered under `name` from the global registry.
+    ///
+    /// Returns the removed process, if one was registered.
+    pub fn unregister_global<N: ProcessName + ?Sized>(name: &N) -> Option<Self> {
+        let key = process_name::<M, S>(ProcessType::Process, name.process_name());
+        let entry = global::unregister(key)?;
+        Some(unsafe { Self::new(entry.node_id(), entry.process_id()) })
     }
 
     /// Look up a process.
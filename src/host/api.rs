@@ -25,6 +25,8 @@ pub mod message {
         pub fn take_module(index: u64) -> u64;
         pub fn push_tcp_stream(tcp_stream_id: u64) -> u64;
         pub fn take_tcp_stream(index: u64) -> u64;
+        pub fn push_tcp_listener(tcp_listener_id: u64) -> u64;
+        pub fn take_tcp_listener(index: u64) -> u64;
         pub fn push_tls_stream(tls_stream_id: u64) -> u64;
         pub fn take_tls_stream(index: u64) -> u64;
         pub fn send(process_id: u64) -> u32;
@@ -72,12 +74,14 @@ pub mod networking {
             id: *mut u64,
         ) -> u32;
         pub fn drop_tcp_listener(tcp_listener_id: u64);
+        pub fn clone_tcp_listener(tcp_listener_id: u64) -> u64;
         pub fn drop_tls_listener(tcp_listener_id: u64);
         pub fn drop_udp_socket(udp_socket_id: u64);
         pub fn tcp_local_addr(tcp_listener_id: u64, addr_dns_iter: *mut u64) -> u32;
         pub fn tls_local_addr(tcp_listener_id: u64, addr_dns_iter: *mut u64) -> u32;
         pub fn udp_local_addr(udp_socket_id: u64, addr_dns_iter: *mut u64) -> u32;
         pub fn tcp_peer_addr(tcp_stream_id: u64, addr_dns_iter: *mut u64) -> u32;
+        pub fn tcp_stream_local_addr(tcp_stream_id: u64, addr_dns_iter: *mut u64) -> u32;
         pub fn udp_peer_addr(udp_stream_id: u64, addr_dns_iter: *mut u64) -> u32;
         pub fn tcp_accept(listener_id: u64, id: *mut u64, peer_dns_iter: *mut u64) -> u32;
         pub fn tcp_connect(
@@ -157,6 +161,36 @@ pub mod networking {
         pub fn get_udp_socket_ttl(udp_socket_id: u64) -> u32;
         pub fn get_udp_socket_broadcast(udp_socket_id: u64) -> i32;
         pub fn clone_udp_socket(udp_socket_id: u64) -> u64;
+        pub fn set_udp_socket_read_timeout(udp_socket_id: u64, duration: u64);
+        pub fn get_udp_socket_read_timeout(udp_socket_id: u64) -> u64;
+        pub fn set_udp_socket_write_timeout(udp_socket_id: u64, duration: u64);
+        pub fn get_udp_socket_write_timeout(udp_socket_id: u64) -> u64;
+        pub fn set_udp_socket_nonblocking(udp_socket_id: u64, nonblocking: u32);
+        pub fn udp_take_error(udp_socket_id: u64, error_id: *mut u64) -> u32;
+        pub fn udp_join_multicast_v4(
+            udp_socket_id: u64,
+            multiaddr: *const u8,
+            interface: *const u8,
+            error_id: *mut u64,
+        ) -> u32;
+        pub fn udp_leave_multicast_v4(
+            udp_socket_id: u64,
+            multiaddr: *const u8,
+            interface: *const u8,
+            error_id: *mut u64,
+        ) -> u32;
+        pub fn udp_join_multicast_v6(
+            udp_socket_id: u64,
+            multiaddr: *const u8,
+            interface: u32,
+            error_id: *mut u64,
+        ) -> u32;
+        pub fn udp_leave_multicast_v6(
+            udp_socket_id: u64,
+            multiaddr: *const u8,
+            interface: u32,
+            error_id: *mut u64,
+        ) -> u32;
         pub fn tcp_flush(tcp_stream_id: u64, error_id: *mut u64) -> u32;
         pub fn tls_flush(tcp_stream_id: u64, error_id: *mut u64) -> u32;
         pub fn set_read_timeout(tcp_stream_id: u64, duration: u64);
@@ -165,6 +199,13 @@ pub mod networking {
         pub fn get_write_timeout(tcp_stream_id: u64) -> u64;
         pub fn set_peek_timeout(tcp_stream_id: u64, duration: u64);
         pub fn get_peek_timeout(tcp_stream_id: u64) -> u64;
+        pub fn tcp_shutdown(tcp_stream_id: u64, how: u32, error_id: *mut u64) -> u32;
+        pub fn tcp_set_nodelay(tcp_stream_id: u64, nodelay: u32, error_id: *mut u64) -> u32;
+        pub fn tcp_nodelay(tcp_stream_id: u64, opaque: *mut u64) -> u32;
+        pub fn tcp_set_keepalive(tcp_stream_id: u64, idle: u64, error_id: *mut u64) -> u32;
+        pub fn tcp_keepalive(tcp_stream_id: u64, opaque: *mut u64) -> u32;
+        pub fn tcp_set_linger(tcp_stream_id: u64, linger: u64, error_id: *mut u64) -> u32;
+        pub fn tcp_linger(tcp_stream_id: u64, opaque: *mut u64) -> u32;
 
         // tls
         pub fn tls_bind(
@@ -207,6 +248,56 @@ pub mod networking {
         pub fn get_tls_read_timeout(tls_stream_id: u64) -> u64;
         pub fn set_tls_write_timeout(tls_stream_id: u64, duration: u64);
         pub fn get_tls_write_timeout(tls_stream_id: u64) -> u64;
+        pub fn create_tls_config(server: u32) -> u64;
+        pub fn drop_tls_config(tls_config_id: u64);
+        pub fn tls_config_set_default_roots(tls_config_id: u64, enabled: u32);
+        pub fn tls_config_set_verify(tls_config_id: u64, verify: u32);
+        pub fn tls_config_add_root_certificate(
+            tls_config_id: u64,
+            cert_ptr: *const u8,
+            cert_len: usize,
+            error_id: *mut u64,
+        ) -> u32;
+        pub fn tls_config_set_certificate(
+            tls_config_id: u64,
+            certs_ptr: *const u8,
+            certs_len: usize,
+            key_ptr: *const u8,
+            key_len: usize,
+            error_id: *mut u64,
+        ) -> u32;
+        pub fn tls_config_set_client_auth(tls_config_id: u64, mode: u32);
+        pub fn tls_config_add_alpn_protocol(
+            tls_config_id: u64,
+            protocol_ptr: *const u8,
+            protocol_len: usize,
+        );
+        pub fn tls_config_set_server_name(tls_config_id: u64, name_ptr: *const u8, name_len: usize);
+        pub fn tls_bind_config(
+            addr_type: u32,
+            addr: *const u8,
+            port: u32,
+            flow_info: u32,
+            scope_id: u32,
+            tls_config_id: u64,
+            id: *mut u64,
+        ) -> u32;
+        pub fn tls_connect_config(
+            addr_str: *const u8,
+            addr_str_len: u32,
+            port: u32,
+            timeout: u64,
+            tls_config_id: u64,
+            id: *mut u64,
+        ) -> u32;
+        pub fn tls_alpn_protocol_size(tls_stream_id: u64) -> u32;
+        pub fn tls_alpn_protocol(tls_stream_id: u64, protocol: *mut u8);
+        pub fn tls_peer_certificates_count(tls_stream_id: u64) -> u32;
+        pub fn tls_peer_certificate_size(tls_stream_id: u64, index: u32) -> u32;
+        pub fn tls_peer_certificate(tls_stream_id: u64, index: u32, cert: *mut u8);
+        pub fn tls_config_set_proxy_protocol(tls_config_id: u64, enabled: u32);
+        pub fn tls_proxy_header_size(tls_stream_id: u64) -> u32;
+        pub fn tls_proxy_header(tls_stream_id: u64, header: *mut u8);
     }
 }
 
@@ -316,6 +407,18 @@ pub mod distributed {
             nodes_len: u32,
             error_ptr: *mut u64,
         ) -> i32;
+        pub fn exec_node_info(
+            node_id: u64,
+            info_id_ptr: *mut u64,
+            info_len_ptr: *mut u32,
+            error_ptr: *mut u64,
+        ) -> u32;
+        pub fn copy_node_info_results(
+            info_id: u64,
+            info_ptr: *mut u8,
+            info_len: u32,
+            error_ptr: *mut u64,
+        ) -> i32;
         pub fn nodes_count() -> u32;
         pub fn node_id() -> u64;
         pub fn module_id() -> u64;
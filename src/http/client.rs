@@ -0,0 +1,429 @@
+use std::collections::HashMap;
+use std::io::{self, BufReader};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use super::parse::{self, Limits};
+use super::{Connection, HttpError, Method, Request, Response, Stream};
+use crate::ap::handlers::{Message, Request as RequestMsg};
+use crate::ap::{
+    AbstractProcess, Config, MessageHandler, ProcessRef, RequestHandler, StartupError, State,
+};
+use crate::net::{TcpStream, TlsStream};
+use crate::serializer::Bincode;
+
+/// Name under which the connection pool of each node is registered.
+const POOL_NAME: &str = "lunatic::http::pool";
+
+/// How long idle connections are kept in the pool.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Maximum number of idle connections kept per host.
+const MAX_IDLE_PER_HOST: usize = 8;
+
+/// An HTTP/1.1 client.
+///
+/// Requests are sent to the absolute URL in their target, e.g.
+/// `https://example.com/users?page=2`. Both `http` and `https` URLs are
+/// supported.
+///
+/// Connections are reused between requests. After a response is read, the
+/// connection is handed to a pool process running on the local node, so that
+/// other processes can reuse it too. Idle connections are closed after 30
+/// seconds.
+///
+/// # Example
+///
+/// ```no_run
+/// use lunatic::http::Client;
+///
+/// let client = Client::new();
+/// let response = client.get("http://example.com/").unwrap();
+/// println!("{}", String::from_utf8_lossy(response.body()));
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Client {
+    timeout: Option<Duration>,
+    connect_timeout: Duration,
+    max_redirects: usize,
+    max_head_size: usize,
+    max_body_size: usize,
+    certs: Vec<Vec<u8>>,
+    pooling: bool,
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Self {
+            timeout: Some(Duration::from_secs(30)),
+            connect_timeout: Duration::from_secs(10),
+            max_redirects: 10,
+            max_head_size: 64 * 1024,
+            max_body_size: 16 * 1024 * 1024,
+            certs: Vec::new(),
+            pooling: true,
+        }
+    }
+}
+
+impl Client {
+    /// Creates a client with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how long to wait for the response to arrive. Defaults to 30
+    /// seconds.
+    ///
+    /// `None` waits forever.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets how long to wait for a new connection to be established.
+    /// Defaults to 10 seconds.
+    #[must_use]
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Sets how many redirects are followed. Defaults to 10.
+    ///
+    /// If set to 0, redirect responses are returned to the caller.
+    #[must_use]
+    pub fn max_redirects(mut self, max_redirects: usize) -> Self {
+        self.max_redirects = max_redirects;
+        self
+    }
+
+    /// Sets the maximum size of response bodies. Defaults to 16 MiB.
+    #[must_use]
+    pub fn max_body_size(mut self, size: usize) -> Self {
+        self.max_body_size = size;
+        self
+    }
+
+    /// Adds a trusted root certificate for `https` connections.
+    #[must_use]
+    pub fn add_root_certificate(mut self, cert: Vec<u8>) -> Self {
+        self.certs.push(cert);
+        self
+    }
+
+    /// Sets if connections are kept in the pool and reused. Defaults to
+    /// `true`.
+    #[must_use]
+    pub fn pooling(mut self, pooling: bool) -> Self {
+        self.pooling = pooling;
+        self
+    }
+
+    /// Sends a `GET` request to `url`.
+    pub fn get(&self, url: &str) -> Result<Response, HttpError> {
+        self.send(Request::new(Method::Get, url))
+    }
+
+    /// Sends a `POST` request with `body` to `url`.
+    pub fn post(&self, url: &str, body: impl Into<Vec<u8>>) -> Result<Response, HttpError> {
+        self.send(Request::new(Method::Post, url).with_body(body))
+    }
+
+    /// Sends the request to the absolute URL in its target and returns the
+    /// response, following redirects.
+    ///
+    /// The `Host` header is set from the URL if missing. Responses with a
+    /// `Transfer-Encoding: chunked` body are decoded.
+    pub fn send(&self, mut request: Request) -> Result<Response, HttpError> {
+        let mut redirects = 0;
+        loop {
+            let url = Url::parse(request.target())?;
+            let response = self.send_to(&url, &request)?;
+            let location = match response.header("Location") {
+                Some(location)
+                    if self.max_redirects > 0
+                        && matches!(response.status(), 301 | 302 | 303 | 307 | 308) =>
+                {
+                    location
+                }
+                _ => return Ok(response),
+            };
+            if redirects == self.max_redirects {
+                return Err(HttpError::TooManyRedirects);
+            }
+            redirects += 1;
+            let next = url.join(location)?;
+            // `303` always switches to `GET`, `301` and `302` only for `POST`
+            // requests, as most clients do.
+            let switch_to_get = response.status() == 303
+                || (matches!(response.status(), 301 | 302) && *request.method() == Method::Post);
+            let method = if switch_to_get && *request.method() != Method::Head {
+                Method::Get
+            } else {
+                request.method().clone()
+            };
+            let mut headers = request.headers().clone();
+            if Url::parse(&next)?.origin() != url.origin() {
+                // Don't leak credentials to other hosts.
+                headers.remove("Authorization");
+                headers.remove("Cookie");
+                headers.remove("Host");
+            }
+            let body = if switch_to_get {
+                Vec::new()
+            } else {
+                request.into_body()
+            };
+            request = Request::new(method, next).with_body(body);
+            *request.headers_mut() = headers;
+        }
+    }
+
+    /// Sends the request to `url` without following redirects.
+    fn send_to(&self, url: &Url, request: &Request) -> Result<Response, HttpError> {
+        let mut outgoing = Request::new(request.method().clone(), url.path.clone())
+            .with_body(request.body().to_vec());
+        *outgoing.headers_mut() = request.headers().clone();
+        if !outgoing.headers().contains("Host") {
+            outgoing.headers_mut().insert("Host", url.authority());
+        }
+        if !self.pooling {
+            outgoing.headers_mut().insert("Connection", "close");
+        }
+
+        let origin = url.origin();
+        if self.pooling {
+            if let Some(connection) = pool().request(Checkout(origin.clone())) {
+                match self.exchange(connection, &origin, &outgoing) {
+                    // The server closed the idle connection, retry with a new
+                    // one.
+                    Err(HttpError::ConnectionClosed) => {}
+                    Err(HttpError::Io(err))
+                        if matches!(
+                            err.kind(),
+                            io::ErrorKind::ConnectionReset | io::ErrorKind::BrokenPipe
+                        ) => {}
+                    result => return result,
+                }
+            }
+        }
+        let connection = Connection::connect(url, self.connect_timeout, &self.certs)?;
+        self.exchange(connection, &origin, &outgoing)
+    }
+
+    /// Writes the request and reads the response over `connection`.
+    fn exchange(
+        &self,
+        mut connection: Connection,
+        origin: &str,
+        request: &Request,
+    ) -> Result<Response, HttpError> {
+        connection.set_read_timeout(self.timeout)?;
+        parse::write_request(&mut connection, request)?;
+        let limits = Limits {
+            max_head_size: self.max_head_size,
+            max_body_size: self.max_body_size,
+        };
+        let mut reader = BufReader::new(connection.clone());
+        let response = parse::read_response(&mut reader, request.method(), limits)?;
+        // Connections with unread data can't be reused.
+        if self.pooling && response.keep_alive() && reader.buffer().is_empty() {
+            drop(reader);
+            pool().send(Checkin(origin.to_owned(), connection));
+        }
+        Ok(response)
+    }
+}
+
+/// An absolute `http` or `https` URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Url {
+    pub(crate) tls: bool,
+    /// Host name, IPv6 addresses are enclosed in brackets.
+    pub(crate) host: String,
+    pub(crate) port: u16,
+    /// Path and query.
+    pub(crate) path: String,
+}
+
+impl Url {
+    pub(crate) fn parse(url: &str) -> Result<Self, HttpError> {
+        let invalid = || HttpError::InvalidUrl(url.to_owned());
+        let (tls, rest) = match url.split_once("://") {
+            Some((scheme, rest)) if scheme.eq_ignore_ascii_case("http") => (false, rest),
+            Some((scheme, rest)) if scheme.eq_ignore_ascii_case("https") => (true, rest),
+            _ => return Err(invalid()),
+        };
+        // Fragments are never sent to the server.
+        let rest = rest.split('#').next().unwrap_or_default();
+        let (authority, path) = match rest.find(['/', '?']) {
+            Some(index) => rest.split_at(index),
+            None => (rest, "/"),
+        };
+        // User information is not supported.
+        if authority.contains('@') {
+            return Err(invalid());
+        }
+        let port_index = match authority.rfind(':') {
+            Some(index) if !authority[index..].contains(']') => Some(index),
+            _ => None,
+        };
+        let (host, port) = match port_index {
+            Some(index) => {
+                let port = authority[index + 1..].parse().map_err(|_| invalid())?;
+                (&authority[..index], port)
+            }
+            None if tls => (authority, 443),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(invalid());
+        }
+        let path = if path.starts_with('?') {
+            format!("/{path}")
+        } else {
+            path.to_owned()
+        };
+        Ok(Url {
+            tls,
+            host: host.to_ascii_lowercase(),
+            port,
+            path,
+        })
+    }
+
+    fn scheme(&self) -> &'static str {
+        if self.tls {
+            "https"
+        } else {
+            "http"
+        }
+    }
+
+    /// Returns the host and port, omitting the default port.
+    pub(crate) fn authority(&self) -> String {
+        match (self.tls, self.port) {
+            (false, 80) | (true, 443) => self.host.clone(),
+            (_, port) => format!("{}:{port}", self.host),
+        }
+    }
+
+    /// Returns the scheme, host and port, identifying the server.
+    fn origin(&self) -> String {
+        format!("{}://{}:{}", self.scheme(), self.host, self.port)
+    }
+
+    /// Resolves the `Location` of a redirect relative to this URL.
+    fn join(&self, location: &str) -> Result<String, HttpError> {
+        if location.contains("://") {
+            return Ok(location.to_owned());
+        }
+        let base = format!("{}://{}", self.scheme(), self.authority());
+        if location.starts_with("//") {
+            return Ok(format!("{}:{location}", self.scheme()));
+        }
+        if location.starts_with('/') {
+            return Ok(format!("{base}{location}"));
+        }
+        if location.is_empty() {
+            return Err(HttpError::InvalidUrl(location.to_owned()));
+        }
+        // Relative to the directory of the current path.
+        let path = self.path.split('?').next().unwrap_or_default();
+        let directory = match path.rfind('/') {
+            Some(index) => &path[..=index],
+            None => "/",
+        };
+        Ok(format!("{base}{directory}{location}"))
+    }
+}
+
+impl Connection {
+    /// Connects to the server of `url`.
+    pub(crate) fn connect(url: &Url, timeout: Duration, certs: &[Vec<u8>]) -> io::Result<Self> {
+        if url.tls {
+            let host = url.host.trim_start_matches('[').trim_end_matches(']');
+            TlsStream::connect_timeout(host, timeout, url.port as u32, certs.to_vec())
+                .map(Connection::Tls)
+        } else {
+            TcpStream::connect_timeout(format!("{}:{}", url.host, url.port), timeout)
+                .map(Connection::Tcp)
+        }
+    }
+}
+
+/// Returns the connection pool of this node, starting it if necessary.
+fn pool() -> ProcessRef<ConnectionPool> {
+    if let Some(pool) = ProcessRef::lookup(POOL_NAME) {
+        return pool;
+    }
+    match ConnectionPool::start_as(&POOL_NAME, ()) {
+        // Another process could have started the pool in the meantime.
+        Ok(pool) | Err(StartupError::NameAlreadyRegistered(pool)) => pool,
+        Err(err) => panic!("failed to start the connection pool: {err:?}"),
+    }
+}
+
+/// The connection pool, holding idle connections by origin.
+struct ConnectionPool;
+
+impl AbstractProcess for ConnectionPool {
+    type State = HashMap<String, Vec<(Connection, Instant)>>;
+    type Serializer = Bincode;
+    type Arg = ();
+    type Handlers = (RequestMsg<Checkout>, Message<Checkin>, Message<Expire>);
+    type StartupError = ();
+
+    fn init(config: Config<Self>, _: ()) -> Result<Self::State, ()> {
+        config.self_ref().with_delay(IDLE_TIMEOUT).send(Expire);
+        Ok(HashMap::new())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Checkout(String);
+
+impl RequestHandler<Checkout> for ConnectionPool {
+    type Response = Option<Connection>;
+
+    fn handle(mut state: State<Self>, Checkout(origin): Checkout) -> Option<Connection> {
+        let idle = state.get_mut(&origin)?;
+        // Prefer the most recently used connection, it's the least likely to
+        // be closed by the server.
+        while let Some((connection, since)) = idle.pop() {
+            if since.elapsed() < IDLE_TIMEOUT {
+                return Some(connection);
+            }
+        }
+        None
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Checkin(String, Connection);
+
+impl MessageHandler<Checkin> for ConnectionPool {
+    fn handle(mut state: State<Self>, Checkin(origin, connection): Checkin) {
+        let idle = state.entry(origin).or_default();
+        if idle.len() == MAX_IDLE_PER_HOST {
+            idle.remove(0);
+        }
+        idle.push((connection, Instant::now()));
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Expire;
+
+impl MessageHandler<Expire> for ConnectionPool {
+    fn handle(mut state: State<Self>, _: Expire) {
+        for idle in state.values_mut() {
+            idle.retain(|(_, since)| since.elapsed() < IDLE_TIMEOUT);
+        }
+        state.retain(|_, idle| !idle.is_empty());
+        state.self_ref().with_delay(IDLE_TIMEOUT).send(Expire);
+    }
+}
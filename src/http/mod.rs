@@ -0,0 +1,122 @@
+//! HTTP/1.1 server and client built on top of lunatic processes.
+//!
+//! Every connection accepted by the [`Server`] is handled inside its own
+//! process, so a slow or crashing handler can't affect other connections.
+//! Handlers are plain functions, registered on a [`Router`].
+//!
+//! The [`Client`] keeps idle connections in a pool process, so that they can
+//! be reused by all processes on the node.
+//!
+//! The [`websocket`] module adds support for WebSocket connections.
+
+mod client;
+mod message;
+pub(crate) mod parse;
+mod router;
+mod server;
+pub mod websocket;
+
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+pub use client::Client;
+pub use message::{reason_phrase, Headers, Method, Request, Response, Version};
+pub use router::{Handler, Router, WebSocketHandler};
+pub use server::{Listener, Server, ShutdownHandle};
+
+use crate::net::{TcpStream, TlsStream};
+
+/// Error reading or writing an HTTP message.
+#[derive(Error, Debug)]
+pub enum HttpError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("malformed message: {0}")]
+    Malformed(String),
+    #[error("headers too large")]
+    HeadersTooLarge,
+    #[error("body too large")]
+    BodyTooLarge,
+    #[error("connection closed")]
+    ConnectionClosed,
+    #[error("invalid URL `{0}`")]
+    InvalidUrl(String),
+    #[error("too many redirects")]
+    TooManyRedirects,
+}
+
+/// A connection HTTP messages can be exchanged over.
+pub(crate) trait Stream:
+    Read + Write + Clone + Serialize + DeserializeOwned + Into<Connection>
+{
+    fn set_read_timeout(&mut self, duration: Option<Duration>) -> io::Result<()>;
+}
+
+impl Stream for TcpStream {
+    fn set_read_timeout(&mut self, duration: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, duration)
+    }
+}
+
+impl Stream for TlsStream {
+    fn set_read_timeout(&mut self, duration: Option<Duration>) -> io::Result<()> {
+        TlsStream::set_read_timeout(self, duration)
+    }
+}
+
+/// A TCP or TLS connection.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Connection {
+    Tcp(TcpStream),
+    Tls(TlsStream),
+}
+
+impl From<TcpStream> for Connection {
+    fn from(stream: TcpStream) -> Self {
+        Connection::Tcp(stream)
+    }
+}
+
+impl From<TlsStream> for Connection {
+    fn from(stream: TlsStream) -> Self {
+        Connection::Tls(stream)
+    }
+}
+
+impl Stream for Connection {
+    fn set_read_timeout(&mut self, duration: Option<Duration>) -> io::Result<()> {
+        match self {
+            Connection::Tcp(stream) => stream.set_read_timeout(duration),
+            Connection::Tls(stream) => stream.set_read_timeout(duration),
+        }
+    }
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(stream) => stream.read(buf),
+            Connection::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(stream) => stream.write(buf),
+            Connection::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Connection::Tcp(stream) => stream.flush(),
+            Connection::Tls(stream) => stream.flush(),
+        }
+    }
+}
@@ -0,0 +1,347 @@
+use std::io::{BufRead, Read, Write};
+
+use super::{Headers, HttpError, Method, Request, Response, Version};
+
+/// Size limits applied while reading messages.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Limits {
+    pub(crate) max_head_size: usize,
+    pub(crate) max_body_size: usize,
+}
+
+/// Reads the head of a request.
+///
+/// Returns `Ok(None)` if the connection was closed before a request started.
+pub(crate) fn read_request_head<R: BufRead>(
+    reader: &mut R,
+    limits: Limits,
+) -> Result<Option<Request>, HttpError> {
+    let (start_line, headers) = match read_head(reader, limits)? {
+        Some(head) => head,
+        None => return Ok(None),
+    };
+    let mut parts = start_line.split(' ');
+    let (method, target, version) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(method), Some(target), Some(version), None) if !method.is_empty() => {
+            (method, target, version)
+        }
+        _ => return Err(HttpError::Malformed("invalid request line".to_owned())),
+    };
+    let version = Version::parse(version)
+        .ok_or_else(|| HttpError::Malformed(format!("unsupported version `{version}`")))?;
+    let method = match method.parse::<Method>() {
+        Ok(method) => method,
+        Err(never) => match never {},
+    };
+    Ok(Some(Request::from_parts(
+        method,
+        target.to_owned(),
+        version,
+        headers,
+    )))
+}
+
+/// Reads the body of a request.
+pub(crate) fn read_request_body<R: BufRead>(
+    reader: &mut R,
+    request: &mut Request,
+    limits: Limits,
+) -> Result<(), HttpError> {
+    // Requests without a length don't have a body.
+    let body = read_body(reader, request.headers(), limits, false)?;
+    request.set_body(body);
+    Ok(())
+}
+
+/// Reads a whole response to a request with `method`.
+pub(crate) fn read_response<R: BufRead>(
+    reader: &mut R,
+    method: &Method,
+    limits: Limits,
+) -> Result<Response, HttpError> {
+    let (start_line, headers) = read_head(reader, limits)?.ok_or(HttpError::ConnectionClosed)?;
+    let mut parts = start_line.splitn(3, ' ');
+    let (version, status, reason) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(version), Some(status), reason) => (version, status, reason.unwrap_or_default()),
+        _ => return Err(HttpError::Malformed("invalid status line".to_owned())),
+    };
+    let version = Version::parse(version)
+        .ok_or_else(|| HttpError::Malformed(format!("unsupported version `{version}`")))?;
+    let status: u16 = status
+        .parse()
+        .map_err(|_| HttpError::Malformed(format!("invalid status `{status}`")))?;
+    let mut response = Response::from_parts(status, reason.to_owned(), version, headers);
+    let no_body =
+        *method == Method::Head || (100..200).contains(&status) || status == 204 || status == 304;
+    if !no_body {
+        let body = read_body(reader, response.headers(), limits, !response.keep_alive())?;
+        response.set_body(body);
+    }
+    Ok(response)
+}
+
+/// Reads the start line and headers of a message.
+fn read_head<R: BufRead>(
+    reader: &mut R,
+    limits: Limits,
+) -> Result<Option<(String, Headers)>, HttpError> {
+    let mut remaining = limits.max_head_size;
+    let mut start_line = None;
+    let mut headers = Headers::new();
+    loop {
+        let line = match read_line(reader, &mut remaining)? {
+            Some(line) => line,
+            // Closing the connection between messages is not an error.
+            None if start_line.is_none() => return Ok(None),
+            None => return Err(HttpError::ConnectionClosed),
+        };
+        match start_line {
+            // Empty lines before the start line should be ignored.
+            None if line.is_empty() => continue,
+            None => start_line = Some(line),
+            Some(start_line) if line.is_empty() => return Ok(Some((start_line, headers))),
+            Some(_) => {
+                let (name, value) = line
+                    .split_once(':')
+                    .filter(|(name, _)| !name.is_empty() && !name.contains(char::is_whitespace))
+                    .ok_or_else(|| HttpError::Malformed(format!("invalid header `{line}`")))?;
+                headers.append(name, value.trim());
+            }
+        }
+    }
+}
+
+/// Reads a line without the line ending.
+///
+/// Returns `Ok(None)` if the stream ended.
+fn read_line<R: BufRead>(
+    reader: &mut R,
+    remaining: &mut usize,
+) -> Result<Option<String>, HttpError> {
+    let mut line = Vec::new();
+    let read = reader
+        .take(*remaining as u64 + 1)
+        .read_until(b'\n', &mut line)?;
+    if read == 0 {
+        return Ok(None);
+    }
+    if read > *remaining {
+        return Err(HttpError::HeadersTooLarge);
+    }
+    *remaining -= read;
+    if line.last() != Some(&b'\n') {
+        return Err(HttpError::ConnectionClosed);
+    }
+    line.pop();
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    String::from_utf8(line)
+        .map(Some)
+        .map_err(|_| HttpError::Malformed("header is not valid UTF-8".to_owned()))
+}
+
+fn read_body<R: BufRead>(
+    reader: &mut R,
+    headers: &Headers,
+    limits: Limits,
+    read_to_end: bool,
+) -> Result<Vec<u8>, HttpError> {
+    if let Some(encoding) = headers.get_all("Transfer-Encoding").last() {
+        if encoding
+            .rsplit(',')
+            .next()
+            .is_some_and(|last| last.trim().eq_ignore_ascii_case("chunked"))
+        {
+            return read_chunked(reader, limits);
+        }
+        return Err(HttpError::Malformed(format!(
+            "unsupported transfer encoding `{encoding}`"
+        )));
+    }
+    if let Some(length) = content_length(headers)? {
+        if length > limits.max_body_size {
+            return Err(HttpError::BodyTooLarge);
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body)?;
+        return Ok(body);
+    }
+    let mut body = Vec::new();
+    if read_to_end {
+        reader
+            .take(limits.max_body_size as u64 + 1)
+            .read_to_end(&mut body)?;
+        if body.len() > limits.max_body_size {
+            return Err(HttpError::BodyTooLarge);
+        }
+    }
+    Ok(body)
+}
+
+/// Returns the length of the body, if the message has one.
+///
+/// The header can be repeated or contain a list, as long as all values are the
+/// same.
+fn content_length(headers: &Headers) -> Result<Option<usize>, HttpError> {
+    let mut length = None;
+    for value in headers
+        .get_all("Content-Length")
+        .flat_map(|values| values.split(','))
+    {
+        let value: usize = value
+            .trim()
+            .parse()
+            .map_err(|_| HttpError::Malformed(format!("invalid content length `{value}`")))?;
+        if length.is_some_and(|length| length != value) {
+            return Err(HttpError::Malformed(
+                "conflicting content lengths".to_owned(),
+            ));
+        }
+        length = Some(value);
+    }
+    Ok(length)
+}
+
+fn read_chunked<R: BufRead>(reader: &mut R, limits: Limits) -> Result<Vec<u8>, HttpError> {
+    let mut body = Vec::new();
+    loop {
+        // Each chunk size line is bounded on its own, the number of chunks is
+        // bounded by the body size.
+        let mut remaining = limits.max_head_size;
+        let line = read_line(reader, &mut remaining)?.ok_or(HttpError::ConnectionClosed)?;
+        // Chunk extensions are ignored.
+        let size = line.split(';').next().unwrap_or_default().trim();
+        let size = u64::from_str_radix(size, 16)
+            .map_err(|_| HttpError::Malformed(format!("invalid chunk size `{size}`")))?;
+        if size == 0 {
+            break;
+        }
+        // The size comes from the peer and can overflow, especially on wasm32.
+        let end = usize::try_from(size)
+            .ok()
+            .and_then(|size| body.len().checked_add(size));
+        let end = match end {
+            Some(end) if end <= limits.max_body_size => end,
+            _ => return Err(HttpError::BodyTooLarge),
+        };
+        let start = body.len();
+        body.resize(end, 0);
+        reader.read_exact(&mut body[start..])?;
+        remaining = limits.max_head_size;
+        let line = read_line(reader, &mut remaining)?.ok_or(HttpError::ConnectionClosed)?;
+        if !line.is_empty() {
+            return Err(HttpError::Malformed("missing chunk terminator".to_owned()));
+        }
+    }
+    // Trailers are read, but ignored.
+    let mut remaining = limits.max_head_size;
+    loop {
+        let line = read_line(reader, &mut remaining)?.ok_or(HttpError::ConnectionClosed)?;
+        if line.is_empty() {
+            return Ok(body);
+        }
+    }
+}
+
+/// Writes the request, setting the `Content-Length` header.
+pub(crate) fn write_request<W: Write>(writer: &mut W, request: &Request) -> Result<(), HttpError> {
+    let start_line = format!(
+        "{} {} {}",
+        request.method(),
+        request.target(),
+        request.version().as_str()
+    );
+    let send_length = !request.body().is_empty()
+        || matches!(request.method(), Method::Post | Method::Put | Method::Patch);
+    write_message(
+        writer,
+        &start_line,
+        request.headers(),
+        request.body(),
+        send_length,
+        true,
+    )
+}
+
+/// Writes the response, setting the `Content-Length` header.
+///
+/// The body is omitted for responses to `HEAD` requests.
+pub(crate) fn write_response<W: Write>(
+    writer: &mut W,
+    response: &Response,
+    include_body: bool,
+) -> Result<(), HttpError> {
+    let start_line = format!(
+        "{} {} {}",
+        response.version().as_str(),
+        response.status(),
+        response.reason()
+    );
+    let status = response.status();
+    let send_length = !(100..200).contains(&status) && status != 204;
+    write_message(
+        writer,
+        &start_line,
+        response.headers(),
+        response.body(),
+        send_length,
+        include_body,
+    )
+}
+
+fn write_message<W: Write>(
+    writer: &mut W,
+    start_line: &str,
+    headers: &Headers,
+    body: &[u8],
+    send_length: bool,
+    include_body: bool,
+) -> Result<(), HttpError> {
+    let mut message = Vec::with_capacity(start_line.len() + body.len() + 256);
+    message.extend_from_slice(start_line.as_bytes());
+    message.extend_from_slice(b"\r\n");
+    for (name, value) in headers.iter() {
+        // The body is always sent with a known length.
+        if name.eq_ignore_ascii_case("Content-Length")
+            || name.eq_ignore_ascii_case("Transfer-Encoding")
+        {
+            continue;
+        }
+        message.extend_from_slice(format!("{name}: {value}\r\n").as_bytes());
+    }
+    if send_length {
+        message.extend_from_slice(format!("Content-Length: {}\r\n", body.len()).as_bytes());
+    }
+    message.extend_from_slice(b"\r\n");
+    if include_body {
+        message.extend_from_slice(body);
+    }
+    writer.write_all(&message)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Decodes `%XX` escape sequences.
+pub(crate) fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
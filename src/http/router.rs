@@ -0,0 +1,259 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::parse::percent_decode;
+use super::websocket::WebSocket;
+use super::{Method, Request, Response};
+use crate::function::FuncRef;
+
+/// A function handling requests.
+pub type Handler = fn(Request) -> Response;
+
+/// A function handling WebSocket connections, called with the upgrade
+/// request.
+pub type WebSocketHandler = fn(Request, WebSocket);
+
+/// Routes requests to handlers, based on the method and path.
+///
+/// Patterns are matched segment by segment. A segment starting with `:`
+/// matches any single segment and a segment starting with `*` matches the
+/// rest of the path. The matched values are available with
+/// [`Request::param`]. Routes are tried in the order they were added.
+///
+/// If the path matches a route, but the method doesn't, a
+/// `405 Method Not Allowed` response is returned. `HEAD` requests are handled
+/// by `GET` routes if there is no explicit `HEAD` route. If no route matches,
+/// the [`fallback`](Router::fallback) handler is called, responding with
+/// `404 Not Found` by default.
+///
+/// Handlers are function pointers, so that the router can be sent to the
+/// process handling a connection.
+///
+/// # Example
+///
+/// ```
+/// use lunatic::http::{Response, Router};
+///
+/// let router = Router::new()
+///     .get("/", |_| Response::text(200, "Hello!"))
+///     .get("/users/:id", |request| {
+///         Response::text(200, format!("User {}", request.param("id").unwrap()))
+///     })
+///     .get("/static/*path", |request| {
+///         Response::text(200, format!("File {}", request.param("path").unwrap()))
+///     });
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Router {
+    routes: Vec<Route>,
+    fallback: Option<FuncRef<Handler>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Route {
+    /// `None` matches any method.
+    method: Option<Method>,
+    segments: Vec<Segment>,
+    endpoint: Endpoint,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+enum Endpoint {
+    Http(FuncRef<Handler>),
+    WebSocket(FuncRef<WebSocketHandler>),
+}
+
+/// Result of routing a request.
+pub(crate) enum Routed {
+    Response(Response),
+    /// The request should be upgraded to a WebSocket and passed to the
+    /// handler.
+    Upgrade(Request, FuncRef<WebSocketHandler>),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+enum Segment {
+    Exact(String),
+    Param(String),
+    Rest(String),
+}
+
+impl Router {
+    /// Creates a router without routes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a route for requests with the `method` matching `pattern`.
+    #[must_use]
+    pub fn route(self, method: Method, pattern: &str, handler: Handler) -> Self {
+        self.add(Some(method), pattern, Endpoint::Http(FuncRef::new(handler)))
+    }
+
+    /// Adds a route for requests with any method matching `pattern`.
+    #[must_use]
+    pub fn any(self, pattern: &str, handler: Handler) -> Self {
+        self.add(None, pattern, Endpoint::Http(FuncRef::new(handler)))
+    }
+
+    /// Adds a route for `GET` requests.
+    #[must_use]
+    pub fn get(self, pattern: &str, handler: Handler) -> Self {
+        self.route(Method::Get, pattern, handler)
+    }
+
+    /// Adds a route for `POST` requests.
+    #[must_use]
+    pub fn post(self, pattern: &str, handler: Handler) -> Self {
+        self.route(Method::Post, pattern, handler)
+    }
+
+    /// Adds a route for `PUT` requests.
+    #[must_use]
+    pub fn put(self, pattern: &str, handler: Handler) -> Self {
+        self.route(Method::Put, pattern, handler)
+    }
+
+    /// Adds a route for `PATCH` requests.
+    #[must_use]
+    pub fn patch(self, pattern: &str, handler: Handler) -> Self {
+        self.route(Method::Patch, pattern, handler)
+    }
+
+    /// Adds a route for `DELETE` requests.
+    #[must_use]
+    pub fn delete(self, pattern: &str, handler: Handler) -> Self {
+        self.route(Method::Delete, pattern, handler)
+    }
+
+    /// Adds a route upgrading `GET` requests to WebSocket connections.
+    ///
+    /// The handler runs in the process of the connection, which is closed
+    /// once the handler returns.
+    #[must_use]
+    pub fn websocket(self, pattern: &str, handler: WebSocketHandler) -> Self {
+        self.add(
+            Some(Method::Get),
+            pattern,
+            Endpoint::WebSocket(FuncRef::new(handler)),
+        )
+    }
+
+    /// Sets the handler called if no route matches.
+    #[must_use]
+    pub fn fallback(mut self, handler: Handler) -> Self {
+        self.fallback = Some(FuncRef::new(handler));
+        self
+    }
+
+    fn add(mut self, method: Option<Method>, pattern: &str, endpoint: Endpoint) -> Self {
+        let segments = split(pattern)
+            .map(|segment| {
+                if let Some(name) = segment.strip_prefix(':') {
+                    Segment::Param(name.to_owned())
+                } else if let Some(name) = segment.strip_prefix('*') {
+                    Segment::Rest(name.to_owned())
+                } else {
+                    Segment::Exact(segment.to_owned())
+                }
+            })
+            .collect();
+        self.routes.push(Route {
+            method,
+            segments,
+            endpoint,
+        });
+        self
+    }
+
+    /// Calls the handler matching the request.
+    ///
+    /// WebSocket routes can only be handled by the [`Server`](super::Server)
+    /// and respond with `426 Upgrade Required`.
+    pub fn handle(&self, request: Request) -> Response {
+        match self.dispatch(request) {
+            Routed::Response(response) => response,
+            Routed::Upgrade(..) => {
+                Response::text(426, "Upgrade Required").with_header("Upgrade", "websocket")
+            }
+        }
+    }
+
+    /// Calls the HTTP handler matching the request, or returns the WebSocket
+    /// handler.
+    pub(crate) fn dispatch(&self, mut request: Request) -> Routed {
+        let path: Vec<&str> = split(request.path()).collect();
+        let mut allowed = Vec::new();
+        let mut get_route = None;
+        for route in self.routes.iter() {
+            let params = match route.matches(&path) {
+                Some(params) => params,
+                None => continue,
+            };
+            match &route.method {
+                Some(method) if method != request.method() => {
+                    if *method == Method::Get && *request.method() == Method::Head {
+                        get_route = get_route.or(Some((route, params)));
+                    }
+                    allowed.push(method.as_str());
+                }
+                _ => {
+                    request.set_params(params);
+                    return route.call(request);
+                }
+            }
+        }
+        if let Some((route, params)) = get_route {
+            request.set_params(params);
+            return route.call(request);
+        }
+        if !allowed.is_empty() {
+            return Routed::Response(Response::new(405).with_header("Allow", allowed.join(", ")));
+        }
+        Routed::Response(match &self.fallback {
+            Some(fallback) => fallback(request),
+            None => Response::text(404, "Not Found"),
+        })
+    }
+}
+
+impl Route {
+    fn call(&self, request: Request) -> Routed {
+        match &self.endpoint {
+            Endpoint::Http(handler) => Routed::Response(handler(request)),
+            Endpoint::WebSocket(handler) => Routed::Upgrade(request, *handler),
+        }
+    }
+
+    /// Returns the matched parameters if the route matches the path.
+    fn matches(&self, path: &[&str]) -> Option<BTreeMap<String, String>> {
+        let mut params = BTreeMap::new();
+        let mut path = path.iter();
+        for segment in self.segments.iter() {
+            match segment {
+                Segment::Rest(name) => {
+                    let rest: Vec<&str> = path.by_ref().copied().collect();
+                    params.insert(name.clone(), percent_decode(&rest.join("/")));
+                    return Some(params);
+                }
+                Segment::Exact(exact) => {
+                    if percent_decode(path.next()?) != *exact {
+                        return None;
+                    }
+                }
+                Segment::Param(name) => {
+                    params.insert(name.clone(), percent_decode(path.next()?));
+                }
+            }
+        }
+        if path.next().is_some() {
+            return None;
+        }
+        Some(params)
+    }
+}
+
+fn split(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
@@ -0,0 +1,316 @@
+use std::io::{self, BufReader};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use super::parse::{self, Limits};
+use super::router::Routed;
+use super::{websocket, HttpError, Method, Response, Router, Stream};
+use crate::net::{TcpListener, TcpStream, TlsListener, TlsStream, ToSocketAddrs};
+use crate::panic::catch_panic;
+use crate::{host, Mailbox, Process, Tag};
+
+/// A listener the [`Server`] accepts connections from.
+#[derive(Debug)]
+pub enum Listener {
+    Tcp(TcpListener),
+    Tls(TlsListener),
+}
+
+impl From<TcpListener> for Listener {
+    fn from(listener: TcpListener) -> Self {
+        Listener::Tcp(listener)
+    }
+}
+
+impl From<TlsListener> for Listener {
+    fn from(listener: TlsListener) -> Self {
+        Listener::Tls(listener)
+    }
+}
+
+impl Listener {
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        match self {
+            Listener::Tcp(listener) => listener.local_addr(),
+            Listener::Tls(listener) => listener.local_addr(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+struct Config {
+    max_head_size: usize,
+    max_body_size: usize,
+    keep_alive_timeout: Duration,
+    shutdown_timeout: Duration,
+}
+
+impl Config {
+    fn limits(&self) -> Limits {
+        Limits {
+            max_head_size: self.max_head_size,
+            max_body_size: self.max_body_size,
+        }
+    }
+}
+
+/// An HTTP/1.1 server.
+///
+/// Each connection is handled by a new process, which reads requests, passes
+/// them to the [`Router`] and writes the responses back. Connections are kept
+/// alive between requests, unless the client asks to close them.
+///
+/// Replacing the [`TcpListener`] with a [`TlsListener`] serves the same
+/// router over HTTPS.
+///
+/// # Example
+///
+/// ```no_run
+/// use lunatic::http::{Response, Router, Server};
+///
+/// let router = Router::new().get("/", |_| Response::text(200, "Hello!"));
+/// Server::bind("127.0.0.1:3000", router).unwrap().serve().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct Server {
+    listener: Listener,
+    router: Router,
+    config: Config,
+    shutdown_tag: Tag,
+}
+
+impl Server {
+    /// Creates a server listening on `addr`.
+    pub fn bind<A: ToSocketAddrs>(addr: A, router: Router) -> io::Result<Self> {
+        Ok(Self::new(TcpListener::bind(addr)?, router))
+    }
+
+    /// Creates a server accepting connections from `listener`.
+    pub fn new(listener: impl Into<Listener>, router: Router) -> Self {
+        Self {
+            listener: listener.into(),
+            router,
+            config: Config {
+                max_head_size: 16 * 1024,
+                max_body_size: 2 * 1024 * 1024,
+                keep_alive_timeout: Duration::from_secs(5),
+                shutdown_timeout: Duration::from_secs(10),
+            },
+            shutdown_tag: Tag::new(),
+        }
+    }
+
+    /// Sets the maximum size of the request line and headers. Defaults to
+    /// 16 KiB.
+    #[must_use]
+    pub fn max_head_size(mut self, size: usize) -> Self {
+        self.config.max_head_size = size;
+        self
+    }
+
+    /// Sets the maximum size of request bodies. Defaults to 2 MiB.
+    #[must_use]
+    pub fn max_body_size(mut self, size: usize) -> Self {
+        self.config.max_body_size = size;
+        self
+    }
+
+    /// Sets how long idle connections are kept open. Defaults to 5 seconds.
+    #[must_use]
+    pub fn keep_alive_timeout(mut self, timeout: Duration) -> Self {
+        self.config.keep_alive_timeout = timeout;
+        self
+    }
+
+    /// Sets how long to wait for open connections to finish during a
+    /// graceful shutdown, before killing them. Defaults to 10 seconds.
+    #[must_use]
+    pub fn shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.config.shutdown_timeout = timeout;
+        self
+    }
+
+    /// Returns the local address the server is listening on.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Returns a handle that can be used by other processes to shut the
+    /// server down.
+    ///
+    /// Needs to be called from the process that will call
+    /// [`serve`](Self::serve).
+    pub fn shutdown_handle(&self) -> io::Result<ShutdownHandle> {
+        let mut addr = self.local_addr()?;
+        // Connect to the loopback address if listening on all interfaces.
+        match addr.ip() {
+            IpAddr::V4(ip) if ip.is_unspecified() => addr.set_ip(Ipv4Addr::LOCALHOST.into()),
+            IpAddr::V6(ip) if ip.is_unspecified() => addr.set_ip(Ipv6Addr::LOCALHOST.into()),
+            _ => {}
+        }
+        Ok(ShutdownHandle {
+            server: unsafe { Process::new(host::node_id(), host::process_id()) },
+            tag: self.shutdown_tag,
+            addr,
+        })
+    }
+
+    /// Accepts connections until the server is shut down with a
+    /// [`ShutdownHandle`].
+    ///
+    /// On shutdown, the server stops accepting connections and waits for the
+    /// open ones to finish their current request.
+    pub fn serve(self) -> io::Result<()> {
+        let this = unsafe { Process::<u64>::new(host::node_id(), host::process_id()) };
+        let done_tag = Tag::new();
+        // Temporarily cast to right mailbox types.
+        let done: Mailbox<u64> = unsafe { Mailbox::new() };
+        let shutdown: Mailbox<()> = unsafe { Mailbox::new() };
+        let mut connections: Vec<Process<()>> = Vec::new();
+
+        loop {
+            let accepted = match &self.listener {
+                Listener::Tcp(listener) => listener.accept().map(|(stream, _)| {
+                    Process::spawn(
+                        (stream, self.router.clone(), this, done_tag, self.config),
+                        connection::<TcpStream>,
+                    )
+                }),
+                Listener::Tls(listener) => listener.accept().map(|(stream, _)| {
+                    Process::spawn(
+                        (stream, self.router.clone(), this, done_tag, self.config),
+                        connection::<TlsStream>,
+                    )
+                }),
+            };
+            while let Ok(id) = done.tag_receive_timeout(&[done_tag], Duration::ZERO) {
+                connections.retain(|connection| connection.id() != id);
+            }
+            match accepted {
+                Ok(connection) => connections.push(connection),
+                // Failed TLS handshakes shouldn't stop the server.
+                Err(err) if matches!(self.listener, Listener::Tcp(_)) => return Err(err),
+                Err(_) => {}
+            }
+            if shutdown
+                .tag_receive_timeout(&[self.shutdown_tag], Duration::ZERO)
+                .is_ok()
+            {
+                break;
+            }
+        }
+        drop(self.listener);
+
+        // Let open connections finish their current request.
+        for connection in connections.iter() {
+            connection.send(());
+        }
+        let deadline = Instant::now() + self.config.shutdown_timeout;
+        while !connections.is_empty() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match done.tag_receive_timeout(&[done_tag], remaining) {
+                Ok(id) => connections.retain(|connection| connection.id() != id),
+                Err(_) => break,
+            }
+        }
+        for connection in connections {
+            connection.kill();
+        }
+        Ok(())
+    }
+}
+
+/// A handle to gracefully shut down a [`Server`].
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ShutdownHandle {
+    server: Process<()>,
+    tag: Tag,
+    addr: SocketAddr,
+}
+
+impl ShutdownHandle {
+    /// Starts the graceful shutdown of the server.
+    ///
+    /// Returns right away, without waiting for the server to stop.
+    pub fn shutdown(&self) {
+        self.server.tag_send(self.tag, ());
+        // Wake up the server if it's waiting on a new connection.
+        let _ = TcpStream::connect(self.addr);
+    }
+}
+
+/// Entry point of the process handling a connection.
+///
+/// Receiving a message in the mailbox closes the connection after the
+/// current request.
+fn connection<S: Stream>(
+    (mut stream, router, server, done_tag, config): (S, Router, Process<u64>, Tag, Config),
+    mailbox: Mailbox<()>,
+) {
+    let _ = stream.set_read_timeout(Some(config.keep_alive_timeout));
+    let mut reader = BufReader::new(stream.clone());
+    loop {
+        let mut request = match parse::read_request_head(&mut reader, config.limits()) {
+            Ok(Some(request)) => request,
+            Ok(None) | Err(HttpError::Io(_)) | Err(HttpError::ConnectionClosed) => break,
+            Err(err) => {
+                let _ = write_error(&mut stream, &err);
+                break;
+            }
+        };
+        if request.headers().has_token("Expect", "100-continue") {
+            let _ = parse::write_response(&mut stream, &Response::new(100), false);
+        }
+        match parse::read_request_body(&mut reader, &mut request, config.limits()) {
+            Ok(()) => {}
+            Err(HttpError::Io(_)) | Err(HttpError::ConnectionClosed) => break,
+            Err(err) => {
+                let _ = write_error(&mut stream, &err);
+                break;
+            }
+        }
+
+        let close = !request.keep_alive() || mailbox.receive_timeout(Duration::ZERO).is_ok();
+        let include_body = *request.method() != Method::Head;
+        let routed = catch_panic(|| router.dispatch(request))
+            .unwrap_or_else(|_| Routed::Response(Response::text(500, "Internal Server Error")));
+        let mut response = match routed {
+            Routed::Response(response) => response,
+            // The client must wait for the handshake before sending frames.
+            Routed::Upgrade(..) if !reader.buffer().is_empty() => {
+                Response::text(400, "Unexpected data after upgrade request")
+            }
+            Routed::Upgrade(request, handler) => {
+                match websocket::upgrade(stream.clone().into(), &request) {
+                    Ok(websocket) => {
+                        let _ = catch_panic(|| handler(request, websocket));
+                        break;
+                    }
+                    Err(response) => response,
+                }
+            }
+        };
+        if close {
+            response.headers_mut().insert("Connection", "close");
+        }
+        // Handlers can also close the connection.
+        let close = close || !response.keep_alive();
+        if parse::write_response(&mut stream, &response, include_body).is_err() || close {
+            break;
+        }
+    }
+    server.tag_send(done_tag, host::process_id());
+}
+
+/// Responds to a request that couldn't be read.
+fn write_error<S: Stream>(stream: &mut S, err: &HttpError) -> Result<(), HttpError> {
+    let status = match err {
+        HttpError::HeadersTooLarge => 431,
+        HttpError::BodyTooLarge => 413,
+        _ => 400,
+    };
+    let response = Response::text(status, err.to_string()).with_header("Connection", "close");
+    parse::write_response(stream, &response, true)
+}
@@ -0,0 +1,574 @@
+//! WebSocket connections ([RFC 6455](https://www.rfc-editor.org/rfc/rfc6455)).
+//!
+//! A [`WebSocket`] is created either on the server side, by upgrading an HTTP
+//! request, or on the client side with [`WebSocket::connect`]. Routes added
+//! with [`Router::websocket`](super::Router::websocket) are upgraded by the
+//! [`Server`](super::Server) automatically.
+//!
+//! Like a [`TcpStream`](crate::net::TcpStream), a [`WebSocket`] can be cloned
+//! and sent to other processes, so that reading and writing can happen in
+//! separate processes.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, BufReader, Read, Write};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::client::Url;
+use super::parse::{self, Limits};
+use super::{Connection, HttpError, Method, Request, Response, Stream};
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xA;
+
+/// Appended to the client's key before hashing it, as defined by the RFC.
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// How long to wait for the connection to be established by
+/// [`WebSocket::connect`].
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Size limits of the handshake.
+const HANDSHAKE_LIMITS: Limits = Limits {
+    max_head_size: 16 * 1024,
+    max_body_size: 0,
+};
+
+/// A message sent over a [`WebSocket`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    /// Pings are answered automatically, but still returned by
+    /// [`WebSocket::receive`].
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    /// Starts or answers the closing handshake.
+    Close(Option<CloseFrame>),
+}
+
+/// The status code and reason sent with a [`Message::Close`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CloseFrame {
+    pub code: u16,
+    pub reason: String,
+}
+
+/// Error communicating over a [`WebSocket`].
+#[derive(Error, Debug)]
+pub enum WebSocketError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("HTTP error: {0}")]
+    Http(#[from] HttpError),
+    #[error("handshake failed: {0}")]
+    Handshake(String),
+    #[error("protocol error: {0}")]
+    Protocol(String),
+    #[error("message too large")]
+    MessageTooLarge,
+    #[error("connection closed")]
+    ConnectionClosed,
+}
+
+/// A WebSocket connection.
+///
+/// Clones share the connection, but each clone tracks the closing handshake
+/// on its own. A clone only knows about close frames it sent or received
+/// itself. Pongs and the close frame answered automatically by
+/// [`receive`](Self::receive) are written by the receiving clone, without the
+/// other clones noticing.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WebSocket {
+    connection: Connection,
+    /// Clients mask the frames they send, servers don't.
+    client: bool,
+    max_message_size: usize,
+    max_frame_size: usize,
+    /// Opcode and payload of a fragmented message that is being received.
+    partial: Option<(u8, Vec<u8>)>,
+    /// Whether this clone sent a close frame.
+    close_sent: bool,
+    /// Whether this clone received a close frame.
+    close_received: bool,
+}
+
+impl WebSocket {
+    fn new(connection: Connection, client: bool) -> Self {
+        Self {
+            connection,
+            client,
+            max_message_size: 16 * 1024 * 1024,
+            max_frame_size: 64 * 1024,
+            partial: None,
+            close_sent: false,
+            close_received: false,
+        }
+    }
+
+    /// Connects to a `ws` or `wss` URL.
+    pub fn connect(url: &str) -> Result<Self, WebSocketError> {
+        Self::connect_request(Request::new(Method::Get, url))
+    }
+
+    /// Connects to the `ws` or `wss` URL in the request target, sending the
+    /// headers of the request with the handshake.
+    pub fn connect_request(request: Request) -> Result<Self, WebSocketError> {
+        let target = request.target();
+        let http_url = if let Some(rest) = target.strip_prefix("ws://") {
+            format!("http://{rest}")
+        } else if let Some(rest) = target.strip_prefix("wss://") {
+            format!("https://{rest}")
+        } else {
+            return Err(HttpError::InvalidUrl(target.to_owned()).into());
+        };
+        let url = Url::parse(&http_url)?;
+        let key = base64_encode(&random_bytes::<16>());
+
+        let mut handshake = Request::new(Method::Get, url.path.clone());
+        *handshake.headers_mut() = request.headers().clone();
+        let headers = handshake.headers_mut();
+        if !headers.contains("Host") {
+            headers.insert("Host", url.authority());
+        }
+        headers.insert("Upgrade", "websocket");
+        headers.insert("Connection", "Upgrade");
+        headers.insert("Sec-WebSocket-Key", key.clone());
+        headers.insert("Sec-WebSocket-Version", "13");
+
+        let mut connection = Connection::connect(&url, CONNECT_TIMEOUT, &[])?;
+        parse::write_request(&mut connection, &handshake)?;
+        // Read byte by byte, so that no frames sent right after the response
+        // end up in the buffer.
+        let mut reader = BufReader::with_capacity(1, connection.clone());
+        let response = parse::read_response(&mut reader, &Method::Get, HANDSHAKE_LIMITS)?;
+        if response.status() != 101 {
+            return Err(WebSocketError::Handshake(format!(
+                "unexpected status {}",
+                response.status()
+            )));
+        }
+        if !response.headers().has_token("Upgrade", "websocket")
+            || !response.headers().has_token("Connection", "upgrade")
+        {
+            return Err(WebSocketError::Handshake(
+                "connection was not upgraded".to_owned(),
+            ));
+        }
+        if response.header("Sec-WebSocket-Accept") != Some(accept_key(&key).as_str()) {
+            return Err(WebSocketError::Handshake(
+                "invalid `Sec-WebSocket-Accept` header".to_owned(),
+            ));
+        }
+        Ok(Self::new(connection, true))
+    }
+
+    /// Reads the upgrade request from `stream` and completes the handshake.
+    ///
+    /// Returns the WebSocket and the request, so that its path and headers can
+    /// be inspected. Invalid requests are answered with an error response.
+    pub fn accept(stream: impl Into<Connection>) -> Result<(Self, Request), WebSocketError> {
+        let mut connection = stream.into();
+        let mut reader = BufReader::with_capacity(1, connection.clone());
+        let request = parse::read_request_head(&mut reader, HANDSHAKE_LIMITS)?
+            .ok_or(WebSocketError::ConnectionClosed)?;
+        match upgrade(connection.clone(), &request) {
+            Ok(websocket) => Ok((websocket, request)),
+            Err(response) => {
+                parse::write_response(&mut connection, &response, true)?;
+                Err(WebSocketError::Handshake(
+                    String::from_utf8_lossy(response.body()).into_owned(),
+                ))
+            }
+        }
+    }
+
+    /// Sets the read timeout of the underlying stream.
+    ///
+    /// If the timeout expires, [`receive`](Self::receive) fails with an I/O
+    /// error of the kind [`TimedOut`](io::ErrorKind::TimedOut).
+    pub fn set_read_timeout(&mut self, duration: Option<Duration>) -> io::Result<()> {
+        self.connection.set_read_timeout(duration)
+    }
+
+    /// Sets the maximum size of received messages. Defaults to 16 MiB.
+    pub fn set_max_message_size(&mut self, size: usize) {
+        self.max_message_size = size;
+    }
+
+    /// Sets the size above which sent messages are split into multiple
+    /// frames. Defaults to 64 KiB.
+    pub fn set_max_frame_size(&mut self, size: usize) {
+        self.max_frame_size = size.max(1);
+    }
+
+    /// Sends a message.
+    ///
+    /// Sending a [`Message::Close`] starts the closing handshake, after which
+    /// no more messages can be sent.
+    pub fn send(&mut self, message: Message) -> Result<(), WebSocketError> {
+        if self.close_sent {
+            return Err(WebSocketError::ConnectionClosed);
+        }
+        match message {
+            Message::Text(text) => self.write_message(OP_TEXT, text.as_bytes()),
+            Message::Binary(data) => self.write_message(OP_BINARY, &data),
+            Message::Ping(data) => self.write_control(OP_PING, &data),
+            Message::Pong(data) => self.write_control(OP_PONG, &data),
+            Message::Close(frame) => {
+                let payload = match frame {
+                    Some(frame) => {
+                        let mut payload = frame.code.to_be_bytes().to_vec();
+                        payload.extend_from_slice(frame.reason.as_bytes());
+                        payload
+                    }
+                    None => Vec::new(),
+                };
+                self.write_control(OP_CLOSE, &payload)?;
+                self.close_sent = true;
+                Ok(())
+            }
+        }
+    }
+
+    /// Receives the next message, waiting for all fragments of it.
+    ///
+    /// Pings are answered with a pong before being returned. A received
+    /// [`Message::Close`] is answered automatically, after which the
+    /// connection should be dropped.
+    pub fn receive(&mut self) -> Result<Message, WebSocketError> {
+        if self.close_received {
+            return Err(WebSocketError::ConnectionClosed);
+        }
+        loop {
+            let (fin, opcode, payload) = self.read_frame()?;
+            match opcode {
+                OP_PING | OP_PONG | OP_CLOSE if !fin || payload.len() > 125 => {
+                    return Err(WebSocketError::Protocol("invalid control frame".to_owned()));
+                }
+                OP_PING => {
+                    if !self.close_sent {
+                        self.write_control(OP_PONG, &payload)?;
+                    }
+                    return Ok(Message::Ping(payload));
+                }
+                OP_PONG => return Ok(Message::Pong(payload)),
+                OP_CLOSE => {
+                    self.close_received = true;
+                    let frame = parse_close(&payload)?;
+                    if !self.close_sent {
+                        // Echo the status code, as recommended by the RFC.
+                        self.write_control(OP_CLOSE, payload.get(..2).unwrap_or_default())?;
+                        self.close_sent = true;
+                    }
+                    return Ok(Message::Close(frame));
+                }
+                OP_TEXT | OP_BINARY if self.partial.is_some() => {
+                    return Err(WebSocketError::Protocol(
+                        "expected a continuation frame".to_owned(),
+                    ));
+                }
+                OP_TEXT | OP_BINARY if fin => return finish(opcode, payload),
+                OP_TEXT | OP_BINARY => self.partial = Some((opcode, payload)),
+                OP_CONTINUATION => {
+                    let (opcode, mut data) = self.partial.take().ok_or_else(|| {
+                        WebSocketError::Protocol("unexpected continuation frame".to_owned())
+                    })?;
+                    if data.len() + payload.len() > self.max_message_size {
+                        return Err(WebSocketError::MessageTooLarge);
+                    }
+                    data.extend_from_slice(&payload);
+                    if fin {
+                        return finish(opcode, data);
+                    }
+                    self.partial = Some((opcode, data));
+                }
+                opcode => {
+                    return Err(WebSocketError::Protocol(format!(
+                        "unknown opcode {opcode:#x}"
+                    )));
+                }
+            }
+        }
+    }
+
+    /// Performs the closing handshake.
+    ///
+    /// Sends a [`Message::Close`] and discards incoming messages until the
+    /// other side answers it.
+    pub fn close(&mut self, frame: Option<CloseFrame>) -> Result<(), WebSocketError> {
+        if !self.close_sent {
+            self.send(Message::Close(frame))?;
+        }
+        while !self.close_received {
+            match self.receive() {
+                Ok(_) => {}
+                Err(WebSocketError::ConnectionClosed) => break,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    fn read_frame(&mut self) -> Result<(bool, u8, Vec<u8>), WebSocketError> {
+        let mut head = [0; 2];
+        self.read_exact(&mut head)?;
+        let fin = head[0] & 0x80 != 0;
+        if head[0] & 0x70 != 0 {
+            return Err(WebSocketError::Protocol("reserved bits set".to_owned()));
+        }
+        let opcode = head[0] & 0x0F;
+        let masked = head[1] & 0x80 != 0;
+        // Only frames sent by clients are masked.
+        if masked == self.client {
+            return Err(WebSocketError::Protocol("invalid masking".to_owned()));
+        }
+        let length = match head[1] & 0x7F {
+            126 => {
+                let mut length = [0; 2];
+                self.read_exact(&mut length)?;
+                u16::from_be_bytes(length) as u64
+            }
+            127 => {
+                let mut length = [0; 8];
+                self.read_exact(&mut length)?;
+                u64::from_be_bytes(length)
+            }
+            length => length as u64,
+        };
+        if length > self.max_message_size as u64 {
+            return Err(WebSocketError::MessageTooLarge);
+        }
+        let mut mask = [0; 4];
+        if masked {
+            self.read_exact(&mut mask)?;
+        }
+        let mut payload = vec![0; length as usize];
+        self.read_exact(&mut payload)?;
+        if masked {
+            apply_mask(&mut payload, mask);
+        }
+        Ok((fin, opcode, payload))
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), WebSocketError> {
+        self.connection
+            .read_exact(buf)
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::UnexpectedEof => WebSocketError::ConnectionClosed,
+                _ => err.into(),
+            })
+    }
+
+    /// Writes a data message, split into frames of at most `max_frame_size`.
+    fn write_message(&mut self, opcode: u8, payload: &[u8]) -> Result<(), WebSocketError> {
+        let mut chunks = payload.chunks(self.max_frame_size).peekable();
+        let mut opcode = opcode;
+        if chunks.peek().is_none() {
+            return self.write_frame(true, opcode, &[]);
+        }
+        while let Some(chunk) = chunks.next() {
+            self.write_frame(chunks.peek().is_none(), opcode, chunk)?;
+            opcode = OP_CONTINUATION;
+        }
+        Ok(())
+    }
+
+    fn write_control(&mut self, opcode: u8, payload: &[u8]) -> Result<(), WebSocketError> {
+        if payload.len() > 125 {
+            return Err(WebSocketError::Protocol(
+                "control frame payload too large".to_owned(),
+            ));
+        }
+        self.write_frame(true, opcode, payload)
+    }
+
+    fn write_frame(&mut self, fin: bool, opcode: u8, payload: &[u8]) -> Result<(), WebSocketError> {
+        let mut frame = Vec::with_capacity(payload.len() + 14);
+        frame.push(if fin { 0x80 } else { 0 } | opcode);
+        let mask_bit = if self.client { 0x80 } else { 0 };
+        match payload.len() {
+            length @ 0..=125 => frame.push(mask_bit | length as u8),
+            length @ 126..=0xFFFF => {
+                frame.push(mask_bit | 126);
+                frame.extend_from_slice(&(length as u16).to_be_bytes());
+            }
+            length => {
+                frame.push(mask_bit | 127);
+                frame.extend_from_slice(&(length as u64).to_be_bytes());
+            }
+        }
+        if self.client {
+            let mask = random_bytes::<4>();
+            frame.extend_from_slice(&mask);
+            let start = frame.len();
+            frame.extend_from_slice(payload);
+            apply_mask(&mut frame[start..], mask);
+        } else {
+            frame.extend_from_slice(payload);
+        }
+        self.connection.write_all(&frame)?;
+        self.connection.flush()?;
+        Ok(())
+    }
+}
+
+/// Completes the handshake of an upgrade request that was already read.
+///
+/// Returns the response to send if the request is not a valid upgrade
+/// request.
+pub(crate) fn upgrade(
+    mut connection: Connection,
+    request: &Request,
+) -> Result<WebSocket, Response> {
+    let headers = request.headers();
+    if *request.method() != Method::Get
+        || !headers.has_token("Upgrade", "websocket")
+        || !headers.has_token("Connection", "upgrade")
+    {
+        return Err(Response::text(400, "Expected a WebSocket upgrade request")
+            .with_header("Connection", "close"));
+    }
+    if headers.get("Sec-WebSocket-Version") != Some("13") {
+        return Err(Response::text(426, "Unsupported WebSocket version")
+            .with_header("Sec-WebSocket-Version", "13")
+            .with_header("Connection", "close"));
+    }
+    let key = match headers.get("Sec-WebSocket-Key") {
+        Some(key) => key,
+        None => {
+            return Err(Response::text(400, "Missing `Sec-WebSocket-Key` header")
+                .with_header("Connection", "close"))
+        }
+    };
+    let response = Response::new(101)
+        .with_header("Upgrade", "websocket")
+        .with_header("Connection", "Upgrade")
+        .with_header("Sec-WebSocket-Accept", accept_key(key));
+    if parse::write_response(&mut connection, &response, false).is_err() {
+        return Err(Response::new(500));
+    }
+    Ok(WebSocket::new(connection, false))
+}
+
+fn finish(opcode: u8, payload: Vec<u8>) -> Result<Message, WebSocketError> {
+    match opcode {
+        OP_TEXT => String::from_utf8(payload)
+            .map(Message::Text)
+            .map_err(|_| WebSocketError::Protocol("text is not valid UTF-8".to_owned())),
+        _ => Ok(Message::Binary(payload)),
+    }
+}
+
+fn parse_close(payload: &[u8]) -> Result<Option<CloseFrame>, WebSocketError> {
+    match payload {
+        [] => Ok(None),
+        [high, low, reason @ ..] => {
+            let reason = std::str::from_utf8(reason).map_err(|_| {
+                WebSocketError::Protocol("close reason is not valid UTF-8".to_owned())
+            })?;
+            Ok(Some(CloseFrame {
+                code: u16::from_be_bytes([*high, *low]),
+                reason: reason.to_owned(),
+            }))
+        }
+        _ => Err(WebSocketError::Protocol("invalid close frame".to_owned())),
+    }
+}
+
+fn apply_mask(payload: &mut [u8], mask: [u8; 4]) {
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+}
+
+/// Returns the `Sec-WebSocket-Accept` value for the client's `key`.
+fn accept_key(key: &str) -> String {
+    base64_encode(&sha1(format!("{key}{GUID}").as_bytes()))
+}
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0; N];
+    for chunk in bytes.chunks_mut(8) {
+        // `RandomState` is seeded from the host's source of randomness.
+        let random = RandomState::new().build_hasher().finish().to_le_bytes();
+        chunk.copy_from_slice(&random[..chunk.len()]);
+    }
+    bytes
+}
+
+fn base64_encode(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut output = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+/// SHA-1, only used for the handshake.
+fn sha1(input: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut message = input.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(input.len() as u64 * 8).to_be_bytes());
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (h, value) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(value);
+        }
+    }
+    let mut digest = [0; 20];
+    for (chunk, value) in digest.chunks_mut(4).zip(h) {
+        chunk.copy_from_slice(&value.to_be_bytes());
+    }
+    digest
+}
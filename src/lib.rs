@@ -110,10 +110,13 @@ pub mod ap;
 pub mod distributed;
 pub mod function;
 pub mod host;
+pub mod http;
 pub mod metrics;
 pub mod net;
 pub mod panic;
+pub mod pg;
 pub mod protocol;
+pub mod registry;
 pub mod serializer;
 pub mod supervisor;
 #[doc(hidden)]
@@ -136,6 +139,8 @@ pub use mailbox::{
 };
 pub use module::{Param, WasmModule};
 #[doc(hidden)]
+pub use paste::paste as __paste;
+#[doc(hidden)]
 pub use process_local::statik::Key as __StaticProcessLocalInner;
 pub use process_local::ProcessLocal;
 pub use process_name::ProcessName;
@@ -5,6 +5,7 @@ use std::time::Duration;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::distributed::monitor;
 use crate::function::process::{IntoProcess, NoLink};
 use crate::host::api::message;
 use crate::serializer::{Bincode, CanSerialize, DecodeError};
@@ -232,14 +233,17 @@ where
         }
     }
 
-    /// Starts monitoring a process, .
+    /// Starts monitoring a process.
+    ///
+    /// Processes on other nodes are only reported dead when the node goes
+    /// down.
     pub fn monitor<T, U>(&self, process: Process<T, U>) {
-        unsafe { host::api::process::monitor(process.id()) };
+        monitor::monitor(process.node_id(), process.id());
     }
 
     /// Stop monitoring a process.
     pub fn stop_monitoring<T, U>(&self, process: Process<T, U>) {
-        unsafe { host::api::process::stop_monitoring(process.id()) };
+        monitor::stop_monitoring(process.node_id(), process.id());
     }
 }
 
@@ -281,9 +285,9 @@ where
             LINK_DIED => Ok(MessageSignal::Signal(Signal::LinkDied(unsafe {
                 Tag::from(message::get_tag())
             }))),
-            PROCESS_DIED => Ok(MessageSignal::Signal(Signal::ProcessDied(unsafe {
-                message::get_process_id()
-            }))),
+            PROCESS_DIED => Ok(MessageSignal::Signal(Signal::ProcessDied(
+                monitor::dead_process_id(unsafe { message::get_process_id() }),
+            ))),
             TIMEOUT => Err(MailboxError::TimedOut),
             _ => panic!("unknown message type: {message_type}"),
         }
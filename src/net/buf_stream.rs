@@ -0,0 +1,180 @@
+use std::fmt;
+use std::io::{BufRead, IoSlice, Read, Result, Write};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{TcpStream, TlsStream};
+
+/// Default capacity of the read buffer (8 KiB).
+const DEFAULT_CAPACITY: usize = 8 * 1024;
+
+/// A [`TcpStream`] with a read buffer that can be sent to other processes.
+pub type BufTcpStream = BufStream<TcpStream>;
+/// A [`TlsStream`] with a read buffer that can be sent to other processes.
+pub type BufTlsStream = BufStream<TlsStream>;
+
+/// Adds a read buffer to a stream, like [`BufReader`](std::io::BufReader),
+/// while keeping the stream serializable.
+///
+/// Wrapping a stream in a `BufReader` and sending the stream to another
+/// process loses the bytes that were already read into the buffer. A
+/// `BufStream` is serialized together with its buffered bytes, so that a
+/// connection can be handed to another process in the middle of a protocol.
+///
+/// Writes go directly to the underlying stream.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::io::BufRead;
+///
+/// use lunatic::net::{BufTcpStream, TcpStream};
+/// use lunatic::{Mailbox, Process};
+///
+/// let stream = TcpStream::connect("127.0.0.1:3000").unwrap();
+/// let mut stream = BufTcpStream::new(stream);
+/// let mut greeting = String::new();
+/// stream.read_line(&mut greeting).unwrap();
+/// // Bytes received after the greeting are sent along with the stream.
+/// Process::spawn(stream, |mut stream, _: Mailbox<()>| {
+///     let mut line = String::new();
+///     stream.read_line(&mut line).unwrap();
+/// });
+/// ```
+pub struct BufStream<S> {
+    stream: S,
+    buf: Box<[u8]>,
+    pos: usize,
+    filled: usize,
+}
+
+impl<S> BufStream<S> {
+    /// Wraps `stream` with a read buffer of 8 KiB.
+    pub fn new(stream: S) -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY, stream)
+    }
+
+    /// Wraps `stream` with a read buffer of `capacity` bytes.
+    pub fn with_capacity(capacity: usize, stream: S) -> Self {
+        Self {
+            stream,
+            buf: vec![0; capacity].into_boxed_slice(),
+            pos: 0,
+            filled: 0,
+        }
+    }
+
+    /// Returns a reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    ///
+    /// Reading from the stream directly skips the buffered bytes.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Returns the bytes that were read from the stream, but not consumed
+    /// yet.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf[self.pos..self.filled]
+    }
+
+    /// Returns the capacity of the read buffer.
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns the underlying stream, dropping any buffered bytes.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Returns the underlying stream and the buffered bytes.
+    pub fn into_parts(self) -> (S, Vec<u8>) {
+        let buffer = self.buffer().to_vec();
+        (self.stream, buffer)
+    }
+}
+
+impl<S: Read> Read for BufStream<S> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        // Skip the buffer for big reads if it's empty.
+        if self.pos == self.filled && buf.len() >= self.buf.len() {
+            return self.stream.read(buf);
+        }
+        let n = self.fill_buf()?.read(buf)?;
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<S: Read> BufRead for BufStream<S> {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        if self.pos == self.filled {
+            self.filled = self.stream.read(&mut self.buf)?;
+            self.pos = 0;
+        }
+        Ok(self.buffer())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.filled);
+    }
+}
+
+impl<S: Write> Write for BufStream<S> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.stream.write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> Result<usize> {
+        self.stream.write_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl<S: Serialize> Serialize for BufStream<S> {
+    fn serialize<Z>(&self, serializer: Z) -> std::result::Result<Z::Ok, Z::Error>
+    where
+        Z: Serializer,
+    {
+        (&self.stream, self.buffer(), self.buf.len()).serialize(serializer)
+    }
+}
+
+impl<'de, S: Deserialize<'de>> Deserialize<'de> for BufStream<S> {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let (stream, buffer, capacity): (S, Vec<u8>, usize) =
+            Deserialize::deserialize(deserializer)?;
+        let mut buf = buffer;
+        let filled = buf.len();
+        buf.resize(capacity.max(filled), 0);
+        Ok(Self {
+            stream,
+            buf: buf.into_boxed_slice(),
+            pos: 0,
+            filled,
+        })
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for BufStream<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufStream")
+            .field("stream", &self.stream)
+            .field(
+                "buffer",
+                &format_args!("{}/{}", self.filled - self.pos, self.buf.len()),
+            )
+            .finish()
+    }
+}
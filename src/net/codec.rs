@@ -0,0 +1,515 @@
+//! Framing of byte streams.
+//!
+//! A [`Framed`] wraps a stream, like a [`TcpStream`] or [`TlsStream`], and
+//! turns the bytes read from it into frames with a [`Decoder`]. Frames sent
+//! through it are turned into bytes with an [`Encoder`].
+//!
+//! The following codecs are provided:
+//!
+//! * [`LengthDelimitedCodec`] prefixes each frame with its length as a
+//!   big-endian `u16` or `u32`.
+//! * [`LinesCodec`] separates frames with `\n` or `\r\n`.
+//! * [`MessageCodec`] serializes values with a serializer implementing
+//!   [`CanSerializeBytes`] and writes them as length-delimited frames.
+//!
+//! Other framings can be used by implementing [`Decoder`] and [`Encoder`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use lunatic::net::codec::{Framed, LinesCodec};
+//! use lunatic::net::TcpStream;
+//!
+//! let stream = TcpStream::connect("127.0.0.1:6379").unwrap();
+//! let mut framed = Framed::new(stream, LinesCodec::crlf());
+//! framed.send("PING").unwrap();
+//! assert_eq!(framed.receive().unwrap().as_deref(), Some("+PONG"));
+//! ```
+//!
+//! [`TcpStream`]: crate::net::TcpStream
+//! [`TlsStream`]: crate::net::TlsStream
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+
+use thiserror::Error;
+
+use crate::serializer::{Bincode, CanSerializeBytes, DecodeError, EncodeError};
+
+/// Default limit for the size of a single frame (16 MiB).
+const DEFAULT_MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+/// Default limit for the length of a line (64 KiB).
+const DEFAULT_MAX_LINE_LENGTH: usize = 64 * 1024;
+/// How much is read from the stream at once.
+const READ_CHUNK_SIZE: usize = 8 * 1024;
+
+/// Error returned when reading or writing a frame fails.
+#[derive(Error, Debug)]
+pub enum CodecError {
+    /// Reading from or writing to the stream failed.
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+    /// Frame is bigger than the maximum frame size.
+    #[error("frame of {0} bytes is too large")]
+    FrameTooLarge(usize),
+    /// Line is not valid UTF-8.
+    #[error("line is not valid UTF-8")]
+    InvalidUtf8,
+    /// The stream ended in the middle of a frame.
+    #[error("stream ended in the middle of a frame")]
+    UnexpectedEof,
+    /// Value failed to be serialized.
+    #[error("serialization failed: {0}")]
+    SerializationFailed(#[from] EncodeError),
+    /// Frame failed to be deserialized.
+    #[error("deserialization failed: {0}")]
+    DeserializationFailed(#[from] DecodeError),
+}
+
+/// Decodes frames from bytes read from a stream.
+pub trait Decoder {
+    /// The type of decoded frames.
+    type Item;
+
+    /// Attempts to decode a frame from the start of `buf`.
+    ///
+    /// If `buf` contains a whole frame, the frame's bytes are removed from it
+    /// and the frame is returned. If more bytes are needed, `Ok(None)` is
+    /// returned and the method is called again after more bytes were read.
+    fn decode(&mut self, buf: &mut Vec<u8>) -> Result<Option<Self::Item>, CodecError>;
+
+    /// Called when the stream ended, with the bytes left after the last
+    /// frame.
+    ///
+    /// By default, the stream needs to end on a frame boundary.
+    fn decode_eof(&mut self, buf: &mut Vec<u8>) -> Result<Option<Self::Item>, CodecError> {
+        match self.decode(buf)? {
+            Some(frame) => Ok(Some(frame)),
+            None if buf.is_empty() => Ok(None),
+            None => Err(CodecError::UnexpectedEof),
+        }
+    }
+}
+
+/// Encodes frames into bytes written to a stream.
+pub trait Encoder<Item> {
+    /// Appends the encoded `item` to `buf`.
+    fn encode(&mut self, item: Item, buf: &mut Vec<u8>) -> Result<(), CodecError>;
+}
+
+/// A stream reading and writing frames with a codec.
+///
+/// Bytes read after the last returned frame are buffered inside the `Framed`.
+/// To read and write from different processes, create a `Framed` for each
+/// clone of the stream.
+pub struct Framed<S, C> {
+    stream: S,
+    codec: C,
+    read_buf: Vec<u8>,
+    write_buf: Vec<u8>,
+    eof: bool,
+}
+
+impl<S, C> Framed<S, C> {
+    /// Wraps `stream`, reading and writing frames with `codec`.
+    pub fn new(stream: S, codec: C) -> Self {
+        Self {
+            stream,
+            codec,
+            read_buf: Vec::new(),
+            write_buf: Vec::new(),
+            eof: false,
+        }
+    }
+
+    /// Returns a reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    ///
+    /// Reading from the stream directly can corrupt the framing.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Returns a reference to the codec.
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    /// Returns a mutable reference to the codec.
+    pub fn codec_mut(&mut self) -> &mut C {
+        &mut self.codec
+    }
+
+    /// Returns the bytes read from the stream that are not part of a returned
+    /// frame yet.
+    pub fn read_buffer(&self) -> &[u8] {
+        &self.read_buf
+    }
+
+    /// Returns the underlying stream, dropping any buffered bytes.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Returns the underlying stream, the codec and the buffered bytes.
+    pub fn into_parts(self) -> (S, C, Vec<u8>) {
+        (self.stream, self.codec, self.read_buf)
+    }
+}
+
+impl<S, C> Framed<S, C>
+where
+    S: Read,
+    C: Decoder,
+{
+    /// Reads the next frame from the stream.
+    ///
+    /// Returns `Ok(None)` if the stream ended on a frame boundary.
+    pub fn receive(&mut self) -> Result<Option<C::Item>, CodecError> {
+        loop {
+            if self.eof {
+                return self.codec.decode_eof(&mut self.read_buf);
+            }
+            if let Some(frame) = self.codec.decode(&mut self.read_buf)? {
+                return Ok(Some(frame));
+            }
+            let len = self.read_buf.len();
+            self.read_buf.resize(len + READ_CHUNK_SIZE, 0);
+            let read = self.stream.read(&mut self.read_buf[len..]);
+            match read {
+                Ok(n) => {
+                    self.read_buf.truncate(len + n);
+                    self.eof = n == 0;
+                }
+                Err(err) => {
+                    self.read_buf.truncate(len);
+                    if err.kind() != io::ErrorKind::Interrupted {
+                        return Err(err.into());
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<S, C> Framed<S, C>
+where
+    S: Write,
+{
+    /// Encodes `item` and writes it to the stream.
+    pub fn send<I>(&mut self, item: I) -> Result<(), CodecError>
+    where
+        C: Encoder<I>,
+    {
+        self.write_buf.clear();
+        self.codec.encode(item, &mut self.write_buf)?;
+        self.stream.write_all(&self.write_buf)?;
+        self.stream.flush()?;
+        Ok(())
+    }
+}
+
+impl<S, C> Iterator for Framed<S, C>
+where
+    S: Read,
+    C: Decoder,
+{
+    type Item = Result<C::Item, CodecError>;
+
+    /// Returns the next frame, or `None` once the stream ended.
+    fn next(&mut self) -> Option<Self::Item> {
+        self.receive().transpose()
+    }
+}
+
+impl<S: fmt::Debug, C: fmt::Debug> fmt::Debug for Framed<S, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Framed")
+            .field("stream", &self.stream)
+            .field("codec", &self.codec)
+            .field("buffered", &self.read_buf.len())
+            .finish()
+    }
+}
+
+/// The integer type prefixing frames of a [`LengthDelimitedCodec`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LengthPrefix {
+    U16,
+    U32,
+}
+
+impl LengthPrefix {
+    fn size(self) -> usize {
+        match self {
+            LengthPrefix::U16 => 2,
+            LengthPrefix::U32 => 4,
+        }
+    }
+}
+
+/// A codec prefixing frames with their length as a big-endian integer.
+///
+/// Frames are received as `Vec<u8>` and can be sent from anything that
+/// dereferences to a byte slice.
+#[derive(Debug, Clone)]
+pub struct LengthDelimitedCodec {
+    prefix: LengthPrefix,
+    max_frame_size: usize,
+}
+
+impl LengthDelimitedCodec {
+    /// Creates a codec with a `u16` length prefix.
+    pub fn u16() -> Self {
+        Self {
+            prefix: LengthPrefix::U16,
+            max_frame_size: u16::MAX as usize,
+        }
+    }
+
+    /// Creates a codec with a `u32` length prefix.
+    pub fn u32() -> Self {
+        Self {
+            prefix: LengthPrefix::U32,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+        }
+    }
+
+    /// Sets the maximum size of frames.
+    ///
+    /// Receiving or sending a bigger frame fails with
+    /// [`CodecError::FrameTooLarge`]. Defaults to 16 MiB, or the maximum value
+    /// of the prefix if it's smaller.
+    #[must_use]
+    pub fn max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.max_frame_size = match self.prefix {
+            LengthPrefix::U16 => max_frame_size.min(u16::MAX as usize),
+            LengthPrefix::U32 => max_frame_size.min(u32::MAX as usize),
+        };
+        self
+    }
+}
+
+impl Decoder for LengthDelimitedCodec {
+    type Item = Vec<u8>;
+
+    fn decode(&mut self, buf: &mut Vec<u8>) -> Result<Option<Vec<u8>>, CodecError> {
+        let prefix_size = self.prefix.size();
+        if buf.len() < prefix_size {
+            return Ok(None);
+        }
+        let len = match self.prefix {
+            LengthPrefix::U16 => u16::from_be_bytes([buf[0], buf[1]]) as usize,
+            LengthPrefix::U32 => u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]) as usize,
+        };
+        // The prefix can't be added to the length on wasm32 for big frames.
+        let end = match prefix_size.checked_add(len) {
+            Some(end) if len <= self.max_frame_size => end,
+            _ => return Err(CodecError::FrameTooLarge(len)),
+        };
+        if buf.len() < end {
+            return Ok(None);
+        }
+        let frame = buf[prefix_size..end].to_vec();
+        buf.drain(..end);
+        Ok(Some(frame))
+    }
+}
+
+impl<T: AsRef<[u8]>> Encoder<T> for LengthDelimitedCodec {
+    fn encode(&mut self, item: T, buf: &mut Vec<u8>) -> Result<(), CodecError> {
+        let frame = item.as_ref();
+        if frame.len() > self.max_frame_size {
+            return Err(CodecError::FrameTooLarge(frame.len()));
+        }
+        match self.prefix {
+            LengthPrefix::U16 => buf.extend_from_slice(&(frame.len() as u16).to_be_bytes()),
+            LengthPrefix::U32 => buf.extend_from_slice(&(frame.len() as u32).to_be_bytes()),
+        }
+        buf.extend_from_slice(frame);
+        Ok(())
+    }
+}
+
+/// A codec separating UTF-8 lines with `\n` or `\r\n`.
+///
+/// Lines are received as `String`s without the delimiter, and sent from
+/// anything that dereferences to a `str`. If the stream ends without a final
+/// delimiter, the rest is returned as the last line.
+#[derive(Debug, Clone)]
+pub struct LinesCodec {
+    delimiter: &'static [u8],
+    max_length: usize,
+}
+
+impl LinesCodec {
+    /// Creates a codec for lines ending with `\n`.
+    pub fn new() -> Self {
+        Self {
+            delimiter: b"\n",
+            max_length: DEFAULT_MAX_LINE_LENGTH,
+        }
+    }
+
+    /// Creates a codec for lines ending with `\r\n`, as used by many text
+    /// based internet protocols.
+    pub fn crlf() -> Self {
+        Self {
+            delimiter: b"\r\n",
+            max_length: DEFAULT_MAX_LINE_LENGTH,
+        }
+    }
+
+    /// Sets the maximum length of lines, excluding the delimiter.
+    ///
+    /// Receiving or sending a longer line fails with
+    /// [`CodecError::FrameTooLarge`]. Defaults to 64 KiB.
+    #[must_use]
+    pub fn max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length;
+        self
+    }
+
+    fn take_line(
+        &self,
+        buf: &mut Vec<u8>,
+        len: usize,
+        consumed: usize,
+    ) -> Result<String, CodecError> {
+        let line = String::from_utf8(buf[..len].to_vec()).map_err(|_| CodecError::InvalidUtf8);
+        buf.drain(..consumed);
+        line
+    }
+}
+
+impl Default for LinesCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder for LinesCodec {
+    type Item = String;
+
+    fn decode(&mut self, buf: &mut Vec<u8>) -> Result<Option<String>, CodecError> {
+        let position = buf
+            .windows(self.delimiter.len())
+            .position(|window| window == self.delimiter);
+        match position {
+            Some(len) if len > self.max_length => Err(CodecError::FrameTooLarge(len)),
+            Some(len) => self
+                .take_line(buf, len, len + self.delimiter.len())
+                .map(Some),
+            // The delimiter could be split between two reads.
+            None if buf.len() > self.max_length + self.delimiter.len() - 1 => {
+                Err(CodecError::FrameTooLarge(buf.len()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn decode_eof(&mut self, buf: &mut Vec<u8>) -> Result<Option<String>, CodecError> {
+        match self.decode(buf)? {
+            Some(line) => Ok(Some(line)),
+            None if buf.is_empty() => Ok(None),
+            None if buf.len() > self.max_length => Err(CodecError::FrameTooLarge(buf.len())),
+            None => {
+                let len = buf.len();
+                self.take_line(buf, len, len).map(Some)
+            }
+        }
+    }
+}
+
+impl<T: AsRef<str>> Encoder<T> for LinesCodec {
+    fn encode(&mut self, item: T, buf: &mut Vec<u8>) -> Result<(), CodecError> {
+        let line = item.as_ref();
+        if line.len() > self.max_length {
+            return Err(CodecError::FrameTooLarge(line.len()));
+        }
+        buf.extend_from_slice(line.as_bytes());
+        buf.extend_from_slice(self.delimiter);
+        Ok(())
+    }
+}
+
+/// A codec serializing values of type `M` with the serializer `S`.
+///
+/// Each value is written as a frame of a [`LengthDelimitedCodec`] with a
+/// `u32` prefix, the same framing used by
+/// [`StreamProtocol`](crate::net::StreamProtocol).
+pub struct MessageCodec<M, S = Bincode> {
+    frames: LengthDelimitedCodec,
+    phantom: PhantomData<fn(M) -> (M, S)>,
+}
+
+impl<M, S> MessageCodec<M, S> {
+    /// Creates a codec with the default maximum frame size of 16 MiB.
+    pub fn new() -> Self {
+        Self {
+            frames: LengthDelimitedCodec::u32(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Sets the maximum size of serialized values.
+    #[must_use]
+    pub fn max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.frames = self.frames.max_frame_size(max_frame_size);
+        self
+    }
+}
+
+impl<M, S> Default for MessageCodec<M, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M, S> Clone for MessageCodec<M, S> {
+    fn clone(&self) -> Self {
+        Self {
+            frames: self.frames.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<M, S> fmt::Debug for MessageCodec<M, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MessageCodec")
+            .field("message", &std::any::type_name::<M>())
+            .field("serializer", &std::any::type_name::<S>())
+            .field("max_frame_size", &self.frames.max_frame_size)
+            .finish()
+    }
+}
+
+impl<M, S: CanSerializeBytes<M>> Decoder for MessageCodec<M, S> {
+    type Item = M;
+
+    fn decode(&mut self, buf: &mut Vec<u8>) -> Result<Option<M>, CodecError> {
+        match self.frames.decode(buf)? {
+            Some(frame) => Ok(Some(S::decode_bytes(&frame)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<M, S: CanSerializeBytes<M>> Encoder<&M> for MessageCodec<M, S> {
+    fn encode(&mut self, item: &M, buf: &mut Vec<u8>) -> Result<(), CodecError> {
+        let frame = S::encode_bytes(item)?;
+        self.frames.encode(frame, buf)
+    }
+}
+
+impl<M, S: CanSerializeBytes<M>> Encoder<M> for MessageCodec<M, S> {
+    fn encode(&mut self, item: M, buf: &mut Vec<u8>) -> Result<(), CodecError> {
+        self.encode(&item, buf)
+    }
+}
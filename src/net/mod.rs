@@ -1,8 +1,14 @@
 //! Networking related functions.
 
+mod buf_stream;
+pub mod codec;
+mod proxy_protocol;
 mod resolver;
+mod stream_protocol;
 mod tcp_listener;
+mod tcp_server;
 mod tcp_stream;
+mod tls_config;
 mod tls_listener;
 mod tls_stream;
 mod udp;
@@ -13,9 +19,16 @@ use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV
 use std::option::IntoIter;
 use std::slice::Iter;
 
+use crate::error::LunaticError;
+
+pub use buf_stream::{BufStream, BufTcpStream, BufTlsStream};
+pub use proxy_protocol::{read_proxy_header, ProxyHeader, Tlv};
 pub use resolver::{resolve, resolve_timeout, SocketAddrIterator};
+pub use stream_protocol::{StreamProtocol, StreamProtocolError};
 pub use tcp_listener::TcpListener;
+pub use tcp_server::TcpServer;
 pub use tcp_stream::TcpStream;
+pub use tls_config::{ClientAuth, TlsConfig};
 pub use tls_listener::TlsListener;
 pub use tls_stream::TlsStream;
 pub use udp::UdpSocket;
@@ -205,3 +218,14 @@ impl<'a> ToSocketAddrs for &'a [SocketAddr] {
         <&[SocketAddr] as std::net::ToSocketAddrs>::to_socket_addrs(self)
     }
 }
+
+/// Turns the result of a networking host call into a `Result`, where `opaque`
+/// holds the value on success and the error ID otherwise.
+pub(crate) fn check(result: u32, opaque: u64) -> Result<u64> {
+    if result == 0 {
+        Ok(opaque)
+    } else {
+        let lunatic_error = LunaticError::Error(opaque);
+        Err(Error::new(ErrorKind::Other, lunatic_error))
+    }
+}
@@ -0,0 +1,266 @@
+use std::io::{Error, ErrorKind, Read, Result};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::TcpStream;
+
+/// Signature starting a version 2 header.
+const V2_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+/// Maximum length of a version 1 header, including the CRLF.
+const V1_MAX_LENGTH: usize = 107;
+
+/// Type of the TLV carrying the host name the client connected to.
+const PP2_TYPE_AUTHORITY: u8 = 0x02;
+
+/// How long to wait for the header of a new connection.
+const HEADER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A header of the [PROXY protocol], sent by proxies and load balancers at the
+/// start of a connection to pass on the address of the original client.
+///
+/// Headers are read by [`TcpListener::accept_proxied`],
+/// [`TlsListener::accept_proxied`] and [`read_proxy_header`]. Both the text
+/// format of version 1 and the binary format of version 2 are supported.
+///
+/// [PROXY protocol]: https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt
+/// [`TcpListener::accept_proxied`]: super::TcpListener::accept_proxied
+/// [`TlsListener::accept_proxied`]: super::TlsListener::accept_proxied
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ProxyHeader {
+    version: u8,
+    source: Option<SocketAddr>,
+    destination: Option<SocketAddr>,
+    tlvs: Vec<Tlv>,
+}
+
+/// A type-length-value field of a version 2 [`ProxyHeader`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Tlv {
+    /// The type of the field, e.g. `0x02` for the authority.
+    pub kind: u8,
+    /// The raw value of the field.
+    pub value: Vec<u8>,
+}
+
+impl ProxyHeader {
+    /// Returns the version of the PROXY protocol, 1 or 2.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Returns the address of the original client.
+    ///
+    /// Returns `None` if the proxy didn't forward a TCP connection, e.g. for
+    /// health checks (`LOCAL` and `UNKNOWN` headers) or Unix sockets.
+    pub fn source(&self) -> Option<SocketAddr> {
+        self.source
+    }
+
+    /// Returns the address the original client connected to.
+    pub fn destination(&self) -> Option<SocketAddr> {
+        self.destination
+    }
+
+    /// Returns the TLV fields of a version 2 header.
+    pub fn tlvs(&self) -> &[Tlv] {
+        &self.tlvs
+    }
+
+    /// Returns the value of the first TLV field of type `kind`.
+    pub fn tlv(&self, kind: u8) -> Option<&[u8]> {
+        self.tlvs
+            .iter()
+            .find(|tlv| tlv.kind == kind)
+            .map(|tlv| tlv.value.as_slice())
+    }
+
+    /// Returns the host name the client connected to, usually taken from the
+    /// TLS SNI extension by the proxy.
+    pub fn authority(&self) -> Option<&str> {
+        self.tlv(PP2_TYPE_AUTHORITY)
+            .and_then(|value| std::str::from_utf8(value).ok())
+    }
+}
+
+/// Reads a [`ProxyHeader`] from a stream accepted with
+/// [`TcpListener::accept`](super::TcpListener::accept).
+///
+/// The stream is left positioned right after the header. Fails if the header
+/// is missing or malformed, or if it isn't received within 5 seconds.
+///
+/// Unlike [`TcpListener::accept_proxied`](super::TcpListener::accept_proxied),
+/// this can be called in the process handling the connection, so that slow
+/// clients don't hold up accepting other connections.
+///
+/// # Example
+///
+/// ```no_run
+/// use lunatic::net::{read_proxy_header, TcpListener, TcpStream};
+/// use lunatic::{Mailbox, Process};
+///
+/// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+/// while let Ok((stream, _)) = listener.accept() {
+///     Process::spawn(stream, |mut stream: TcpStream, _: Mailbox<()>| {
+///         let header = read_proxy_header(&mut stream).unwrap();
+///         println!("connection from {:?}", header.source());
+///     });
+/// }
+/// ```
+pub fn read_proxy_header(stream: &mut TcpStream) -> Result<ProxyHeader> {
+    let timeout = stream.read_timeout();
+    stream.set_read_timeout(Some(HEADER_TIMEOUT))?;
+    let header = read_header(stream);
+    stream.set_read_timeout(timeout)?;
+    header
+}
+
+/// Reads a header of either version from `reader`, without reading past its
+/// end.
+pub(crate) fn read_header(reader: &mut impl Read) -> Result<ProxyHeader> {
+    // Both versions are at least this long.
+    let mut start = [0; 8];
+    reader.read_exact(&mut start)?;
+    if start.starts_with(b"PROXY ") {
+        read_v1(reader, &start)
+    } else if start == V2_SIGNATURE[..8] {
+        read_v2(reader)
+    } else {
+        Err(invalid("missing PROXY protocol header"))
+    }
+}
+
+fn read_v1(reader: &mut impl Read, start: &[u8]) -> Result<ProxyHeader> {
+    let mut line = start.to_vec();
+    // Read byte by byte to not consume data following the header.
+    while !line.ends_with(b"\r\n") {
+        if line.len() == V1_MAX_LENGTH {
+            return Err(invalid("PROXY protocol header is too long"));
+        }
+        let mut byte = [0];
+        reader.read_exact(&mut byte)?;
+        line.push(byte[0]);
+    }
+    let line = std::str::from_utf8(&line[..line.len() - 2])
+        .map_err(|_| invalid("PROXY protocol header is not valid UTF-8"))?;
+    let parts: Vec<&str> = line.split(' ').collect();
+    let (source, destination) = match parts.as_slice() {
+        ["PROXY", "UNKNOWN", ..] => (None, None),
+        ["PROXY", protocol @ ("TCP4" | "TCP6"), addresses @ ..] if addresses.len() == 4 => {
+            let parse = |ip: &str, port: &str| -> Result<SocketAddr> {
+                let ip: IpAddr = ip
+                    .parse()
+                    .map_err(|_| invalid("invalid address in PROXY protocol header"))?;
+                let port = port
+                    .parse()
+                    .map_err(|_| invalid("invalid port in PROXY protocol header"))?;
+                if ip.is_ipv4() != (*protocol == "TCP4") {
+                    return Err(invalid("address doesn't match PROXY protocol family"));
+                }
+                Ok(SocketAddr::new(ip, port))
+            };
+            (
+                Some(parse(addresses[0], addresses[2])?),
+                Some(parse(addresses[1], addresses[3])?),
+            )
+        }
+        _ => return Err(invalid("malformed PROXY protocol header")),
+    };
+    Ok(ProxyHeader {
+        version: 1,
+        source,
+        destination,
+        tlvs: Vec::new(),
+    })
+}
+
+fn read_v2(reader: &mut impl Read) -> Result<ProxyHeader> {
+    let mut rest = [0; 8];
+    reader.read_exact(&mut rest)?;
+    if rest[..4] != V2_SIGNATURE[8..] {
+        return Err(invalid("missing PROXY protocol header"));
+    }
+    let version = rest[4] >> 4;
+    let command = rest[4] & 0x0f;
+    let family = rest[5] >> 4;
+    let len = u16::from_be_bytes([rest[6], rest[7]]) as usize;
+    if version != 2 {
+        return Err(invalid("unsupported PROXY protocol version"));
+    }
+    let mut payload = vec![0; len];
+    reader.read_exact(&mut payload)?;
+
+    let addresses_len = match family {
+        0 => 0,
+        1 => 12,
+        2 => 36,
+        3 => 216,
+        _ => return Err(invalid("unsupported PROXY protocol address family")),
+    };
+    if payload.len() < addresses_len {
+        return Err(invalid("truncated PROXY protocol addresses"));
+    }
+    let (addresses, mut tlvs_bytes) = payload.split_at(addresses_len);
+    let (source, destination) = match (command, family) {
+        // LOCAL connections, e.g. health checks, are not proxied.
+        (0, _) => (None, None),
+        (1, 1) => {
+            let ip = |at: usize| {
+                Ipv4Addr::new(
+                    addresses[at],
+                    addresses[at + 1],
+                    addresses[at + 2],
+                    addresses[at + 3],
+                )
+            };
+            let port = |at: usize| u16::from_be_bytes([addresses[at], addresses[at + 1]]);
+            (
+                Some(SocketAddr::new(ip(0).into(), port(8))),
+                Some(SocketAddr::new(ip(4).into(), port(10))),
+            )
+        }
+        (1, 2) => {
+            let ip = |at: usize| {
+                let mut octets = [0; 16];
+                octets.copy_from_slice(&addresses[at..at + 16]);
+                Ipv6Addr::from(octets)
+            };
+            let port = |at: usize| u16::from_be_bytes([addresses[at], addresses[at + 1]]);
+            (
+                Some(SocketAddr::new(ip(0).into(), port(32))),
+                Some(SocketAddr::new(ip(16).into(), port(34))),
+            )
+        }
+        (1, _) => (None, None),
+        _ => return Err(invalid("unsupported PROXY protocol command")),
+    };
+
+    let mut tlvs = Vec::new();
+    while !tlvs_bytes.is_empty() {
+        if tlvs_bytes.len() < 3 {
+            return Err(invalid("truncated PROXY protocol TLV"));
+        }
+        let kind = tlvs_bytes[0];
+        let len = u16::from_be_bytes([tlvs_bytes[1], tlvs_bytes[2]]) as usize;
+        if tlvs_bytes.len() < 3 + len {
+            return Err(invalid("truncated PROXY protocol TLV"));
+        }
+        tlvs.push(Tlv {
+            kind,
+            value: tlvs_bytes[3..3 + len].to_vec(),
+        });
+        tlvs_bytes = &tlvs_bytes[3 + len..];
+    }
+
+    Ok(ProxyHeader {
+        version: 2,
+        source,
+        destination,
+        tlvs,
+    })
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,301 @@
+use std::any::TypeId;
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+use std::{any, fmt};
+
+use thiserror::Error;
+
+use super::codec::{CodecError, Framed, LengthDelimitedCodec};
+use crate::protocol::{Branch, Choose, End, Offer, Pop, Rec, Recv, Send};
+use crate::serializer::{Bincode, CanSerializeBytes, DecodeError, EncodeError};
+
+/// A `StreamProtocol` drives a byte stream, like a [`TcpStream`] or
+/// [`TlsStream`], with the same session types used by
+/// [`Protocol`](crate::protocol::Protocol).
+///
+/// This allows external wire protocols to get the same compile-time ordering
+/// guarantees as protocols between processes. Each value is serialized with
+/// `S` and written as a frame of a [`LengthDelimitedCodec`] with a `u32`
+/// prefix. Choices are sent as a serialized `bool`.
+///
+/// Both ends need to be typed as each others dual. If the other end is also a
+/// lunatic process, it can use `StreamProtocol<<P as HasDual>::Dual, _>`.
+///
+/// Only `StreamProtocol<End>` can be dropped. All other protocols will panic
+/// if dropped without reaching `StreamProtocol<End>`, unless a step failed.
+/// If a step fails, the protocol is consumed and the stream closed.
+///
+/// # Example
+///
+/// ```no_run
+/// use lunatic::net::{StreamProtocol, TcpStream};
+/// use lunatic::protocol::{End, Recv, Send};
+///
+/// let stream = TcpStream::connect("127.0.0.1:3000").unwrap();
+/// let protocol: StreamProtocol<Send<String, Recv<u64, End>>, _> = StreamProtocol::new(stream);
+/// let protocol = protocol.send("hello".to_owned()).unwrap();
+/// let (protocol, length) = protocol.receive().unwrap();
+/// let stream = protocol.into_inner();
+/// ```
+///
+/// [`TcpStream`]: crate::net::TcpStream
+/// [`TlsStream`]: crate::net::TlsStream
+pub struct StreamProtocol<P: 'static, T, S = Bincode, Z: 'static = ()> {
+    // Is `None` after the stream was moved to the next protocol state.
+    stream: Option<Framed<T, LengthDelimitedCodec>>,
+    phantom: PhantomData<(P, S, Z)>,
+}
+
+impl<P: 'static, T, S, Z: 'static> Drop for StreamProtocol<P, T, S, Z> {
+    fn drop(&mut self) {
+        if self.stream.is_some() && TypeId::of::<P>() != TypeId::of::<End>() {
+            panic!(
+                "StreamProtocol prematurely dropped, before reaching the `End` state (currently: {}).",
+                any::type_name::<P>()
+            );
+        }
+    }
+}
+
+impl<P, T, S, Z> StreamProtocol<P, T, S, Z> {
+    /// Turn a stream into a protocol.
+    pub fn new(stream: T) -> Self {
+        Self {
+            stream: Some(Framed::new(stream, LengthDelimitedCodec::u32())),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Sets the maximum size of frames.
+    ///
+    /// If a value serializes to a bigger frame, or the other end announces
+    /// one, the step fails with [`StreamProtocolError::FrameTooLarge`].
+    /// Defaults to 16 MiB.
+    #[must_use]
+    pub fn with_max_frame_size(mut self, max_frame_size: usize) -> Self {
+        *self.framed().codec_mut() = LengthDelimitedCodec::u32().max_frame_size(max_frame_size);
+        self
+    }
+
+    /// Returns a reference to the underlying stream.
+    pub fn get_ref(&self) -> &T {
+        self.stream.as_ref().expect("stream is present").get_ref()
+    }
+
+    /// Abandons the protocol before reaching the `End` state, without
+    /// panicking, and returns the underlying stream.
+    ///
+    /// Bytes that were already read from the stream, but are not part of a
+    /// received value, are dropped.
+    pub fn cancel(mut self) -> T {
+        self.stream.take().expect("stream is present").into_inner()
+    }
+
+    /// Cast the protocol to another type.
+    fn cast<P2, Z2>(mut self) -> StreamProtocol<P2, T, S, Z2> {
+        StreamProtocol {
+            stream: self.stream.take(),
+            phantom: PhantomData,
+        }
+    }
+
+    fn framed(&mut self) -> &mut Framed<T, LengthDelimitedCodec> {
+        self.stream.as_mut().expect("stream is present")
+    }
+}
+
+impl<P, T, S, Z> StreamProtocol<P, T, S, Z>
+where
+    T: Read + Write,
+{
+    /// Serializes `message` and writes it as one frame to the stream.
+    ///
+    /// If writing fails, the stream is closed so that the protocol can be
+    /// dropped.
+    fn write_frame<A>(&mut self, message: &A) -> Result<(), StreamProtocolError>
+    where
+        S: CanSerializeBytes<A>,
+    {
+        let result = self.write_frame_(message);
+        if result.is_err() {
+            self.stream = None;
+        }
+        result
+    }
+
+    fn write_frame_<A>(&mut self, message: &A) -> Result<(), StreamProtocolError>
+    where
+        S: CanSerializeBytes<A>,
+    {
+        let data = S::encode_bytes(message)?;
+        self.framed().send(data)?;
+        Ok(())
+    }
+
+    /// Reads one frame from the stream and deserializes it.
+    ///
+    /// If reading fails, the stream is closed so that the protocol can be
+    /// dropped.
+    fn read_frame<A>(&mut self) -> Result<A, StreamProtocolError>
+    where
+        S: CanSerializeBytes<A>,
+    {
+        let result = self.read_frame_();
+        if result.is_err() {
+            self.stream = None;
+        }
+        result
+    }
+
+    fn read_frame_<A>(&mut self) -> Result<A, StreamProtocolError>
+    where
+        S: CanSerializeBytes<A>,
+    {
+        match self.framed().receive()? {
+            Some(data) => Ok(S::decode_bytes(&data)?),
+            None => Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+        }
+    }
+}
+
+impl<P, A, T, S, Z> StreamProtocol<Send<A, P>, T, S, Z>
+where
+    T: Read + Write,
+    S: CanSerializeBytes<A>,
+{
+    /// Send a value of type `A` over the stream. Returns the resulting
+    /// session.
+    pub fn send(mut self, message: A) -> Result<StreamProtocol<P, T, S, Z>, StreamProtocolError> {
+        self.write_frame(&message)?;
+        Ok(self.cast())
+    }
+}
+
+impl<P, A, T, S, Z> StreamProtocol<Recv<A, P>, T, S, Z>
+where
+    T: Read + Write,
+    S: CanSerializeBytes<A>,
+{
+    /// Receives a value of type `A` from the stream. Returns a tuple
+    /// containing the resulting session and the received value.
+    #[allow(clippy::type_complexity)]
+    pub fn receive(mut self) -> Result<(StreamProtocol<P, T, S, Z>, A), StreamProtocolError> {
+        let received = self.read_frame()?;
+        Ok((self.cast(), received))
+    }
+}
+
+impl<P, Q, T, S, Z> StreamProtocol<Choose<P, Q>, T, S, Z>
+where
+    T: Read + Write,
+    S: CanSerializeBytes<bool>,
+{
+    /// Perform an active choice, selecting protocol `P`.
+    pub fn select_left(mut self) -> Result<StreamProtocol<P, T, S, Z>, StreamProtocolError> {
+        self.write_frame(&true)?;
+        Ok(self.cast())
+    }
+
+    /// Perform an active choice, selecting protocol `Q`.
+    pub fn select_right(mut self) -> Result<StreamProtocol<Q, T, S, Z>, StreamProtocolError> {
+        self.write_frame(&false)?;
+        Ok(self.cast())
+    }
+}
+
+impl<P, Q, T, S, Z> StreamProtocol<Offer<P, Q>, T, S, Z>
+where
+    T: Read + Write,
+    S: CanSerializeBytes<bool>,
+{
+    /// Passive choice. This allows the other end of the stream to select one
+    /// of two options for continuing the protocol: either `P` or `Q`.
+    #[allow(clippy::type_complexity)]
+    pub fn offer(
+        mut self,
+    ) -> Result<Branch<StreamProtocol<P, T, S, Z>, StreamProtocol<Q, T, S, Z>>, StreamProtocolError>
+    {
+        if self.read_frame()? {
+            Ok(Branch::Left(self.cast()))
+        } else {
+            Ok(Branch::Right(self.cast()))
+        }
+    }
+}
+
+impl<P, T, S, Z> StreamProtocol<Rec<P>, T, S, Z> {
+    /// Enter a recursive environment, putting the current environment on the
+    /// top of the environment stack.
+    pub fn repeat(self) -> StreamProtocol<P, T, S, StreamProtocol<Rec<P>, T, S, Z>> {
+        self.cast()
+    }
+}
+
+impl<P2, T, S, Z> StreamProtocol<Pop, T, S, StreamProtocol<P2, T, S, Z>> {
+    /// Pop the top environment from the environment stack.
+    pub fn pop(self) -> StreamProtocol<P2, T, S, Z> {
+        self.cast()
+    }
+}
+
+impl<T, S> StreamProtocol<End, T, S> {
+    /// Returns the underlying stream after the protocol finished.
+    ///
+    /// Bytes the other end sent after the last value may already have been
+    /// read from the stream. Use [`into_framed`](Self::into_framed) to keep
+    /// them.
+    pub fn into_inner(mut self) -> T {
+        self.stream.take().expect("stream is present").into_inner()
+    }
+
+    /// Returns the underlying stream after the protocol finished, together
+    /// with the bytes read from it after the last value.
+    pub fn into_framed(mut self) -> Framed<T, LengthDelimitedCodec> {
+        self.stream.take().expect("stream is present")
+    }
+}
+
+impl<P, T, S, Z> fmt::Debug for StreamProtocol<P, T, S, Z> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamProtocol")
+            .field("type", &any::type_name::<P>())
+            .field("codec", &self.stream.as_ref().map(Framed::codec))
+            .finish()
+    }
+}
+
+/// Error returned when a step of a [`StreamProtocol`] fails.
+///
+/// The protocol is consumed by the failed step and can't be continued.
+#[derive(Error, Debug)]
+pub enum StreamProtocolError {
+    /// Reading from or writing to the stream failed.
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    /// Value failed to be serialized.
+    #[error("serialization failed: {0}")]
+    SerializationFailed(#[from] EncodeError),
+    /// Frame failed to be deserialized.
+    #[error("deserialization failed: {0}")]
+    DeserializationFailed(#[from] DecodeError),
+    /// Frame is bigger than the maximum frame size.
+    #[error("frame of {0} bytes is too large")]
+    FrameTooLarge(usize),
+}
+
+impl From<CodecError> for StreamProtocolError {
+    fn from(error: CodecError) -> Self {
+        match error {
+            CodecError::Io(error) => StreamProtocolError::Io(error),
+            CodecError::FrameTooLarge(size) => StreamProtocolError::FrameTooLarge(size),
+            CodecError::UnexpectedEof => io::Error::from(io::ErrorKind::UnexpectedEof).into(),
+            CodecError::InvalidUtf8 => io::Error::from(io::ErrorKind::InvalidData).into(),
+            CodecError::SerializationFailed(error) => {
+                StreamProtocolError::SerializationFailed(error)
+            }
+            CodecError::DeserializationFailed(error) => {
+                StreamProtocolError::DeserializationFailed(error)
+            }
+        }
+    }
+}
@@ -1,7 +1,10 @@
+use std::cell::UnsafeCell;
 use std::io::{Error, ErrorKind, Result};
 use std::net::SocketAddr;
 
-use super::SocketAddrIterator;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{read_proxy_header, ProxyHeader, SocketAddrIterator};
 use crate::error::LunaticError;
 use crate::host;
 use crate::net::TcpStream;
@@ -42,18 +45,62 @@ use crate::net::TcpStream;
 ///     }
 /// }
 /// ```
+///
+/// Like a [`TcpStream`], a listener can be cloned and sent to other processes.
+/// All clones accept connections from the same socket.
 #[derive(Debug)]
 pub struct TcpListener {
     id: u64,
+    consumed: UnsafeCell<bool>,
 }
 
 impl Drop for TcpListener {
     fn drop(&mut self) {
-        unsafe { host::api::networking::drop_tcp_listener(self.id) };
+        // Only drop listener if it's not already consumed
+        if unsafe { !*self.consumed.get() } {
+            unsafe { host::api::networking::drop_tcp_listener(self.id) };
+        }
+    }
+}
+
+impl Clone for TcpListener {
+    fn clone(&self) -> Self {
+        let id = unsafe { host::api::networking::clone_tcp_listener(self.id) };
+        TcpListener::from(id)
+    }
+}
+
+impl Serialize for TcpListener {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // Mark listener as consumed
+        unsafe { *self.consumed.get() = true };
+        let index = unsafe { host::api::message::push_tcp_listener(self.id) };
+        serializer.serialize_u64(index)
+    }
+}
+
+impl<'de> Deserialize<'de> for TcpListener {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<TcpListener, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let index = Deserialize::deserialize(deserializer)?;
+        let id = unsafe { host::api::message::take_tcp_listener(index) };
+        Ok(TcpListener::from(id))
     }
 }
 
 impl TcpListener {
+    pub(crate) fn from(id: u64) -> Self {
+        TcpListener {
+            id,
+            consumed: UnsafeCell::new(false),
+        }
+    }
+
     /// Creates a new [`TcpListener`] bound to the given address.
     ///
     /// Binding with a port number of 0 will request that the operating system
@@ -102,7 +149,7 @@ impl TcpListener {
                 }
             };
             if result == 0 {
-                return Ok(Self { id });
+                return Ok(TcpListener::from(id));
             }
         }
         let lunatic_error = LunaticError::Error(id);
@@ -136,6 +183,33 @@ impl TcpListener {
         }
     }
 
+    /// Accepts a new incoming connection from a proxy or load balancer that
+    /// sends a [PROXY protocol] header (version 1 or 2) first.
+    ///
+    /// Returns the TCP stream positioned right after the header, the address
+    /// of the original client and the header itself. If the proxy didn't
+    /// forward a client connection, e.g. for health checks, the address of the
+    /// proxy is returned instead.
+    ///
+    /// Fails if the header is missing or malformed, or if it isn't received
+    /// within 5 seconds. Only use this for listeners that are reachable
+    /// exclusively through a trusted proxy, because clients could otherwise
+    /// spoof their address.
+    ///
+    /// The header is read in the calling process. A connection that doesn't
+    /// send its header blocks the next `accept` for up to 5 seconds, and the
+    /// error is returned from this call. Accept loops handling many
+    /// connections should use [`accept`](Self::accept) and read the header
+    /// with [`read_proxy_header`] in the process handling the connection.
+    ///
+    /// [PROXY protocol]: https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt
+    pub fn accept_proxied(&self) -> Result<(TcpStream, SocketAddr, ProxyHeader)> {
+        let (mut tcp_stream, peer) = self.accept()?;
+        let header = read_proxy_header(&mut tcp_stream)?;
+        let client = header.source().unwrap_or(peer);
+        Ok((tcp_stream, client, header))
+    }
+
     /// Returns the local address that this listener is bound to.
     ///
     /// This can be useful, for example, to identify when binding to port 0
@@ -0,0 +1,217 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::Result;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use super::{TcpListener, TcpStream, ToSocketAddrs};
+use crate::function::FuncRef;
+use crate::serializer::Bincode;
+use crate::{sleep, Mailbox, MessageSignal, Process, ProcessConfig, ProcessDiedSignal};
+
+/// How long an acceptor waits after the first failed `accept`.
+const MIN_BACKOFF: Duration = Duration::from_millis(5);
+
+/// Upper bound of the wait after repeatedly failed `accept`s.
+const MAX_BACKOFF: Duration = Duration::from_secs(1);
+
+/// A TCP server, accepting connections with a pool of processes and handling
+/// each connection in a new process.
+///
+/// The acceptor processes share the same [`TcpListener`]. Before accepting a
+/// connection, an acceptor needs a permit from the process running
+/// [`serve`](TcpServer::serve). Once [`max_connections`](TcpServer::max_connections)
+/// are open, new connections wait in the listener's backlog until one of the
+/// open connections finishes.
+///
+/// Connection processes are not linked to the server, so a crashing handler
+/// only closes its own connection. Acceptor processes that die are restarted.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::io::{BufRead, BufReader, Write};
+///
+/// use lunatic::net::{TcpServer, TcpStream};
+///
+/// fn echo(_: (), mut stream: TcpStream) {
+///     let mut reader = BufReader::new(stream.clone());
+///     let mut line = String::new();
+///     while reader.read_line(&mut line).unwrap() > 0 {
+///         stream.write_all(line.as_bytes()).unwrap();
+///         line.clear();
+///     }
+/// }
+///
+/// TcpServer::bind("127.0.0.1:6666")
+///     .unwrap()
+///     .acceptors(4)
+///     .max_connections(1000)
+///     .serve((), echo);
+/// ```
+#[derive(Debug)]
+pub struct TcpServer {
+    listener: TcpListener,
+    acceptors: usize,
+    max_connections: usize,
+    config: Option<ProcessConfig>,
+}
+
+/// A function handling a connection.
+type Handler<C> = fn(C, TcpStream);
+
+/// Messages received by the process running the server.
+#[derive(Serialize, Deserialize)]
+enum Event {
+    /// The acceptor is waiting for a permit to accept a connection.
+    Ready(Process<()>),
+    /// The acceptor accepted a connection.
+    Accepted(u64, TcpStream),
+    /// The acceptor failed to accept a connection and returns its permit.
+    Failed(u64),
+}
+
+impl TcpServer {
+    /// Creates a server listening on `addr`.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        Ok(Self::new(TcpListener::bind(addr)?))
+    }
+
+    /// Creates a server accepting connections from `listener`.
+    pub fn new(listener: TcpListener) -> Self {
+        Self {
+            listener,
+            acceptors: 1,
+            max_connections: usize::MAX,
+            config: None,
+        }
+    }
+
+    /// Sets the number of acceptor processes. Defaults to 1.
+    #[must_use]
+    pub fn acceptors(mut self, acceptors: usize) -> Self {
+        self.acceptors = acceptors.max(1);
+        self
+    }
+
+    /// Sets the maximum number of open connections. Unlimited by default.
+    #[must_use]
+    pub fn max_connections(mut self, max_connections: usize) -> Self {
+        self.max_connections = max_connections;
+        self
+    }
+
+    /// Spawns the connection processes with `config`, e.g. to limit their
+    /// memory or fuel.
+    #[must_use]
+    pub fn connection_config(mut self, config: ProcessConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Returns the local address the server is listening on.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accepts connections and calls `handler` with a copy of `capture` for
+    /// each of them, inside a new process.
+    ///
+    /// This function never returns.
+    pub fn serve<C>(self, capture: C, handler: Handler<C>)
+    where
+        C: Serialize + DeserializeOwned + Clone,
+    {
+        // Temporarily cast to right mailbox type.
+        let mailbox: Mailbox<Event> = unsafe { Mailbox::new() };
+        let mailbox = mailbox.monitorable();
+        let handler = FuncRef::new(handler);
+
+        let mut acceptors = HashMap::new();
+        for _ in 0..self.acceptors {
+            let acceptor = self.spawn_acceptor(mailbox);
+            acceptors.insert(acceptor.id(), acceptor);
+        }
+        // Acceptors holding a permit.
+        let mut holding = HashSet::new();
+        let mut waiting = VecDeque::new();
+        let mut connections = HashSet::new();
+
+        loop {
+            match mailbox.receive() {
+                MessageSignal::Message(Event::Ready(acceptor)) => waiting.push_back(acceptor),
+                MessageSignal::Message(Event::Accepted(acceptor, stream)) => {
+                    holding.remove(&acceptor);
+                    let entry = (capture.clone(), stream, handler);
+                    let connection = match &self.config {
+                        Some(config) => Process::spawn_config(config, entry, connection::<C>),
+                        None => Process::spawn(entry, connection::<C>),
+                    };
+                    mailbox.monitor(connection);
+                    connections.insert(connection.id());
+                }
+                MessageSignal::Message(Event::Failed(acceptor)) => {
+                    holding.remove(&acceptor);
+                }
+                MessageSignal::Signal(ProcessDiedSignal(id)) => {
+                    if acceptors.remove(&id).is_some() {
+                        holding.remove(&id);
+                        waiting.retain(|acceptor: &Process<()>| acceptor.id() != id);
+                        let acceptor = self.spawn_acceptor(mailbox);
+                        acceptors.insert(acceptor.id(), acceptor);
+                    } else {
+                        connections.remove(&id);
+                    }
+                }
+            }
+            // Hand out permits while below the limit.
+            while connections.len() + holding.len() < self.max_connections {
+                match waiting.pop_front() {
+                    Some(acceptor) => {
+                        holding.insert(acceptor.id());
+                        acceptor.send(());
+                    }
+                    None => break,
+                }
+            }
+        }
+    }
+
+    fn spawn_acceptor(&self, mailbox: Mailbox<Event, Bincode, ProcessDiedSignal>) -> Process<()> {
+        let acceptor = Process::spawn((self.listener.clone(), mailbox.this()), acceptor);
+        mailbox.monitor(acceptor);
+        acceptor
+    }
+}
+
+/// Entry point of the acceptor processes.
+///
+/// If `accept` keeps failing, e.g. because the process ran out of file
+/// descriptors, the acceptor backs off exponentially before asking for the
+/// next permit.
+fn acceptor((listener, server): (TcpListener, Process<Event>), mailbox: Mailbox<()>) {
+    let this = mailbox.this();
+    let mut backoff = MIN_BACKOFF;
+    loop {
+        server.send(Event::Ready(this));
+        mailbox.receive();
+        match listener.accept() {
+            Ok((stream, _)) => {
+                server.send(Event::Accepted(this.id(), stream));
+                backoff = MIN_BACKOFF;
+            }
+            Err(_) => {
+                server.send(Event::Failed(this.id()));
+                sleep(backoff);
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
+
+/// Entry point of the connection processes.
+fn connection<C>((capture, stream, handler): (C, TcpStream, FuncRef<Handler<C>>), _: Mailbox<()>) {
+    handler(capture, stream);
+}
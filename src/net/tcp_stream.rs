@@ -1,11 +1,11 @@
 use std::cell::UnsafeCell;
 use std::io::{Error, ErrorKind, IoSlice, Read, Result, Write};
-use std::net::SocketAddr;
+use std::net::{Shutdown, SocketAddr};
 use std::time::Duration;
 
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use super::SocketAddrIterator;
+use super::{check, SocketAddrIterator};
 use crate::error::LunaticError;
 use crate::host;
 
@@ -168,14 +168,140 @@ impl TcpStream {
         let result = unsafe {
             host::api::networking::tcp_peer_addr(self.id, &mut dns_iter_or_error_id as *mut u64)
         };
-        if result == 0 {
-            let mut dns_iter = SocketAddrIterator::from(dns_iter_or_error_id);
-            let addr = dns_iter.next().expect("must contain one element");
-            Ok(addr)
-        } else {
-            let lunatic_error = LunaticError::Error(dns_iter_or_error_id);
-            Err(Error::new(ErrorKind::Other, lunatic_error))
-        }
+        check(result, dns_iter_or_error_id).map(|dns_iter_id| {
+            let mut dns_iter = SocketAddrIterator::from(dns_iter_id);
+            dns_iter.next().expect("must contain one element")
+        })
+    }
+
+    /// Returns the local address this socket is bound to.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        let mut dns_iter_or_error_id = 0;
+        let result = unsafe {
+            host::api::networking::tcp_stream_local_addr(
+                self.id,
+                &mut dns_iter_or_error_id as *mut u64,
+            )
+        };
+        check(result, dns_iter_or_error_id).map(|dns_iter_id| {
+            let mut dns_iter = SocketAddrIterator::from(dns_iter_id);
+            dns_iter.next().expect("must contain one element")
+        })
+    }
+
+    /// Shuts down the read, write, or both halves of this connection.
+    ///
+    /// This function will cause all pending and future I/O on the specified
+    /// portions to return immediately with an appropriate value. Shutting down
+    /// the write half signals the end of the stream to the other side, while
+    /// still allowing to read the rest of its data.
+    ///
+    /// Like the timeouts, this affects everyone holding a reference to the
+    /// TcpStream.
+    pub fn shutdown(&self, how: Shutdown) -> Result<()> {
+        let how = match how {
+            Shutdown::Read => 0,
+            Shutdown::Write => 1,
+            Shutdown::Both => 2,
+        };
+        let mut error_id = 0;
+        let result =
+            unsafe { host::api::networking::tcp_shutdown(self.id, how, &mut error_id as *mut u64) };
+        check(result, error_id).map(|_| ())
+    }
+
+    /// Sets the value of the `TCP_NODELAY` option on this socket.
+    ///
+    /// If set, this option disables the Nagle algorithm. This means that
+    /// segments are always sent as soon as possible, even if there is only a
+    /// small amount of data.
+    pub fn set_nodelay(&self, nodelay: bool) -> Result<()> {
+        let mut error_id = 0;
+        let result = unsafe {
+            host::api::networking::tcp_set_nodelay(
+                self.id,
+                nodelay as u32,
+                &mut error_id as *mut u64,
+            )
+        };
+        check(result, error_id).map(|_| ())
+    }
+
+    /// Gets the value of the `TCP_NODELAY` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`TcpStream::set_nodelay`].
+    pub fn nodelay(&self) -> Result<bool> {
+        let mut nodelay_or_error_id = 0;
+        let result = unsafe {
+            host::api::networking::tcp_nodelay(self.id, &mut nodelay_or_error_id as *mut u64)
+        };
+        check(result, nodelay_or_error_id).map(|nodelay| nodelay != 0)
+    }
+
+    /// Enables `SO_KEEPALIVE` on this socket, sending the first keepalive
+    /// probe after the connection was idle for `idle`.
+    ///
+    /// Keepalive probes are disabled by passing `None`.
+    pub fn set_keepalive(&self, idle: Option<Duration>) -> Result<()> {
+        let mut error_id = 0;
+        let result = unsafe {
+            host::api::networking::tcp_set_keepalive(
+                self.id,
+                idle.map_or(u64::MAX, |d| d.as_millis() as u64),
+                &mut error_id as *mut u64,
+            )
+        };
+        check(result, error_id).map(|_| ())
+    }
+
+    /// Gets the idle time after which keepalive probes are sent, or `None` if
+    /// `SO_KEEPALIVE` is disabled.
+    ///
+    /// For more information about this option, see
+    /// [`TcpStream::set_keepalive`].
+    pub fn keepalive(&self) -> Result<Option<Duration>> {
+        let mut idle_or_error_id = 0;
+        let result = unsafe {
+            host::api::networking::tcp_keepalive(self.id, &mut idle_or_error_id as *mut u64)
+        };
+        check(result, idle_or_error_id).map(|idle| match idle {
+            u64::MAX => None,
+            millis => Some(Duration::from_millis(millis)),
+        })
+    }
+
+    /// Sets the value of the `SO_LINGER` option on this socket.
+    ///
+    /// This value controls how the socket is closed when data remains to be
+    /// sent. If set, closing the socket blocks until all the data is sent or
+    /// the timeout expires. If `None`, closing returns immediately and the
+    /// data is sent in the background.
+    pub fn set_linger(&self, linger: Option<Duration>) -> Result<()> {
+        let mut error_id = 0;
+        let result = unsafe {
+            host::api::networking::tcp_set_linger(
+                self.id,
+                linger.map_or(u64::MAX, |d| d.as_millis() as u64),
+                &mut error_id as *mut u64,
+            )
+        };
+        check(result, error_id).map(|_| ())
+    }
+
+    /// Gets the value of the `SO_LINGER` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`TcpStream::set_linger`].
+    pub fn linger(&self) -> Result<Option<Duration>> {
+        let mut linger_or_error_id = 0;
+        let result = unsafe {
+            host::api::networking::tcp_linger(self.id, &mut linger_or_error_id as *mut u64)
+        };
+        check(result, linger_or_error_id).map(|linger| match linger {
+            u64::MAX => None,
+            millis => Some(Duration::from_millis(millis)),
+        })
     }
 
     /// Sets write timeout for TcpStream
@@ -311,13 +437,9 @@ impl Write for TcpStream {
 
     fn flush(&mut self) -> Result<()> {
         let mut error_id = 0;
-        match unsafe { host::api::networking::tcp_flush(self.id, &mut error_id as *mut u64) } {
-            0 => Ok(()),
-            _ => {
-                let lunatic_error = LunaticError::Error(error_id);
-                Err(Error::new(ErrorKind::Other, lunatic_error))
-            }
-        }
+        let result =
+            unsafe { host::api::networking::tcp_flush(self.id, &mut error_id as *mut u64) };
+        check(result, error_id).map(|_| ())
     }
 }
 
@@ -0,0 +1,254 @@
+use std::io::Result;
+
+use serde::{Deserialize, Serialize};
+
+use super::check;
+use crate::host;
+
+/// Whether a [`TlsListener`](super::TlsListener) asks clients for a
+/// certificate.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClientAuth {
+    /// Clients are not asked for a certificate.
+    #[default]
+    None,
+    /// Clients may present a certificate, which is verified if present.
+    Optional,
+    /// Clients must present a valid certificate (mutual TLS).
+    Required,
+}
+
+/// Configuration of TLS connections and listeners.
+///
+/// The same configuration type is used on both sides of a connection, but some
+/// options only apply to one of them:
+///
+/// * [`server_name`](TlsConfig::server_name) only applies to clients.
+/// * [`client_auth`](TlsConfig::client_auth) and
+///   [`proxy_protocol`](TlsConfig::proxy_protocol) only apply to listeners.
+///
+/// Root certificates are used to verify the peer. For clients, the
+/// [`certificate`](TlsConfig::certificate) is presented to servers that ask for
+/// one. For listeners, it's required and identifies the server.
+///
+/// The configuration is a plain value that can be cloned and sent to other
+/// processes. It's only turned into a host resource when connecting or
+/// binding.
+///
+/// # Example
+///
+/// ```no_run
+/// use lunatic::net::{TlsConfig, TlsStream};
+///
+/// let ca = std::fs::read("ca.crt").unwrap();
+/// let cert = std::fs::read("client.crt").unwrap();
+/// let key = std::fs::read("client.key").unwrap();
+/// let config = TlsConfig::new()
+///     .default_roots(false)
+///     .add_root_certificate(ca)
+///     .certificate(cert, key)
+///     .alpn_protocols(["h2", "http/1.1"])
+///     .server_name("internal.example.com");
+/// let stream = TlsStream::connect_with_config("10.0.0.1", 443, &config).unwrap();
+/// assert_eq!(stream.alpn_protocol().as_deref(), Some(&b"h2"[..]));
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TlsConfig {
+    default_roots: bool,
+    roots: Vec<Vec<u8>>,
+    certificate: Option<(Vec<u8>, Vec<u8>)>,
+    client_auth: ClientAuth,
+    alpn_protocols: Vec<Vec<u8>>,
+    server_name: Option<String>,
+    verify: bool,
+    proxy_protocol: bool,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TlsConfig {
+    /// Creates a configuration trusting the default root certificates, without
+    /// a certificate of its own.
+    pub fn new() -> Self {
+        Self {
+            default_roots: true,
+            roots: Vec::new(),
+            certificate: None,
+            client_auth: ClientAuth::None,
+            alpn_protocols: Vec::new(),
+            server_name: None,
+            verify: true,
+            proxy_protocol: false,
+        }
+    }
+
+    /// Sets whether the default (webpki) root certificates are trusted.
+    /// Enabled by default.
+    ///
+    /// Disabling them and adding custom roots with
+    /// [`add_root_certificate`](Self::add_root_certificate) restricts the
+    /// trusted peers to a private certificate authority.
+    #[must_use]
+    pub fn default_roots(mut self, enabled: bool) -> Self {
+        self.default_roots = enabled;
+        self
+    }
+
+    /// Trusts an additional PEM encoded root certificate.
+    #[must_use]
+    pub fn add_root_certificate(mut self, cert: Vec<u8>) -> Self {
+        self.roots.push(cert);
+        self
+    }
+
+    /// Sets the PEM encoded certificate chain and private key identifying this
+    /// side of the connection.
+    #[must_use]
+    pub fn certificate(mut self, certs: Vec<u8>, key: Vec<u8>) -> Self {
+        self.certificate = Some((certs, key));
+        self
+    }
+
+    /// Sets whether listeners ask clients for a certificate, which is then
+    /// verified against the root certificates. Defaults to
+    /// [`ClientAuth::None`].
+    #[must_use]
+    pub fn client_auth(mut self, client_auth: ClientAuth) -> Self {
+        self.client_auth = client_auth;
+        self
+    }
+
+    /// Sets the protocols offered during ALPN negotiation, in order of
+    /// preference.
+    ///
+    /// The negotiated protocol can be read with
+    /// [`TlsStream::alpn_protocol`](super::TlsStream::alpn_protocol).
+    #[must_use]
+    pub fn alpn_protocols<P: AsRef<[u8]>>(
+        mut self,
+        protocols: impl IntoIterator<Item = P>,
+    ) -> Self {
+        self.alpn_protocols = protocols
+            .into_iter()
+            .map(|protocol| protocol.as_ref().to_vec())
+            .collect();
+        self
+    }
+
+    /// Overrides the server name sent with SNI and used to verify the server
+    /// certificate. Defaults to the address passed when connecting.
+    #[must_use]
+    pub fn server_name(mut self, name: impl Into<String>) -> Self {
+        self.server_name = Some(name.into());
+        self
+    }
+
+    /// Disables the verification of the peer's certificate.
+    ///
+    /// # Warning
+    ///
+    /// Any certificate will be trusted, exposing the connection to
+    /// man-in-the-middle attacks. Only use this for testing.
+    #[must_use]
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.verify = !accept;
+        self
+    }
+
+    /// Makes listeners read a PROXY protocol header from new connections,
+    /// before the TLS handshake. Disabled by default.
+    ///
+    /// The header can be retrieved by accepting connections with
+    /// [`TlsListener::accept_proxied`](super::TlsListener::accept_proxied).
+    /// Connections without a valid header are rejected.
+    #[must_use]
+    pub fn proxy_protocol(mut self, enabled: bool) -> Self {
+        self.proxy_protocol = enabled;
+        self
+    }
+
+    /// Creates the host resource for this configuration.
+    pub(crate) fn create(&self, server: bool) -> Result<HostTlsConfig> {
+        let config = HostTlsConfig(unsafe {
+            host::api::networking::create_tls_config(if server { 1 } else { 0 })
+        });
+        let id = config.0;
+        unsafe {
+            host::api::networking::tls_config_set_default_roots(
+                id,
+                if self.default_roots { 1 } else { 0 },
+            );
+            host::api::networking::tls_config_set_verify(id, if self.verify { 1 } else { 0 });
+            host::api::networking::tls_config_set_proxy_protocol(
+                id,
+                if self.proxy_protocol { 1 } else { 0 },
+            );
+        }
+        for cert in self.roots.iter() {
+            let mut error_id = 0;
+            let result = unsafe {
+                host::api::networking::tls_config_add_root_certificate(
+                    id,
+                    cert.as_ptr(),
+                    cert.len(),
+                    &mut error_id as *mut u64,
+                )
+            };
+            check(result, error_id)?;
+        }
+        if let Some((certs, key)) = &self.certificate {
+            let mut error_id = 0;
+            let result = unsafe {
+                host::api::networking::tls_config_set_certificate(
+                    id,
+                    certs.as_ptr(),
+                    certs.len(),
+                    key.as_ptr(),
+                    key.len(),
+                    &mut error_id as *mut u64,
+                )
+            };
+            check(result, error_id)?;
+        }
+        let client_auth = match self.client_auth {
+            ClientAuth::None => 0,
+            ClientAuth::Optional => 1,
+            ClientAuth::Required => 2,
+        };
+        unsafe { host::api::networking::tls_config_set_client_auth(id, client_auth) };
+        for protocol in self.alpn_protocols.iter() {
+            unsafe {
+                host::api::networking::tls_config_add_alpn_protocol(
+                    id,
+                    protocol.as_ptr(),
+                    protocol.len(),
+                )
+            };
+        }
+        if let Some(name) = &self.server_name {
+            unsafe {
+                host::api::networking::tls_config_set_server_name(id, name.as_ptr(), name.len())
+            };
+        }
+        Ok(config)
+    }
+}
+
+/// A TLS configuration held by the host as a resource.
+pub(crate) struct HostTlsConfig(u64);
+
+impl HostTlsConfig {
+    pub(crate) fn id(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Drop for HostTlsConfig {
+    fn drop(&mut self) {
+        unsafe { host::api::networking::drop_tls_config(self.0) };
+    }
+}
@@ -1,10 +1,10 @@
 use std::io::{Error, ErrorKind, Result};
 use std::net::SocketAddr;
 
-use super::SocketAddrIterator;
+use super::{proxy_protocol, ProxyHeader, SocketAddrIterator};
 use crate::error::LunaticError;
 use crate::host;
-use crate::net::TlsStream;
+use crate::net::{TlsConfig, TlsStream};
 
 /// A TLS server, listening for connections.
 ///
@@ -69,54 +69,51 @@ impl TlsListener {
     where
         A: super::ToSocketAddrs,
     {
-        let mut id = 0;
-        for addr in addr.to_socket_addrs()? {
-            let result = match addr {
-                SocketAddr::V4(v4_addr) => {
-                    let ip = v4_addr.ip().octets();
-                    let port = v4_addr.port() as u32;
-                    unsafe {
-                        host::api::networking::tls_bind(
-                            4,
-                            ip.as_ptr(),
-                            port,
-                            0,
-                            0,
-                            &mut id as *mut u64,
-                            certs.as_ptr() as *const u32,
-                            certs.len(),
-                            keys.as_ptr() as *const u32,
-                            keys.len(),
-                        )
-                    }
-                }
-                SocketAddr::V6(v6_addr) => {
-                    let ip = v6_addr.ip().octets();
-                    let port = v6_addr.port() as u32;
-                    let flow_info = v6_addr.flowinfo();
-                    let scope_id = v6_addr.scope_id();
-                    unsafe {
-                        host::api::networking::tls_bind(
-                            6,
-                            ip.as_ptr(),
-                            port,
-                            flow_info,
-                            scope_id,
-                            &mut id as *mut u64,
-                            certs.as_ptr() as *const u32,
-                            certs.len(),
-                            keys.as_ptr() as *const u32,
-                            keys.len(),
-                        )
-                    }
-                }
-            };
-            if result == 0 {
-                return Ok(Self { id });
-            }
-        }
-        let lunatic_error = LunaticError::Error(id);
-        Err(Error::new(ErrorKind::Other, lunatic_error))
+        bind_each(
+            addr,
+            |addr_type, ip, port, flow_info, scope_id, id| unsafe {
+                host::api::networking::tls_bind(
+                    addr_type,
+                    ip.as_ptr(),
+                    port,
+                    flow_info,
+                    scope_id,
+                    id as *mut u64,
+                    certs.as_ptr() as *const u32,
+                    certs.len(),
+                    keys.as_ptr() as *const u32,
+                    keys.len(),
+                )
+            },
+        )
+    }
+
+    /// Creates a new [`TlsListener`] bound to the given address, configured
+    /// with `config`.
+    ///
+    /// The configuration must contain the server's
+    /// [`certificate`](TlsConfig::certificate). It also controls whether
+    /// clients need to present a certificate and which ALPN protocols are
+    /// accepted.
+    pub fn bind_with_config<A>(addr: A, config: &TlsConfig) -> Result<Self>
+    where
+        A: super::ToSocketAddrs,
+    {
+        let config = config.create(true)?;
+        bind_each(
+            addr,
+            |addr_type, ip, port, flow_info, scope_id, id| unsafe {
+                host::api::networking::tls_bind_config(
+                    addr_type,
+                    ip.as_ptr(),
+                    port,
+                    flow_info,
+                    scope_id,
+                    config.id(),
+                    id as *mut u64,
+                )
+            },
+        )
     }
 
     /// Accepts a new incoming connection.
@@ -146,6 +143,34 @@ impl TlsListener {
         }
     }
 
+    /// Accepts a new incoming connection from a proxy or load balancer that
+    /// sends a [PROXY protocol] header (version 1 or 2) before the TLS
+    /// handshake.
+    ///
+    /// The listener needs to be created with [`TlsListener::bind_with_config`]
+    /// and [`TlsConfig::proxy_protocol`] enabled, so that the header is read
+    /// before the handshake.
+    ///
+    /// Returns the TLS stream, the address of the original client and the
+    /// header itself. If the proxy didn't forward a client connection, e.g.
+    /// for health checks, the address of the proxy is returned instead.
+    ///
+    /// [PROXY protocol]: https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt
+    pub fn accept_proxied(&self) -> Result<(TlsStream, SocketAddr, ProxyHeader)> {
+        let (tls_stream, peer) = self.accept()?;
+        let header = match tls_stream.proxy_header() {
+            Some(header) => proxy_protocol::read_header(&mut header.as_slice())?,
+            None => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "PROXY protocol is not enabled on the listener",
+                ))
+            }
+        };
+        let client = header.source().unwrap_or(peer);
+        Ok((tls_stream, client, header))
+    }
+
     /// Returns the local address that this listener is bound to.
     ///
     /// This can be useful, for example, to identify when binding to port 0
@@ -165,3 +190,37 @@ impl TlsListener {
         }
     }
 }
+
+/// Calls `bind` with the address type, IP, port, flow info and scope ID of
+/// each address until it succeeds. `bind` writes the listener ID or the error
+/// ID into its last argument.
+fn bind_each<A>(
+    addr: A,
+    mut bind: impl FnMut(u32, &[u8], u32, u32, u32, &mut u64) -> u32,
+) -> Result<TlsListener>
+where
+    A: super::ToSocketAddrs,
+{
+    let mut id = 0;
+    for addr in addr.to_socket_addrs()? {
+        let result = match addr {
+            SocketAddr::V4(v4_addr) => {
+                let ip = v4_addr.ip().octets();
+                let port = v4_addr.port() as u32;
+                bind(4, &ip, port, 0, 0, &mut id)
+            }
+            SocketAddr::V6(v6_addr) => {
+                let ip = v6_addr.ip().octets();
+                let port = v6_addr.port() as u32;
+                let flow_info = v6_addr.flowinfo();
+                let scope_id = v6_addr.scope_id();
+                bind(6, &ip, port, flow_info, scope_id, &mut id)
+            }
+        };
+        if result == 0 {
+            return Ok(TlsListener { id });
+        }
+    }
+    let lunatic_error = LunaticError::Error(id);
+    Err(Error::new(ErrorKind::Other, lunatic_error))
+}
@@ -5,6 +5,7 @@ use std::time::Duration;
 use serde::de::{self, Visitor};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
+use super::{check, TlsConfig};
 use crate::error::LunaticError;
 use crate::host;
 
@@ -170,6 +171,95 @@ impl TlsStream {
         Err(Error::new(ErrorKind::Other, lunatic_error))
     }
 
+    /// Creates a TLS connection to the specified address, configured with
+    /// `config`.
+    ///
+    /// The configuration controls the trusted root certificates, the client
+    /// certificate, ALPN protocols and the server name used for SNI and
+    /// verification.
+    pub fn connect_with_config(addr: &str, port: u32, config: &TlsConfig) -> Result<Self> {
+        TlsStream::connect_config_(addr, None, port, config)
+    }
+
+    /// Same as [`TlsStream::connect_with_config`], but only waits for the
+    /// duration of timeout to connect.
+    pub fn connect_timeout_with_config(
+        addr: &str,
+        timeout: Duration,
+        port: u32,
+        config: &TlsConfig,
+    ) -> Result<Self> {
+        TlsStream::connect_config_(addr, Some(timeout), port, config)
+    }
+
+    fn connect_config_(
+        addr: &str,
+        timeout: Option<Duration>,
+        port: u32,
+        config: &TlsConfig,
+    ) -> Result<Self> {
+        let config = config.create(false)?;
+        let mut id = 0;
+        let timeout_ms = match timeout {
+            Some(timeout) => timeout.as_millis() as u64,
+            None => u64::MAX,
+        };
+        let result = unsafe {
+            host::api::networking::tls_connect_config(
+                addr.as_ptr(),
+                addr.len() as u32,
+                port,
+                timeout_ms,
+                config.id(),
+                &mut id as *mut u64,
+            )
+        };
+        check(result, id).map(TlsStream::from)
+    }
+
+    /// Returns the protocol agreed on during ALPN negotiation, if any.
+    pub fn alpn_protocol(&self) -> Option<Vec<u8>> {
+        let size = unsafe { host::api::networking::tls_alpn_protocol_size(self.id) };
+        if size == u32::MAX {
+            return None;
+        }
+        let mut protocol = vec![0; size as usize];
+        unsafe { host::api::networking::tls_alpn_protocol(self.id, protocol.as_mut_ptr()) };
+        Some(protocol)
+    }
+
+    /// Returns the DER encoded certificate chain presented by the peer,
+    /// starting with the peer's own certificate.
+    ///
+    /// The chain is empty if the peer didn't present a certificate, e.g. a
+    /// client connecting to a listener that doesn't require one.
+    pub fn peer_certificates(&self) -> Vec<Vec<u8>> {
+        let count = unsafe { host::api::networking::tls_peer_certificates_count(self.id) };
+        (0..count)
+            .map(|index| {
+                let size =
+                    unsafe { host::api::networking::tls_peer_certificate_size(self.id, index) };
+                let mut cert = vec![0; size as usize];
+                unsafe {
+                    host::api::networking::tls_peer_certificate(self.id, index, cert.as_mut_ptr())
+                };
+                cert
+            })
+            .collect()
+    }
+
+    /// Returns the raw PROXY protocol header read by the host before the
+    /// handshake, if the listener was configured to expect one.
+    pub(crate) fn proxy_header(&self) -> Option<Vec<u8>> {
+        let size = unsafe { host::api::networking::tls_proxy_header_size(self.id) };
+        if size == u32::MAX {
+            return None;
+        }
+        let mut header = vec![0; size as usize];
+        unsafe { host::api::networking::tls_proxy_header(self.id, header.as_mut_ptr()) };
+        Some(header)
+    }
+
     /// Sets write timeout for TlsStream
     ///
     /// This method will change the timeout for everyone holding a reference to
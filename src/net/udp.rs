@@ -1,11 +1,14 @@
 use std::cell::UnsafeCell;
 use std::io::{Error, ErrorKind, Result};
-use std::net::SocketAddr;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
 
-use super::SocketAddrIterator;
+use super::{check, SocketAddrIterator};
 use crate::error::LunaticError;
 use crate::host;
 
+const TIMEOUT: u32 = 9027;
+
 /// A UDP socket.
 ///
 /// After creating a `UdpSocket` by [`bind`]ing it to a socket address, data can
@@ -20,6 +23,11 @@ use crate::host;
 /// UDP is an unordered, unreliable protocol; refer to [`TcpListener`] and
 /// [`TcpStream`] for TCP primitives.
 ///
+/// By default, receiving and sending block until they can complete. Use
+/// [`set_read_timeout`] and [`set_write_timeout`] to give up after a while, or
+/// [`set_nonblocking`] to return right away. In both cases the operation fails
+/// with an error of kind [`ErrorKind::WouldBlock`].
+///
 /// [`bind`]: UdpSocket::bind
 /// [`connect`]: UdpSocket::connect
 /// [IETF RFC 768]: https://tools.ietf.org/html/rfc768
@@ -27,6 +35,9 @@ use crate::host;
 /// [received from]: UdpSocket::recv_from
 /// [`send`]: UdpSocket::send
 /// [sent to]: UdpSocket::send_to
+/// [`set_read_timeout`]: UdpSocket::set_read_timeout
+/// [`set_write_timeout`]: UdpSocket::set_write_timeout
+/// [`set_nonblocking`]: UdpSocket::set_nonblocking
 /// [`TcpListener`]: crate::net::TcpListener
 /// [`TcpStream`]: crate::net::TcpStream
 ///
@@ -138,14 +149,10 @@ impl UdpSocket {
         let result = unsafe {
             host::api::networking::udp_local_addr(self.id, &mut dns_iter_or_error_id as *mut u64)
         };
-        if result == 0 {
-            let mut dns_iter = SocketAddrIterator::from(dns_iter_or_error_id);
-            let addr = dns_iter.next().expect("must contain one element");
-            Ok(addr)
-        } else {
-            let lunatic_error = LunaticError::Error(dns_iter_or_error_id);
-            Err(Error::new(ErrorKind::Other, lunatic_error))
-        }
+        check(result, dns_iter_or_error_id).map(|dns_iter_id| {
+            let mut dns_iter = SocketAddrIterator::from(dns_iter_id);
+            dns_iter.next().expect("must contain one element")
+        })
     }
 
     /// Returns the remote address this socket was connected to.
@@ -269,12 +276,7 @@ impl UdpSocket {
                 &mut nsend_or_error_id as *mut u64,
             )
         };
-        if result == 0 {
-            Ok(nsend_or_error_id as usize)
-        } else {
-            let lunatic_error = LunaticError::Error(nsend_or_error_id);
-            Err(Error::new(ErrorKind::Other, lunatic_error))
-        }
+        check_timeout(result, nsend_or_error_id).map(|nsend| nsend as usize)
     }
 
     /// Sends data on the socket to the given address. On success, returns the
@@ -341,8 +343,13 @@ impl UdpSocket {
                     }
                 }
             };
-            if result == 0 {
-                return Ok(nsend_or_error_id as usize);
+            match result {
+                0 => return Ok(nsend_or_error_id as usize),
+                // Trying the next address won't help if the socket isn't ready.
+                TIMEOUT => {
+                    return check_timeout(result, nsend_or_error_id).map(|nsend| nsend as usize)
+                }
+                _ => {}
             }
         }
         let lunatic_error = LunaticError::Error(nsend_or_error_id);
@@ -383,12 +390,7 @@ impl UdpSocket {
                 &mut nrecv_or_error_id as *mut u64,
             )
         };
-        if result == 0 {
-            Ok(nrecv_or_error_id as usize)
-        } else {
-            let lunatic_error = LunaticError::Error(nrecv_or_error_id);
-            Err(Error::new(ErrorKind::Other, lunatic_error))
-        }
+        check_timeout(result, nrecv_or_error_id).map(|nrecv| nrecv as usize)
     }
 
     /// Receives a single datagram message on the socket. On success, returns
@@ -421,14 +423,11 @@ impl UdpSocket {
                 &mut dns_iter_id as *mut u64,
             )
         };
-        if result == 0 {
+        check_timeout(result, nrecv_or_error_id).map(|nrecv| {
             let mut dns_iter = SocketAddrIterator::from(dns_iter_id);
             let peer = dns_iter.next().expect("must contain one element");
-            Ok((nrecv_or_error_id as usize, peer))
-        } else {
-            let lunatic_error = LunaticError::Error(nrecv_or_error_id);
-            Err(Error::new(ErrorKind::Other, lunatic_error))
-        }
+            (nrecv as usize, peer)
+        })
     }
 
     /// Sets the value for the `IP_TTL` option on this socket.
@@ -539,13 +538,226 @@ impl UdpSocket {
         })
     }
 
-    /// Dummy fn - This is just to make porting from std easier?
-    pub fn set_nonblocking(&self, _: bool) -> Result<()> {
+    /// Sets the read timeout of the socket.
+    ///
+    /// If the value is `None`, [`recv`](Self::recv) and
+    /// [`recv_from`](Self::recv_from) block until a datagram arrives.
+    /// Otherwise they fail with an error of kind [`ErrorKind::WouldBlock`] once
+    /// the timeout expires.
+    ///
+    /// Like the other options, this affects all handles to the socket. An
+    /// error is returned if a zero [`Duration`] is passed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// use lunatic::net::UdpSocket;
+    ///
+    /// let socket = UdpSocket::bind("127.0.0.1:34254").expect("couldn't bind to address");
+    /// socket
+    ///     .set_read_timeout(Some(Duration::from_secs(1)))
+    ///     .expect("set_read_timeout call failed");
+    /// ```
+    pub fn set_read_timeout(&self, duration: Option<Duration>) -> Result<()> {
+        let duration = timeout_millis(duration)?;
+        unsafe { host::api::networking::set_udp_socket_read_timeout(self.id, duration) };
+        Ok(())
+    }
+
+    /// Returns the read timeout of the socket.
+    ///
+    /// For more information about this option, see
+    /// [`UdpSocket::set_read_timeout`].
+    pub fn read_timeout(&self) -> Result<Option<Duration>> {
+        let duration = unsafe { host::api::networking::get_udp_socket_read_timeout(self.id) };
+        Ok(from_millis(duration))
+    }
+
+    /// Sets the write timeout of the socket.
+    ///
+    /// If the value is `None`, [`send`](Self::send) and
+    /// [`send_to`](Self::send_to) block until the datagram is queued.
+    /// Otherwise they fail with an error of kind [`ErrorKind::WouldBlock`] once
+    /// the timeout expires.
+    ///
+    /// Like the other options, this affects all handles to the socket. An
+    /// error is returned if a zero [`Duration`] is passed.
+    pub fn set_write_timeout(&self, duration: Option<Duration>) -> Result<()> {
+        let duration = timeout_millis(duration)?;
+        unsafe { host::api::networking::set_udp_socket_write_timeout(self.id, duration) };
         Ok(())
     }
 
-    /// Dummy fn - This is just to make porting from std easier?
+    /// Returns the write timeout of the socket.
+    ///
+    /// For more information about this option, see
+    /// [`UdpSocket::set_write_timeout`].
+    pub fn write_timeout(&self) -> Result<Option<Duration>> {
+        let duration = unsafe { host::api::networking::get_udp_socket_write_timeout(self.id) };
+        Ok(from_millis(duration))
+    }
+
+    /// Moves this socket into or out of non-blocking mode.
+    ///
+    /// In non-blocking mode, receiving and sending return an error of kind
+    /// [`ErrorKind::WouldBlock`] right away if the operation can't complete,
+    /// instead of waiting. Non-blocking mode takes precedence over the
+    /// timeouts.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::io::ErrorKind;
+    ///
+    /// use lunatic::net::UdpSocket;
+    ///
+    /// let socket = UdpSocket::bind("127.0.0.1:34254").expect("couldn't bind to address");
+    /// socket.set_nonblocking(true).expect("set_nonblocking call failed");
+    /// let mut buf = [0; 10];
+    /// match socket.recv(&mut buf) {
+    ///     Ok(received) => println!("received {received} bytes"),
+    ///     Err(e) if e.kind() == ErrorKind::WouldBlock => println!("nothing to receive"),
+    ///     Err(e) => panic!("encountered IO error: {e}"),
+    /// }
+    /// ```
+    pub fn set_nonblocking(&self, nonblocking: bool) -> Result<()> {
+        let api_nonblocking = if nonblocking { 1 } else { 0 };
+        unsafe { host::api::networking::set_udp_socket_nonblocking(self.id, api_nonblocking) };
+        Ok(())
+    }
+
+    /// Gets the value of the `SO_ERROR` option on this socket.
+    ///
+    /// This will retrieve the stored error in the underlying socket, clearing
+    /// the field in the process. This can be useful for checking errors
+    /// between calls.
     pub fn take_error(&self) -> Result<Option<LunaticError>> {
-        Ok(None)
+        let mut error_id = 0;
+        let result =
+            unsafe { host::api::networking::udp_take_error(self.id, &mut error_id as *mut u64) };
+        match result {
+            0 => Ok(None),
+            _ => Ok(Some(LunaticError::Error(error_id))),
+        }
+    }
+
+    /// Executes an operation of the `IP_ADD_MEMBERSHIP` type.
+    ///
+    /// This function specifies a new multicast group for this socket to join.
+    /// The address must be a valid multicast address, and `interface` is the
+    /// address of the local interface with which the system should join the
+    /// multicast group. If it's equal to [`Ipv4Addr::UNSPECIFIED`] then an
+    /// appropriate interface is chosen by the system.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::Ipv4Addr;
+    ///
+    /// use lunatic::net::UdpSocket;
+    ///
+    /// let socket = UdpSocket::bind("0.0.0.0:5353").expect("couldn't bind to address");
+    /// socket
+    ///     .join_multicast_v4(&Ipv4Addr::new(224, 0, 0, 251), &Ipv4Addr::UNSPECIFIED)
+    ///     .expect("join_multicast_v4 call failed");
+    /// ```
+    pub fn join_multicast_v4(&self, multiaddr: &Ipv4Addr, interface: &Ipv4Addr) -> Result<()> {
+        let mut error_id = 0;
+        let result = unsafe {
+            host::api::networking::udp_join_multicast_v4(
+                self.id,
+                multiaddr.octets().as_ptr(),
+                interface.octets().as_ptr(),
+                &mut error_id as *mut u64,
+            )
+        };
+        check(result, error_id).map(|_| ())
+    }
+
+    /// Executes an operation of the `IPV6_ADD_MEMBERSHIP` type.
+    ///
+    /// This function specifies a new multicast group for this socket to join.
+    /// The address must be a valid multicast address, and `interface` is the
+    /// index of the interface to join/leave (or 0 to indicate any interface).
+    pub fn join_multicast_v6(&self, multiaddr: &Ipv6Addr, interface: u32) -> Result<()> {
+        let mut error_id = 0;
+        let result = unsafe {
+            host::api::networking::udp_join_multicast_v6(
+                self.id,
+                multiaddr.octets().as_ptr(),
+                interface,
+                &mut error_id as *mut u64,
+            )
+        };
+        check(result, error_id).map(|_| ())
+    }
+
+    /// Executes an operation of the `IP_DROP_MEMBERSHIP` type.
+    ///
+    /// For more information about this option, see
+    /// [`UdpSocket::join_multicast_v4`].
+    pub fn leave_multicast_v4(&self, multiaddr: &Ipv4Addr, interface: &Ipv4Addr) -> Result<()> {
+        let mut error_id = 0;
+        let result = unsafe {
+            host::api::networking::udp_leave_multicast_v4(
+                self.id,
+                multiaddr.octets().as_ptr(),
+                interface.octets().as_ptr(),
+                &mut error_id as *mut u64,
+            )
+        };
+        check(result, error_id).map(|_| ())
+    }
+
+    /// Executes an operation of the `IPV6_DROP_MEMBERSHIP` type.
+    ///
+    /// For more information about this option, see
+    /// [`UdpSocket::join_multicast_v6`].
+    pub fn leave_multicast_v6(&self, multiaddr: &Ipv6Addr, interface: u32) -> Result<()> {
+        let mut error_id = 0;
+        let result = unsafe {
+            host::api::networking::udp_leave_multicast_v6(
+                self.id,
+                multiaddr.octets().as_ptr(),
+                interface,
+                &mut error_id as *mut u64,
+            )
+        };
+        check(result, error_id).map(|_| ())
+    }
+}
+
+/// Like [`check`], but reports a send or receive that gave up as
+/// [`ErrorKind::WouldBlock`], matching the standard library on Unix.
+fn check_timeout(result: u32, opaque: u64) -> Result<u64> {
+    if result == TIMEOUT {
+        Err(Error::new(
+            ErrorKind::WouldBlock,
+            "UdpSocket operation timed out",
+        ))
+    } else {
+        check(result, opaque)
+    }
+}
+
+/// Encodes a timeout for the host, where `u64::MAX` stands for no timeout.
+fn timeout_millis(duration: Option<Duration>) -> Result<u64> {
+    match duration {
+        Some(duration) if duration.is_zero() => Err(Error::new(
+            ErrorKind::InvalidInput,
+            "cannot set a 0 duration timeout",
+        )),
+        // Round up so that sub-millisecond timeouts don't turn into zero.
+        Some(duration) => Ok((duration.as_micros() as u64).div_ceil(1000)),
+        None => Ok(u64::MAX),
+    }
+}
+
+fn from_millis(duration: u64) -> Option<Duration> {
+    match duration {
+        u64::MAX => None,
+        millis => Some(Duration::from_millis(millis)),
     }
 }
@@ -0,0 +1,279 @@
+//! Named process groups.
+//!
+//! A [`ProcessGroup`] is a set of processes registered under a common name.
+//! Any process can [`join`](ProcessGroup::join) or
+//! [`leave`](ProcessGroup::leave) a group, look up its
+//! [`members`](ProcessGroup::members) or [`broadcast`](ProcessGroup::broadcast)
+//! a message to all of them. This makes groups a good fit for pub/sub and
+//! fan-out patterns.
+//!
+//! Groups are managed by a process group server that is started on demand on
+//! each node. Local members are monitored by the server and automatically
+//! removed from all groups when they die. Members from other nodes are not
+//! monitored and must leave the group explicitly.
+//!
+//! Group names are scoped by the message and serializer types of the
+//! processes, the same way it's done for the registry. Processes of different
+//! types can't end up in the same group.
+//!
+//! Groups are local to a node, but
+//! [`members_all_nodes`](ProcessGroup::members_all_nodes) and
+//! [`broadcast_all_nodes`](ProcessGroup::broadcast_all_nodes) can be used to
+//! reach the members of a group on all connected nodes.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use lunatic::pg::ProcessGroup;
+//! use lunatic::{Mailbox, Process};
+//!
+//! let subscriber = Process::spawn((), |_, mailbox: Mailbox<String>| loop {
+//!     println!("{}", mailbox.receive());
+//! });
+//! let chat = ProcessGroup::new("chat");
+//! chat.join(subscriber);
+//! chat.broadcast("Hello!".to_owned());
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ap::handlers::Request;
+use crate::ap::{AbstractProcess, Config, ProcessRef, RequestHandler, StartupError, State};
+use crate::function::process::{process_name, IntoProcess, ProcessType};
+use crate::serializer::{Bincode, CanSerialize};
+use crate::{distributed, host, Mailbox, Process, Tag};
+
+/// Name under which the process group server of each node is registered.
+const SERVER_NAME: &str = "lunatic::pg";
+
+/// A named group of processes of the type `Process<M, S>`.
+///
+/// `ProcessGroup` is only a handle to the group and can be freely created
+/// and cloned. All handles with the same name and type refer to the
+/// same group.
+pub struct ProcessGroup<M, S = Bincode> {
+    key: String,
+    phantom: PhantomData<(M, S)>,
+}
+
+impl<M, S> ProcessGroup<M, S> {
+    /// Returns a handle to the group `name`.
+    pub fn new(name: &str) -> Self {
+        Self {
+            // Scope the group name by the process type.
+            key: process_name::<M, S>(ProcessType::Process, name),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Adds `process` to the group.
+    ///
+    /// Joining a group the process is already a member of has no effect.
+    ///
+    /// Only processes running on this node are removed automatically when
+    /// they die. A process from another node stays a member until it
+    /// [leaves](Self::leave) the group, even if it or its node is gone.
+    pub fn join(&self, process: Process<M, S>) {
+        server().request(Join(self.key.clone(), (process.node_id(), process.id())));
+    }
+
+    /// Removes `process` from the group.
+    pub fn leave(&self, process: Process<M, S>) {
+        server().request(Leave(self.key.clone(), (process.node_id(), process.id())));
+    }
+
+    /// Returns all members of the group on this node, in the order they
+    /// joined.
+    pub fn members(&self) -> Vec<Process<M, S>> {
+        let members = server().request(Members(self.key.clone()));
+        into_processes(members)
+    }
+
+    /// Returns all members of the group on this and all connected nodes.
+    ///
+    /// The process group servers on other nodes are queried in parallel.
+    /// Nodes that can't be reached or don't respond before the `timeout`
+    /// expires are skipped.
+    pub fn members_all_nodes(&self, timeout: Duration) -> Vec<Process<M, S>> {
+        let mut members = server().request(Members(self.key.clone()));
+
+        let this_node = host::node_id();
+        let remote_nodes: Vec<u64> = distributed::nodes()
+            .into_iter()
+            .filter(|node| *node != this_node)
+            .collect();
+        let tag = Tag::new();
+        let parent = unsafe { Process::<Vec<(u64, u64)>>::this() };
+        let mut queried = 0;
+        for node in remote_nodes {
+            // Nodes can disconnect before the query is spawned, skip them.
+            let spawned = <Mailbox<()> as IntoProcess<(), Bincode>>::spawn(
+                (parent, tag, self.key.clone()),
+                |(parent, tag, key), _: Mailbox<()>| {
+                    let members = server().request(Members(key));
+                    parent.tag_send(tag, members);
+                },
+                None,
+                None,
+                None,
+                Some(node),
+            );
+            if spawned.is_ok() {
+                queried += 1;
+            }
+        }
+
+        let deadline = Instant::now() + timeout;
+        // Temporarily cast to right mailbox type.
+        let mailbox: Mailbox<Vec<(u64, u64)>> = unsafe { Mailbox::new() };
+        for _ in 0..queried {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match mailbox.tag_receive_timeout(&[tag], remaining) {
+                Ok(remote_members) => members.extend(remote_members),
+                Err(_) => break,
+            }
+        }
+        into_processes(members)
+    }
+}
+
+impl<M, S> ProcessGroup<M, S>
+where
+    M: Clone,
+    S: CanSerialize<M>,
+{
+    /// Sends `message` to all members of the group on this node.
+    pub fn broadcast(&self, message: M) {
+        for member in self.members() {
+            member.send(message.clone());
+        }
+    }
+
+    /// Sends `message` to all members of the group on this and all connected
+    /// nodes.
+    ///
+    /// See [`members_all_nodes`](Self::members_all_nodes) for the meaning of
+    /// `timeout`.
+    pub fn broadcast_all_nodes(&self, message: M, timeout: Duration) {
+        for member in self.members_all_nodes(timeout) {
+            member.send(message.clone());
+        }
+    }
+}
+
+impl<M, S> Clone for ProcessGroup<M, S> {
+    fn clone(&self) -> Self {
+        Self {
+            key: self.key.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<M, S> fmt::Debug for ProcessGroup<M, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProcessGroup")
+            .field("key", &self.key)
+            .finish()
+    }
+}
+
+/// Returns the process group server of this node, starting it if necessary.
+fn server() -> ProcessRef<ProcessGroups> {
+    if let Some(server) = ProcessRef::lookup(SERVER_NAME) {
+        return server;
+    }
+    match ProcessGroups::start_as(&SERVER_NAME, ()) {
+        // Another process could have started the server in the meantime.
+        Ok(server) | Err(StartupError::NameAlreadyRegistered(server)) => server,
+        Err(err) => panic!("failed to start the process group server: {err:?}"),
+    }
+}
+
+fn into_processes<M, S>(members: Vec<(u64, u64)>) -> Vec<Process<M, S>> {
+    members
+        .into_iter()
+        .map(|(node_id, id)| unsafe { Process::new(node_id, id) })
+        .collect()
+}
+
+/// The process group server, holding the members of all groups on a node.
+struct ProcessGroups;
+
+impl AbstractProcess for ProcessGroups {
+    type State = HashMap<String, Vec<(u64, u64)>>;
+    type Serializer = Bincode;
+    type Arg = ();
+    type Handlers = (Request<Join>, Request<Leave>, Request<Members>);
+    type StartupError = ();
+
+    fn init(_: Config<Self>, _: ()) -> Result<Self::State, ()> {
+        Ok(HashMap::new())
+    }
+
+    fn handle_process_death(mut state: State<Self>, process_id: u64) {
+        let member = (host::node_id(), process_id);
+        state.retain(|_, members| {
+            members.retain(|m| *m != member);
+            !members.is_empty()
+        });
+    }
+}
+
+/// Returns `true` if `member` is part of any group.
+fn is_member(groups: &HashMap<String, Vec<(u64, u64)>>, member: (u64, u64)) -> bool {
+    groups.values().any(|members| members.contains(&member))
+}
+
+#[derive(Serialize, Deserialize)]
+struct Join(String, (u64, u64));
+
+impl RequestHandler<Join> for ProcessGroups {
+    type Response = ();
+
+    fn handle(mut state: State<Self>, Join(group, member): Join) {
+        // Only local processes can be monitored.
+        if member.0 == host::node_id() && !is_member(&state, member) {
+            state.monitor(unsafe { Process::<()>::new(member.0, member.1) });
+        }
+        let members = state.entry(group).or_default();
+        if !members.contains(&member) {
+            members.push(member);
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Leave(String, (u64, u64));
+
+impl RequestHandler<Leave> for ProcessGroups {
+    type Response = ();
+
+    fn handle(mut state: State<Self>, Leave(group, member): Leave) {
+        if let Some(members) = state.get_mut(&group) {
+            members.retain(|m| *m != member);
+            if members.is_empty() {
+                state.remove(&group);
+            }
+        }
+        if member.0 == host::node_id() && !is_member(&state, member) {
+            state.stop_monitoring(unsafe { Process::<()>::new(member.0, member.1) });
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Members(String);
+
+impl RequestHandler<Members> for ProcessGroups {
+    type Response = Vec<(u64, u64)>;
+
+    fn handle(state: State<Self>, Members(group): Members) -> Vec<(u64, u64)> {
+        state.get(&group).cloned().unwrap_or_default()
+    }
+}
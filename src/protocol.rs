@@ -1,12 +1,16 @@
+pub mod multiparty;
+
 use std::any::TypeId;
 use std::marker::PhantomData;
-use std::mem::ManuallyDrop;
-use std::time::Duration;
+use std::mem::{self, ManuallyDrop};
+use std::time::{Duration, Instant};
 use std::{any, fmt};
 
+use thiserror::Error;
+
 use crate::function::process::IntoProcess;
 use crate::mailbox::MailboxError;
-use crate::serializer::{Bincode, CanSerialize};
+use crate::serializer::{Bincode, CanSerialize, DecodeError};
 use crate::{host, LunaticError, Mailbox, Process, ProcessConfig, Tag};
 
 /// A value that the protocol captures from the parent process.
@@ -31,12 +35,19 @@ pub struct ProtocolCapture<C> {
 ///
 /// Only `Protocol<End>` or `Protocol<TaskEnd>` can be dropped.
 /// All other protocols will panic if dropped without reaching
-/// `Protocol<End>` or `Protocol<TaskEnd>.
+/// `Protocol<End>` or `Protocol<TaskEnd>. A protocol can be abandoned early
+/// with [`cancel`](Protocol::cancel).
+///
+/// Receiving steps can be limited with a timeout (e.g.
+/// [`receive_timeout`](Protocol::receive_timeout)) and the whole protocol can
+/// be limited with a deadline ([`with_deadline`](Protocol::with_deadline)).
+/// If a step fails, the protocol is consumed and a [`ProtocolError`] returned.
 #[derive(Hash)]
 pub struct Protocol<P: 'static, S = Bincode, Z: 'static = ()> {
     id: u64,
     node_id: u64,
     tag: Tag,
+    deadline: Option<Instant>,
     phantom: PhantomData<(P, S, Z)>,
 }
 
@@ -81,6 +92,46 @@ impl<P, S, Z> Protocol<P, S, Z> {
             id: process.id(),
             node_id: process.node_id(),
             tag,
+            deadline: None,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Sets a deadline for all following steps of the protocol, `timeout`
+    /// from now.
+    ///
+    /// Once the deadline expires, receiving steps with a timeout return
+    /// [`ProtocolError::DeadlineExceeded`] and receiving steps without one
+    /// panic.
+    #[must_use]
+    pub fn with_deadline(mut self, timeout: Duration) -> Self {
+        self.deadline = Some(Instant::now() + timeout);
+        self
+    }
+
+    /// Returns the deadline of the protocol, if one is set.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Abandons the protocol before reaching the `End` state, without
+    /// panicking.
+    ///
+    /// The other side is not notified and needs to use timeouts if it should
+    /// not wait forever on the next message.
+    pub fn cancel(self) {
+        let _: Protocol<End, S, Z> = self.cast(); // Only `End` protocols can be dropped
+    }
+
+    /// Create a protocol of another type that continues the same session.
+    ///
+    /// The original protocol can't be dropped and needs to be forgotten.
+    fn duplicate<P2, Z2>(&self) -> Protocol<P2, S, Z2> {
+        Protocol {
+            id: self.id,
+            node_id: self.node_id,
+            tag: self.tag,
+            deadline: self.deadline,
             phantom: PhantomData,
         }
     }
@@ -89,11 +140,55 @@ impl<P, S, Z> Protocol<P, S, Z> {
     fn cast<P2, Z2>(self) -> Protocol<P2, S, Z2> {
         // Don't drop the session yet.
         let self_ = ManuallyDrop::new(self);
-        Protocol {
-            id: self_.id,
-            node_id: self_.node_id,
-            tag: self_.tag,
-            phantom: PhantomData,
+        self_.duplicate()
+    }
+
+    /// Receives the next message of the session.
+    ///
+    /// Waits at most for the duration of `timeout` or until the deadline
+    /// expires, whatever comes first.
+    fn receive_<A>(&self, timeout: Option<Duration>) -> Result<A, ProtocolError>
+    where
+        S: CanSerialize<A>,
+    {
+        let remaining = self
+            .deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        let (timeout, error) = match (timeout, remaining) {
+            (Some(timeout), Some(remaining)) if remaining < timeout => {
+                (Some(remaining), ProtocolError::DeadlineExceeded)
+            }
+            (None, Some(remaining)) => (Some(remaining), ProtocolError::DeadlineExceeded),
+            (timeout, _) => (timeout, ProtocolError::TimedOut),
+        };
+        // Temporarily cast to right mailbox type.
+        let mailbox: Mailbox<A, S> = unsafe { Mailbox::new() };
+        match timeout {
+            Some(timeout) => match mailbox.tag_receive_timeout(&[self.tag], timeout) {
+                Ok(message) => Ok(message),
+                Err(MailboxError::TimedOut) => Err(error),
+                Err(MailboxError::DeserializationFailed(err)) => {
+                    Err(ProtocolError::DeserializationFailed(err))
+                }
+            },
+            None => Ok(mailbox.tag_receive(&[self.tag])),
+        }
+    }
+
+    /// Receives the next message of the session, or panics if the deadline
+    /// expires.
+    #[track_caller]
+    fn receive_or_panic<A>(self) -> (Self, A)
+    where
+        S: CanSerialize<A>,
+    {
+        match self.receive_(None) {
+            Ok(message) => (self, message),
+            Err(err) => {
+                let state = any::type_name::<P>();
+                self.cancel();
+                panic!("Protocol failed in state {state}: {err}");
+            }
         }
     }
 }
@@ -111,7 +206,7 @@ where
         // Temporarily cast to right process type.
         let process: Process<A, S> = unsafe { Process::new(self_.node_id, self_.id) };
         process.tag_send(self_.tag, message);
-        Protocol::from_process_with_tag(process, self_.tag)
+        self_.duplicate()
     }
 }
 
@@ -121,12 +216,31 @@ where
 {
     /// Receives a value of type `A` from the session. Returns a tuple
     /// containing the resulting session and the received value.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the deadline of the protocol expires.
     #[must_use]
+    #[track_caller]
     pub fn receive(self) -> (Protocol<P, S, Z>, A) {
-        // Temporarily cast to right mailbox type.
-        let mailbox: Mailbox<A, S> = unsafe { Mailbox::new() };
-        let received = mailbox.tag_receive(&[self.tag]);
-        (self.cast(), received)
+        let (self_, received) = self.receive_or_panic();
+        (self_.cast(), received)
+    }
+
+    /// Same as `receive`, but only waits for the duration of timeout for the
+    /// value. If the timeout or the deadline of the protocol expires, the
+    /// protocol is consumed and an error returned.
+    pub fn receive_timeout(
+        self,
+        timeout: Duration,
+    ) -> Result<(Protocol<P, S, Z>, A), ProtocolError> {
+        match self.receive_(Some(timeout)) {
+            Ok(received) => Ok((self.cast(), received)),
+            Err(err) => {
+                self.cancel();
+                Err(err)
+            }
+        }
     }
 }
 
@@ -136,23 +250,30 @@ where
 {
     /// A task is a special case of a protocol spawned with the `spawn!(@task
     /// ...)` macro. It only returns one value.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the deadline of the protocol expires.
     #[must_use]
+    #[track_caller]
     pub fn result(self) -> A {
-        // Temporarily cast to right mailbox type.
-        let mailbox: Mailbox<A, S> = unsafe { Mailbox::new() };
-        let result = mailbox.tag_receive(&[self.tag]);
-        let _: Protocol<TaskEnd, S, Z> = self.cast(); // Only `End` protocols can be dropped
+        let (self_, result) = self.receive_or_panic();
+        let _: Protocol<TaskEnd, S, Z> = self_.cast(); // Only `End` protocols can be dropped
         result
     }
 
     /// A task is a special case of a protocol spawned with the `spawn!(@task
     /// ...)` macro. It only returns one value.
+    ///
+    /// Waits at most for the duration of `duration` or until the deadline of
+    /// the protocol expires, whatever comes first.
     pub fn result_timeout(self, duration: Duration) -> Result<A, MailboxError> {
-        // Temporarily cast to right mailbox type.
-        let mailbox: Mailbox<A, S> = unsafe { Mailbox::new() };
-        let result = mailbox.tag_receive_timeout(&[self.tag], duration);
+        let result = self.receive_(Some(duration));
         let _: Protocol<TaskEnd, S, Z> = self.cast(); // Only `End` protocols can be dropped
-        result
+        result.map_err(|err| match err {
+            ProtocolError::DeserializationFailed(err) => MailboxError::DeserializationFailed(err),
+            ProtocolError::TimedOut | ProtocolError::DeadlineExceeded => MailboxError::TimedOut,
+        })
     }
 }
 
@@ -168,7 +289,7 @@ where
         // Temporarily cast to right process type.
         let process: Process<bool, S> = unsafe { Process::new(self_.node_id, self_.id) };
         process.tag_send(self_.tag, true);
-        Protocol::from_process_with_tag(process, self_.tag)
+        self_.duplicate()
     }
 
     /// Perform an active choice, selecting protocol `Q`.
@@ -179,7 +300,7 @@ where
         // Temporarily cast to right process type.
         let process: Process<bool, S> = unsafe { Process::new(self_.node_id, self_.id) };
         process.tag_send(self_.tag, false);
-        Protocol::from_process_with_tag(process, self_.tag)
+        self_.duplicate()
     }
 }
 
@@ -189,18 +310,116 @@ where
 {
     /// Passive choice. This allows the other end of the session to select one
     /// of two options for continuing the protocol: either `P` or `Q`.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the deadline of the protocol expires.
     #[must_use]
+    #[track_caller]
     pub fn offer(self) -> Branch<Protocol<P, S, Z>, Protocol<Q, S, Z>> {
-        unsafe {
-            // Temporarily cast to right mailbox type.
-            let mailbox: Mailbox<bool, S> = Mailbox::new();
-            if mailbox.receive() {
-                Branch::Left(self.cast())
-            } else {
-                Branch::Right(self.cast())
+        let (self_, left) = self.receive_or_panic();
+        self_.branch(left)
+    }
+
+    /// Same as `offer`, but only waits for the duration of timeout for the
+    /// choice. If the timeout or the deadline of the protocol expires, the
+    /// protocol is consumed and an error returned.
+    #[allow(clippy::type_complexity)]
+    pub fn offer_timeout(
+        self,
+        timeout: Duration,
+    ) -> Result<Branch<Protocol<P, S, Z>, Protocol<Q, S, Z>>, ProtocolError> {
+        match self.receive_(Some(timeout)) {
+            Ok(left) => Ok(self.branch(left)),
+            Err(err) => {
+                self.cancel();
+                Err(err)
             }
         }
     }
+
+    fn branch(self, left: bool) -> Branch<Protocol<P, S, Z>, Protocol<Q, S, Z>> {
+        if left {
+            Branch::Left(self.cast())
+        } else {
+            Branch::Right(self.cast())
+        }
+    }
+}
+
+impl<B, S, Z> Protocol<Select<B>, S, Z>
+where
+    B: Branches,
+    S: CanSerialize<u32>,
+{
+    /// Perform an active choice, selecting the branch `label`.
+    ///
+    /// Labels are the variants of the enum generated by the
+    /// [`branches!`](crate::branches) macro (e.g. `Command::Add`). The
+    /// returned protocol continues with the protocol of the selected branch.
+    #[must_use]
+    pub fn select<P>(self, label: Label<B, P, S, Z>) -> Protocol<P, S, Z> {
+        // Don't drop the session yet.
+        let self_ = ManuallyDrop::new(self);
+        // Construct the branch only to find out which label was selected.
+        let branch = label(self_.duplicate());
+        let label = B::label(&branch);
+        // The branch holds a protocol that is not in the `End` state.
+        mem::forget(branch);
+        // Temporarily cast to right process type.
+        let process: Process<u32, S> = unsafe { Process::new(self_.node_id, self_.id) };
+        process.tag_send(self_.tag, label);
+        self_.duplicate()
+    }
+}
+
+impl<B, S, Z> Protocol<Case<B>, S, Z>
+where
+    B: Branches,
+    S: CanSerialize<u32>,
+{
+    /// Passive choice. This allows the other end of the session to select one
+    /// of the labelled branches of `B`.
+    ///
+    /// Returns the enum generated by the [`branches!`](crate::branches) macro,
+    /// holding the protocol of the selected branch.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the deadline of the protocol expires.
+    #[must_use]
+    #[track_caller]
+    pub fn offer(self) -> B::Offered<S, Z> {
+        let (self_, label) = self.receive_or_panic();
+        self_.branch(label)
+    }
+
+    /// Same as `offer`, but only waits for the duration of timeout for the
+    /// choice. If the timeout or the deadline of the protocol expires, the
+    /// protocol is consumed and an error returned.
+    pub fn offer_timeout(self, timeout: Duration) -> Result<B::Offered<S, Z>, ProtocolError> {
+        match self.receive_(Some(timeout)) {
+            Ok(label) => Ok(self.branch(label)),
+            Err(err) => {
+                self.cancel();
+                Err(err)
+            }
+        }
+    }
+
+    #[track_caller]
+    fn branch(self, label: u32) -> B::Offered<S, Z> {
+        let continuation = Continuation {
+            protocol: self.cast(),
+        };
+        match B::branch(label, continuation) {
+            Some(branch) => branch,
+            None => panic!(
+                "Protocol received unknown label {label} for branches `{}`",
+                any::type_name::<B>()
+            ),
+        }
+    }
 }
 
 impl<P, S, Z> Protocol<Rec<P>, S, Z> {
@@ -262,6 +481,12 @@ pub struct Choose<P, Q>(PhantomData<(P, Q)>);
 /// Passive choice (offer) between `P` and `Q`
 pub struct Offer<P, Q>(PhantomData<(P, Q)>);
 
+/// Active choice between the labelled branches of `B`
+pub struct Select<B>(PhantomData<B>);
+
+/// Passive choice (offer) between the labelled branches of `B`
+pub struct Case<B>(PhantomData<B>);
+
 /// Allows recursively calling a protocol
 pub struct Rec<P>(PhantomData<P>);
 
@@ -301,6 +526,14 @@ impl<P: HasDual, Q: HasDual> HasDual for Offer<P, Q> {
     type Dual = Choose<P::Dual, Q::Dual>;
 }
 
+impl<B: Branches> HasDual for Select<B> {
+    type Dual = Case<B::Dual>;
+}
+
+impl<B: Branches> HasDual for Case<B> {
+    type Dual = Select<B::Dual>;
+}
+
 impl<P: HasDual> HasDual for Rec<P> {
     type Dual = Rec<P::Dual>;
 }
@@ -314,6 +547,77 @@ pub enum Branch<L, R> {
     Right(R),
 }
 
+/// Error returned when a step of a [`Protocol`] fails.
+///
+/// The protocol is consumed by the failed step and can't be continued.
+#[derive(Error, Debug)]
+pub enum ProtocolError {
+    /// The timeout of the step expired.
+    #[error("timed out")]
+    TimedOut,
+    /// The deadline of the protocol expired.
+    #[error("deadline exceeded")]
+    DeadlineExceeded,
+    /// Message failed to be deserialized.
+    #[error("deserialization failed: {0}")]
+    DeserializationFailed(#[from] DecodeError),
+}
+
+impl ProtocolError {
+    /// Returns true if the step timed out or the deadline expired.
+    pub fn is_timed_out(&self) -> bool {
+        matches!(
+            self,
+            ProtocolError::TimedOut | ProtocolError::DeadlineExceeded
+        )
+    }
+}
+
+/// Labelled branches of an n-ary choice.
+///
+/// Used by the [`Select`] and [`Case`] protocols. This trait should not be
+/// implemented manually, but with the [`branches!`](crate::branches) macro.
+/// The macro generates an enum with one variant per branch and its dual,
+/// holding the continuation of the protocol for the other side.
+pub trait Branches: 'static {
+    /// The same branches, as seen from the other side of the protocol.
+    type Dual: Branches<Dual = Self>;
+
+    /// Enum returned by [`Protocol::offer`], with one variant per branch.
+    type Offered<S, Z: 'static>;
+
+    /// Returns the label of the branch.
+    #[doc(hidden)]
+    fn label<S, Z: 'static>(branch: &Self::Offered<S, Z>) -> u32;
+
+    /// Returns the branch for a label, continuing with the `continuation`.
+    #[doc(hidden)]
+    fn branch<S, Z: 'static>(
+        label: u32,
+        continuation: Continuation<S, Z>,
+    ) -> Option<Self::Offered<S, Z>>;
+}
+
+/// A label of the branches `B`, continuing with protocol `P`.
+///
+/// Labels are constructors of the enum variants generated by the
+/// [`branches!`](crate::branches) macro.
+pub type Label<B, P, S, Z> = fn(Protocol<P, S, Z>) -> <B as Branches>::Offered<S, Z>;
+
+/// The protocol after a labelled choice, before the type of the selected
+/// branch is known.
+#[doc(hidden)]
+pub struct Continuation<S, Z: 'static> {
+    protocol: Protocol<End, S, Z>,
+}
+
+impl<S, Z> Continuation<S, Z> {
+    /// Continues the protocol as `P`.
+    pub fn into_protocol<P>(self) -> Protocol<P, S, Z> {
+        self.protocol.cast()
+    }
+}
+
 mod private {
     use super::*;
     pub trait Sealed {}
@@ -325,10 +629,122 @@ mod private {
     impl<A, P> Sealed for Recv<A, P> {}
     impl<P, Q> Sealed for Choose<P, Q> {}
     impl<P, Q> Sealed for Offer<P, Q> {}
+    impl<B> Sealed for Select<B> {}
+    impl<B> Sealed for Case<B> {}
     impl<P> Sealed for Rec<P> {}
     impl Sealed for Pop {}
 }
 
+/// Defines the labelled branches of an n-ary choice.
+///
+/// The macro generates an enum with one variant per branch, holding the
+/// protocol that follows if the branch is selected. It also generates the dual
+/// enum, with a `Dual` suffix, that contains the protocols of the other side.
+/// Both enums implement [`Branches`], so that they can be used with the
+/// [`Select`] and [`Case`] protocols.
+///
+/// # Example
+///
+/// ```
+/// branches! {
+///     pub enum Command {
+///         Add(Recv<i32, Recv<i32, Send<i32, End>>>),
+///         Negate(Recv<i32, Send<i32, End>>),
+///         Quit(End),
+///     }
+/// }
+///
+/// let child = Process::spawn_link((), |_, protocol: Protocol<Case<Command>>| {
+///     match protocol.offer() {
+///         Command::Add(protocol) => {
+///             let (protocol, a) = protocol.receive();
+///             let (protocol, b) = protocol.receive();
+///             let _ = protocol.send(a + b);
+///         }
+///         Command::Negate(protocol) => {
+///             let (protocol, a) = protocol.receive();
+///             let _ = protocol.send(-a);
+///         }
+///         Command::Quit(_) => {}
+///     }
+/// });
+///
+/// let child = child.select(CommandDual::Negate);
+/// let (_, result) = child.send(1).receive();
+/// assert_eq!(result, -1);
+/// ```
+#[macro_export]
+macro_rules! branches {
+    (
+        $(#[$attr:meta])*
+        $vis:vis enum $name:ident {
+            $($(#[$variant_attr:meta])* $variant:ident($protocol:ty)),* $(,)?
+        }
+    ) => {
+        $crate::__paste! {
+            $(#[$attr])*
+            $vis enum $name<S = $crate::serializer::Bincode, Z: 'static = ()> {
+                $(
+                    $(#[$variant_attr])*
+                    $variant($crate::protocol::Protocol<$protocol, S, Z>),
+                )*
+            }
+
+            #[doc = concat!("The dual of [`", stringify!($name), "`].")]
+            $vis enum [<$name Dual>]<S = $crate::serializer::Bincode, Z: 'static = ()> {
+                $(
+                    $variant($crate::protocol::Protocol<
+                        <$protocol as $crate::protocol::HasDual>::Dual,
+                        S,
+                        Z,
+                    >),
+                )*
+            }
+
+            $crate::__branches_impl!($name, [<$name Dual>], $($variant),*);
+            $crate::__branches_impl!([<$name Dual>], $name, $($variant),*);
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __branches_impl {
+    ($name:ident, $dual:ident, $($variant:ident),*) => {
+        impl<S0: 'static, Z0: 'static> $crate::protocol::Branches for $name<S0, Z0> {
+            type Dual = $dual<S0, Z0>;
+            type Offered<S, Z: 'static> = $name<S, Z>;
+
+            fn label<S, Z: 'static>(branch: &Self::Offered<S, Z>) -> u32 {
+                // Labels are the positions of the variants.
+                enum Label {
+                    $($variant),*
+                }
+
+                match branch {
+                    $($name::$variant(_) => Label::$variant as u32,)*
+                }
+            }
+
+            fn branch<S, Z: 'static>(
+                label: u32,
+                continuation: $crate::protocol::Continuation<S, Z>,
+            ) -> Option<Self::Offered<S, Z>> {
+                enum Label {
+                    $($variant),*
+                }
+
+                $(
+                    if label == Label::$variant as u32 {
+                        return Some($name::$variant(continuation.into_protocol()));
+                    }
+                )*
+                None
+            }
+        }
+    };
+}
+
 impl<P, S, Z> IntoProcess<P, S> for Protocol<P, S, Z>
 where
     P: HasDual,
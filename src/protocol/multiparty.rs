@@ -0,0 +1,553 @@
+//! Multiparty session types for protocols between more than two processes.
+//!
+//! A multiparty protocol is described once, from a global point of view, as a
+//! sequence of interactions between roles. The global type is then projected
+//! onto each role, resulting in the local protocol that a single
+//! [`Participant`] follows. Because all local protocols are derived from the
+//! same global type, they are guaranteed to fit together.
+//!
+//! Roles are declared with the [`roles!`](crate::roles) macro. Global types
+//! are built from [`Message`], [`Choice`] and [`End`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use lunatic::protocol::multiparty::{Invitation, Message, Session};
+//! use lunatic::protocol::End;
+//! use lunatic::{Mailbox, Process};
+//!
+//! lunatic::roles! { Coordinator, Worker, Auditor }
+//!
+//! // The coordinator sends a job to the worker, the worker reports the
+//! // result to the auditor and the auditor approves it to the coordinator.
+//! type Job = Message<
+//!     Coordinator,
+//!     Worker,
+//!     u64,
+//!     Message<Worker, Auditor, u64, Message<Auditor, Coordinator, bool, End>>,
+//! >;
+//!
+//! let worker = Process::spawn_link((), |_, mailbox: Mailbox<Invitation<Job>>| {
+//!     let worker = mailbox.receive().join::<Worker>();
+//!     let (worker, job) = worker.receive();
+//!     let _ = worker.send(job * 2);
+//! });
+//! let auditor = Process::spawn_link((), |_, mailbox: Mailbox<Invitation<Job>>| {
+//!     let auditor = mailbox.receive().join::<Auditor>();
+//!     let (auditor, result) = auditor.receive();
+//!     let _ = auditor.send(result % 2 == 0);
+//! });
+//!
+//! let coordinator = Session::<Job>::new()
+//!     .with_role::<Worker>(worker)
+//!     .with_role::<Auditor>(auditor)
+//!     .start::<Coordinator>();
+//! let coordinator = coordinator.send(21);
+//! let (_, approved) = coordinator.receive();
+//! assert!(approved);
+//! ```
+
+use std::any::TypeId;
+use std::marker::PhantomData;
+use std::{any, fmt};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Branch, End};
+use crate::serializer::{Bincode, CanSerialize};
+use crate::{host, Mailbox, Process, Tag};
+
+/// A role in a multiparty protocol.
+///
+/// Roles are declared with the [`roles!`](crate::roles) macro, that assigns
+/// each of them a unique index.
+pub trait Role: 'static {
+    /// Position of the role in the [`roles!`](crate::roles) declaration.
+    const INDEX: usize;
+    /// Number of roles in the [`roles!`](crate::roles) declaration.
+    const COUNT: usize;
+}
+
+/// Projection of a global type onto the role `R`.
+///
+/// The projection is only defined for well formed global types. Global types
+/// where a role sends a message to itself, or where a role that is not part of
+/// a [`Choice`] would need to behave differently depending on the chosen
+/// branch, fail to compile.
+pub trait Project<R: Role> {
+    /// The local protocol of role `R`.
+    type Local;
+}
+
+/// The local protocol of role `R` in the global type `G`.
+pub type Local<G, R> = <G as Project<R>>::Local;
+
+// Global types.
+
+/// Global type: role `F` sends a value of type `A` to role `T`, then the
+/// protocol continues as `G`.
+pub struct Message<F, T, A, G>(PhantomData<(F, T, A, G)>);
+
+/// Global type: role `F` chooses between the protocols `L` and `R` and
+/// notifies role `T` about it.
+///
+/// All other roles must behave the same in both branches.
+pub struct Choice<F, T, L, R>(PhantomData<(F, T, L, R)>);
+
+// Local types.
+
+/// Local type: send a value of type `A` to role `R`, then continue as `P`.
+pub struct SendTo<R, A, P>(PhantomData<(R, A, P)>);
+
+/// Local type: receive a value of type `A` from role `R`, then continue as
+/// `P`.
+pub struct RecvFrom<R, A, P>(PhantomData<(R, A, P)>);
+
+/// Local type: choose between `P` and `Q` and notify role `R` about it.
+pub struct ChooseTo<R, P, Q>(PhantomData<(R, P, Q)>);
+
+/// Local type: continue as `P` or `Q`, depending on the choice of role `R`.
+pub struct OfferFrom<R, P, Q>(PhantomData<(R, P, Q)>);
+
+#[doc(hidden)]
+pub struct __True;
+
+#[doc(hidden)]
+pub struct __False;
+
+/// Type level comparison of roles, implemented by the
+/// [`roles!`](crate::roles) macro.
+#[doc(hidden)]
+pub trait __RoleEq<R> {
+    type Output;
+}
+
+/// Projects a [`Message`] depending on the position of the role in it.
+#[doc(hidden)]
+pub trait __ProjectMessage<F, T, A, L> {
+    type Local;
+}
+
+impl<F, T, A, L> __ProjectMessage<F, T, A, L> for (__True, __False) {
+    type Local = SendTo<T, A, L>;
+}
+
+impl<F, T, A, L> __ProjectMessage<F, T, A, L> for (__False, __True) {
+    type Local = RecvFrom<F, A, L>;
+}
+
+impl<F, T, A, L> __ProjectMessage<F, T, A, L> for (__False, __False) {
+    type Local = L;
+}
+
+/// Projects a [`Choice`] depending on the position of the role in it.
+#[doc(hidden)]
+pub trait __ProjectChoice<F, T, L, R> {
+    type Local;
+}
+
+impl<F, T, L, R> __ProjectChoice<F, T, L, R> for (__True, __False) {
+    type Local = ChooseTo<T, L, R>;
+}
+
+impl<F, T, L, R> __ProjectChoice<F, T, L, R> for (__False, __True) {
+    type Local = OfferFrom<F, L, R>;
+}
+
+// Roles not involved in the choice must have the same protocol in both branches.
+impl<F, T, L> __ProjectChoice<F, T, L, L> for (__False, __False) {
+    type Local = L;
+}
+
+impl<R: Role> Project<R> for End {
+    type Local = End;
+}
+
+impl<R, F, T, A, G> Project<R> for Message<F, T, A, G>
+where
+    R: Role + __RoleEq<F> + __RoleEq<T>,
+    G: Project<R>,
+    (<R as __RoleEq<F>>::Output, <R as __RoleEq<T>>::Output):
+        __ProjectMessage<F, T, A, Local<G, R>>,
+{
+    type Local = <(<R as __RoleEq<F>>::Output, <R as __RoleEq<T>>::Output) as __ProjectMessage<
+        F,
+        T,
+        A,
+        Local<G, R>,
+    >>::Local;
+}
+
+impl<R, F, T, GL, GR> Project<R> for Choice<F, T, GL, GR>
+where
+    R: Role + __RoleEq<F> + __RoleEq<T>,
+    GL: Project<R>,
+    GR: Project<R>,
+    (<R as __RoleEq<F>>::Output, <R as __RoleEq<T>>::Output):
+        __ProjectChoice<F, T, Local<GL, R>, Local<GR, R>>,
+{
+    type Local = <(<R as __RoleEq<F>>::Output, <R as __RoleEq<T>>::Output) as __ProjectChoice<
+        F,
+        T,
+        Local<GL, R>,
+        Local<GR, R>,
+    >>::Local;
+}
+
+/// Addresses of all participants and the tags they use to send messages.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Peers {
+    /// Node and process ID of each role.
+    processes: Vec<(u64, u64)>,
+    /// Tag used by each role for the messages it sends.
+    tags: Vec<Tag>,
+}
+
+/// Sets up a multiparty session with the global type `G`.
+///
+/// The process creating the session assigns a process to every other role and
+/// then [`start`](Session::start)s the session, taking the remaining role
+/// itself. All other processes receive an [`Invitation`] that they need to
+/// [`join`](Invitation::join).
+pub struct Session<G, S = Bincode> {
+    processes: Vec<Option<(u64, u64)>>,
+    phantom: PhantomData<(G, S)>,
+}
+
+impl<G, S> Session<G, S> {
+    /// Creates a new session without participants.
+    pub fn new() -> Self {
+        Self {
+            processes: Vec::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Assigns the role `R` to `process`.
+    #[must_use]
+    pub fn with_role<R: Role>(mut self, process: Process<Invitation<G, S>>) -> Self {
+        self.insert(R::INDEX, (process.node_id(), process.id()));
+        self
+    }
+
+    /// Starts the session, taking the role `R` in it.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if any role, other than `R`, was not assigned
+    /// to a process.
+    #[track_caller]
+    pub fn start<R: Role>(mut self) -> Participant<Local<G, R>, S>
+    where
+        G: Project<R>,
+    {
+        self.insert(R::INDEX, (host::node_id(), host::process_id()));
+        // Roles declared after the last assigned one are missing too.
+        if self.processes.len() < R::COUNT {
+            self.processes.resize(R::COUNT, None);
+        }
+        let processes: Vec<(u64, u64)> = self
+            .processes
+            .iter()
+            .enumerate()
+            .map(|(index, process)| {
+                process.unwrap_or_else(|| panic!("role {index} has no participant"))
+            })
+            .collect();
+        let tags = processes.iter().map(|_| Tag::new()).collect();
+        let peers = Peers { processes, tags };
+        for (role, &(node_id, id)) in peers.processes.iter().enumerate() {
+            if role != R::INDEX {
+                // Temporarily cast to right process type.
+                let process: Process<Invitation<G, S>> = unsafe { Process::new(node_id, id) };
+                process.send(Invitation {
+                    role,
+                    peers: peers.clone(),
+                    phantom: PhantomData,
+                });
+            }
+        }
+        Participant::new(R::INDEX, peers)
+    }
+
+    fn insert(&mut self, index: usize, process: (u64, u64)) {
+        if self.processes.len() <= index {
+            self.processes.resize(index + 1, None);
+        }
+        self.processes[index] = Some(process);
+    }
+}
+
+impl<G, S> Default for Session<G, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<G, S> fmt::Debug for Session<G, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Session")
+            .field("type", &any::type_name::<G>())
+            .field("processes", &self.processes)
+            .finish()
+    }
+}
+
+/// An invitation to take part in a multiparty session with the global type
+/// `G`, sent by [`Session::start`].
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Invitation<G, S = Bincode> {
+    role: usize,
+    peers: Peers,
+    #[serde(skip)]
+    phantom: PhantomData<(G, S)>,
+}
+
+impl<G, S> Invitation<G, S> {
+    /// Joins the session as role `R`.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the invitation was sent for another role.
+    #[track_caller]
+    pub fn join<R: Role>(self) -> Participant<Local<G, R>, S>
+    where
+        G: Project<R>,
+    {
+        assert_eq!(
+            self.role,
+            R::INDEX,
+            "Invitation for role {} joined as {}",
+            self.role,
+            any::type_name::<R>()
+        );
+        Participant::new(self.role, self.peers)
+    }
+}
+
+impl<G, S> fmt::Debug for Invitation<G, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Invitation")
+            .field("type", &any::type_name::<G>())
+            .field("role", &self.role)
+            .finish()
+    }
+}
+
+/// A process taking part in a multiparty session, following the local
+/// protocol `L`.
+///
+/// Only `Participant<End>` can be dropped. All other participants will panic
+/// if dropped without reaching `Participant<End>`, unless
+/// [`cancel`](Participant::cancel)ed.
+pub struct Participant<L: 'static, S = Bincode> {
+    role: usize,
+    // Is `None` after the peers were moved to the next protocol state.
+    peers: Option<Peers>,
+    phantom: PhantomData<(L, S)>,
+}
+
+impl<L: 'static, S> Drop for Participant<L, S> {
+    fn drop(&mut self) {
+        if self.peers.is_some() && TypeId::of::<L>() != TypeId::of::<End>() {
+            panic!(
+                "Participant prematurely dropped, before reaching the `End` state (currently: {}).",
+                any::type_name::<L>()
+            );
+        }
+    }
+}
+
+impl<L, S> Participant<L, S> {
+    fn new(role: usize, peers: Peers) -> Self {
+        Self {
+            role,
+            peers: Some(peers),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the index of the role this participant has in the session.
+    pub fn role(&self) -> usize {
+        self.role
+    }
+
+    /// Abandons the session before reaching the `End` state, without
+    /// panicking.
+    ///
+    /// The other participants are not notified.
+    pub fn cancel(mut self) {
+        self.peers = None;
+    }
+
+    /// Cast the participant to another local type.
+    fn cast<L2>(mut self) -> Participant<L2, S> {
+        Participant {
+            role: self.role,
+            peers: self.peers.take(),
+            phantom: PhantomData,
+        }
+    }
+
+    fn peers(&self) -> &Peers {
+        self.peers.as_ref().expect("peers are present")
+    }
+
+    /// Sends `message` to the participant with the role `index`.
+    fn send_to<A>(&self, index: usize, message: A)
+    where
+        S: CanSerialize<A>,
+    {
+        let peers = self.peers();
+        let (node_id, id) = peers.processes[index];
+        // Temporarily cast to right process type.
+        let process: Process<A, S> = unsafe { Process::new(node_id, id) };
+        process.tag_send(peers.tags[self.role], message);
+    }
+
+    /// Receives the next message from the participant with the role `index`.
+    fn receive_from<A>(&self, index: usize) -> A
+    where
+        S: CanSerialize<A>,
+    {
+        // Temporarily cast to right mailbox type.
+        let mailbox: Mailbox<A, S> = unsafe { Mailbox::new() };
+        mailbox.tag_receive(&[self.peers().tags[index]])
+    }
+}
+
+impl<R, A, P, S> Participant<SendTo<R, A, P>, S>
+where
+    R: Role,
+    S: CanSerialize<A>,
+{
+    /// Send a value of type `A` to role `R`. Returns the resulting session.
+    #[must_use]
+    pub fn send(self, message: A) -> Participant<P, S> {
+        self.send_to(R::INDEX, message);
+        self.cast()
+    }
+}
+
+impl<R, A, P, S> Participant<RecvFrom<R, A, P>, S>
+where
+    R: Role,
+    S: CanSerialize<A>,
+{
+    /// Receives a value of type `A` from role `R`. Returns a tuple containing
+    /// the resulting session and the received value.
+    #[must_use]
+    pub fn receive(self) -> (Participant<P, S>, A) {
+        let received = self.receive_from(R::INDEX);
+        (self.cast(), received)
+    }
+}
+
+impl<R, P, Q, S> Participant<ChooseTo<R, P, Q>, S>
+where
+    R: Role,
+    S: CanSerialize<bool>,
+{
+    /// Perform an active choice, selecting protocol `P` and notifying role
+    /// `R`.
+    #[must_use]
+    pub fn select_left(self) -> Participant<P, S> {
+        self.send_to(R::INDEX, true);
+        self.cast()
+    }
+
+    /// Perform an active choice, selecting protocol `Q` and notifying role
+    /// `R`.
+    #[must_use]
+    pub fn select_right(self) -> Participant<Q, S> {
+        self.send_to(R::INDEX, false);
+        self.cast()
+    }
+}
+
+impl<R, P, Q, S> Participant<OfferFrom<R, P, Q>, S>
+where
+    R: Role,
+    S: CanSerialize<bool>,
+{
+    /// Passive choice. This allows role `R` to select one of two options for
+    /// continuing the protocol: either `P` or `Q`.
+    #[must_use]
+    pub fn offer(self) -> Branch<Participant<P, S>, Participant<Q, S>> {
+        if self.receive_from(R::INDEX) {
+            Branch::Left(self.cast())
+        } else {
+            Branch::Right(self.cast())
+        }
+    }
+}
+
+impl<L, S> fmt::Debug for Participant<L, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Participant")
+            .field("type", &any::type_name::<L>())
+            .field("role", &self.role)
+            .finish()
+    }
+}
+
+/// Declares the roles of a multiparty protocol.
+///
+/// Each role becomes a unit struct implementing [`Role`]. The roles of a
+/// protocol need to be declared together, so that they can be told apart
+/// during the projection of global types.
+///
+/// # Example
+///
+/// ```
+/// lunatic::roles! { pub Coordinator, pub Worker, pub Auditor }
+/// ```
+///
+/// [`Role`]: crate::protocol::multiparty::Role
+#[macro_export]
+macro_rules! roles {
+    ($($(#[$attr:meta])* $vis:vis $role:ident),+ $(,)?) => {
+        $(
+            $(#[$attr])*
+            #[derive(Debug, Clone, Copy)]
+            $vis struct $role;
+        )+
+
+        $crate::__roles!(@index 0usize, 0usize $(+ $crate::__roles!(@one $role))+; $($role)+);
+        $crate::__roles!(@eq $($role)+);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __roles {
+    (@one $role:ident) => { 1usize };
+    (@index $index:expr, $count:expr;) => {};
+    (@index $index:expr, $count:expr; $head:ident $($tail:ident)*) => {
+        impl $crate::protocol::multiparty::Role for $head {
+            const INDEX: usize = $index;
+            const COUNT: usize = $count;
+        }
+
+        $crate::__roles!(@index $index + 1, $count; $($tail)*);
+    };
+    (@eq) => {};
+    (@eq $head:ident $($tail:ident)*) => {
+        impl $crate::protocol::multiparty::__RoleEq<$head> for $head {
+            type Output = $crate::protocol::multiparty::__True;
+        }
+
+        $(
+            impl $crate::protocol::multiparty::__RoleEq<$tail> for $head {
+                type Output = $crate::protocol::multiparty::__False;
+            }
+
+            impl $crate::protocol::multiparty::__RoleEq<$head> for $tail {
+                type Output = $crate::protocol::multiparty::__False;
+            }
+        )*
+
+        $crate::__roles!(@eq $($tail)*);
+    };
+}
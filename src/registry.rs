@@ -0,0 +1,566 @@
+//! Process registry with metadata and automatic cleanup.
+//!
+//! Processes registered with [`Process::register_with_metadata`] or
+//! [`ProcessRef::register_with_metadata`] are tracked by a registry server
+//! that is started on demand on each node. Together with the name, each entry
+//! holds [`Metadata`].
+//!
+//! The registry server monitors registered local processes and removes their
+//! entries once they die, so that `lookup` never returns references to dead
+//! processes.
+//!
+//! Plain [`Process::register`], [`ProcessRef::register`] and `start_as` write
+//! to the host registry directly and only notify the server, without waiting
+//! for it, so that it can remove the name once the process dies. The host
+//! registry stays the source of truth for all lookups. The server only adds
+//! the metadata, and its entries are ignored once the name is taken over by
+//! another process.
+//!
+//! Entries with metadata can be listed with [`list`]. Processes that depend on
+//! others can wait for them to be registered with
+//! [`ProcessRef::await_registered`].
+//!
+//! # Custom registries
+//!
+//! Besides the built-in registry, processes can be registered in and looked up
+//! from any type implementing the [`Registry`] trait, like a process-local map
+//! or a registry sharded across nodes. The registry is selected per call with
+//! [`AbstractProcessBuilder::via`](crate::ap::AbstractProcessBuilder::via),
+//! [`ProcessRef::lookup_via`] or [`Process::lookup_via`], similar to Elixir's
+//! `{:via, module, name}` tuples.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use lunatic::registry::{self, Metadata};
+//! use lunatic::{Mailbox, Process};
+//!
+//! let process = Process::spawn((), |_, mailbox: Mailbox<String>| loop {
+//!     println!("{}", mailbox.receive());
+//! });
+//! let mut metadata = Metadata::new();
+//! metadata.insert("region".to_owned(), "eu".to_owned());
+//! process.register_with_metadata(&"logger/eu", metadata);
+//!
+//! for entry in registry::list("logger/") {
+//!     println!("{}: {:?}", entry.name(), entry.metadata());
+//! }
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ap::handlers::{DeferredRequest, Message, Request};
+use crate::ap::{
+    AbstractProcess, Config, DeferredRequestHandler, DeferredResponse, MessageHandler, ProcessRef,
+    RequestHandler, StartupError, State,
+};
+use crate::function::process::{process_name, ProcessType};
+use crate::serializer::Bincode;
+use crate::time::Timeout;
+use crate::{host, Process};
+
+/// Name under which the registry server of each node is registered.
+const SERVER_NAME: &str = "lunatic::registry";
+
+/// Metadata stored together with a registry entry.
+pub type Metadata = BTreeMap<String, String>;
+
+/// An entry in the registry.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    name: String,
+    // Name used in the host registry, containing type information.
+    key: String,
+    node_id: u64,
+    process_id: u64,
+    metadata: Metadata,
+}
+
+impl Entry {
+    /// Returns the name the process is registered under.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the node ID of the registered process.
+    pub fn node_id(&self) -> u64 {
+        self.node_id
+    }
+
+    /// Returns the process ID of the registered process.
+    pub fn process_id(&self) -> u64 {
+        self.process_id
+    }
+
+    /// Returns the metadata of the entry.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Returns the registered process if it's of type `Process<M, S>`.
+    pub fn process<M, S>(&self) -> Option<Process<M, S>> {
+        if self.key == process_name::<M, S>(ProcessType::Process, &self.name) {
+            Some(unsafe { Process::new(self.node_id, self.process_id) })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the registered process if it's of type `ProcessRef<T>`.
+    pub fn process_ref<T: AbstractProcess>(&self) -> Option<ProcessRef<T>> {
+        if self.key == process_name::<T, T::Serializer>(ProcessType::ProcessRef, &self.name) {
+            Some(unsafe { ProcessRef::new(self.node_id, self.process_id) })
+        } else {
+            None
+        }
+    }
+}
+
+/// A name registry that processes can be registered in.
+///
+/// Names passed to the registry already contain the type information of the
+/// process, so that processes of different types registered under the same
+/// name don't collide.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::cell::RefCell;
+/// use std::collections::HashMap;
+///
+/// use lunatic::registry::Registry;
+///
+/// /// A registry only visible inside of the current process.
+/// #[derive(Default)]
+/// struct LocalRegistry(RefCell<HashMap<String, (u64, u64)>>);
+///
+/// impl Registry for LocalRegistry {
+///     fn register(&self, name: &str, node_id: u64, process_id: u64) -> Result<(), (u64, u64)> {
+///         let mut names = self.0.borrow_mut();
+///         if let Some(existing) = names.get(name) {
+///             return Err(*existing);
+///         }
+///         names.insert(name.to_owned(), (node_id, process_id));
+///         Ok(())
+///     }
+///
+///     fn lookup(&self, name: &str) -> Option<(u64, u64)> {
+///         self.0.borrow().get(name).copied()
+///     }
+///
+///     fn unregister(&self, name: &str) {
+///         self.0.borrow_mut().remove(name);
+///     }
+/// }
+/// ```
+pub trait Registry {
+    /// Registers the process under `name`.
+    ///
+    /// If another process is already registered under `name`, it returns
+    /// `Err((node_id, process_id))` of the existing process.
+    fn register(&self, name: &str, node_id: u64, process_id: u64) -> Result<(), (u64, u64)>;
+
+    /// Returns the node ID and process ID of the process registered under
+    /// `name`.
+    fn lookup(&self, name: &str) -> Option<(u64, u64)>;
+
+    /// Removes `name` from the registry.
+    fn unregister(&self, name: &str);
+}
+
+/// The built-in registry of the node.
+///
+/// Registering a process with `HostRegistry` is the same as using
+/// [`Process::register_with_metadata`] without metadata, including the
+/// cleanup of entries once the process dies. Unlike `register`, it fails if
+/// the name is already taken.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HostRegistry;
+
+impl Registry for HostRegistry {
+    fn register(&self, name: &str, node_id: u64, process_id: u64) -> Result<(), (u64, u64)> {
+        match server().request(RegisterNew(Entry {
+            name: plain_name(name).to_owned(),
+            key: name.to_owned(),
+            node_id,
+            process_id,
+            metadata: Metadata::new(),
+        })) {
+            Some(existing) => Err((existing.node_id, existing.process_id)),
+            None => Ok(()),
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<(u64, u64)> {
+        lookup_in_host(name)
+    }
+
+    fn unregister(&self, name: &str) {
+        unregister(name);
+    }
+}
+
+/// Strips the type information added by `process_name` from `key`.
+fn plain_name(key: &str) -> &str {
+    key.splitn(4, '/').nth(3).unwrap_or(key)
+}
+
+/// Returns all entries on this node that were registered with metadata and
+/// have a name starting with `prefix`, ordered by name.
+///
+/// Processes registered with plain `register` or `start_as` are not listed.
+pub fn list(prefix: &str) -> Vec<Entry> {
+    server().request(List(prefix.to_owned()))
+}
+
+/// Registers the process under `key` in the host registry and adds the entry
+/// to the registry server.
+pub(crate) fn register(name: &str, key: String, node_id: u64, process_id: u64, metadata: Metadata) {
+    server().request(Register(Entry {
+        name: name.to_owned(),
+        key,
+        node_id,
+        process_id,
+        metadata,
+    }));
+}
+
+/// Registers the process under `key` in the host registry and lets the
+/// registry server remove it once the process dies.
+pub(crate) fn register_plain(key: &str, node_id: u64, process_id: u64) {
+    unsafe { host::api::registry::put(key.as_ptr(), key.len(), node_id, process_id) };
+    watch(key, node_id, process_id);
+}
+
+/// Lets the registry server remove `key` from the host registry once the
+/// process dies.
+pub(crate) fn watch(key: &str, node_id: u64, process_id: u64) {
+    server().send(Watch {
+        key: key.to_owned(),
+        node_id,
+        process_id,
+    });
+}
+
+/// Returns the process registered under `key` in the host registry, together
+/// with its metadata.
+///
+/// The metadata is empty if the process wasn't registered with metadata.
+pub(crate) fn lookup_with_metadata(key: String) -> Option<((u64, u64), Metadata)> {
+    let process = lookup_in_host(&key)?;
+    let metadata = running_server()
+        .and_then(|server| server.request(Lookup(key)))
+        // The name could have been taken over with a plain `register`.
+        .filter(|entry| (entry.node_id, entry.process_id) == process)
+        .map(|entry| entry.metadata)
+        .unwrap_or_default();
+    Some((process, metadata))
+}
+
+/// Removes the process registered under `key` from the host registry and the
+/// registry server.
+pub(crate) fn unregister(key: &str) -> Option<(u64, u64)> {
+    let process = lookup_in_host(key)?;
+    unsafe { host::api::registry::remove(key.as_ptr(), key.len()) };
+    if let Some(server) = running_server() {
+        server.request(Unregister(key.to_owned()));
+    }
+    Some(process)
+}
+
+/// Waits until a process is registered under `key` in the host registry.
+///
+/// The registry server answers as soon as the name is registered through this
+/// module.
+pub(crate) fn await_registered(key: &str, timeout: Duration) -> Result<(u64, u64), Timeout> {
+    if let Some(process) = lookup_in_host(key) {
+        return Ok(process);
+    }
+    server()
+        .with_timeout(timeout)
+        .deferred_request(AwaitRegistered {
+            key: key.to_owned(),
+            timeout,
+        })
+}
+
+/// Returns the registry server of this node, if it was started.
+fn running_server() -> Option<ProcessRef<ProcessRegistry>> {
+    ProcessRef::lookup(SERVER_NAME)
+}
+
+/// Returns the registry server of this node, starting it if necessary.
+fn server() -> ProcessRef<ProcessRegistry> {
+    if let Some(server) = running_server() {
+        return server;
+    }
+    match ProcessRegistry::start_as(&SERVER_NAME, ()) {
+        // Another process could have started the server in the meantime.
+        Ok(server) | Err(StartupError::NameAlreadyRegistered(server)) => server,
+        Err(err) => panic!("failed to start the registry server: {err:?}"),
+    }
+}
+
+/// The registry server.
+struct ProcessRegistry;
+
+struct RegistryState {
+    /// All entries with metadata of the node by their key.
+    entries: HashMap<String, Entry>,
+    /// Local processes registered without metadata by their key.
+    plain: HashMap<String, (u64, u64)>,
+    /// Processes waiting for a key to be registered, with the time they stop
+    /// waiting.
+    waiters: HashMap<String, Vec<Waiter>>,
+}
+
+type Waiter = (Instant, DeferredResponse<(u64, u64), ProcessRegistry>);
+
+impl AbstractProcess for ProcessRegistry {
+    type State = RegistryState;
+    type Serializer = Bincode;
+    type Arg = ();
+    type Handlers = (
+        Request<Register>,
+        Request<RegisterNew>,
+        Message<Watch>,
+        DeferredRequest<AwaitRegistered>,
+        Request<Lookup>,
+        Request<Unregister>,
+        Request<List>,
+    );
+    type StartupError = ();
+
+    fn init(_: Config<Self>, _: ()) -> Result<Self::State, ()> {
+        Ok(RegistryState {
+            entries: HashMap::new(),
+            plain: HashMap::new(),
+            waiters: HashMap::new(),
+        })
+    }
+
+    fn handle_process_death(mut state: State<Self>, process_id: u64) {
+        let process = (host::node_id(), process_id);
+        state.entries.retain(|key, entry| {
+            let dead = (entry.node_id, entry.process_id) == process;
+            if dead {
+                remove_from_host(key, process);
+            }
+            !dead
+        });
+        state.plain.retain(|key, registered| {
+            let dead = *registered == process;
+            if dead {
+                remove_from_host(key, process);
+            }
+            !dead
+        });
+    }
+}
+
+fn monitored(state: &RegistryState, node_id: u64, process_id: u64) -> bool {
+    state
+        .entries
+        .values()
+        .any(|entry| entry.node_id == node_id && entry.process_id == process_id)
+        || state
+            .plain
+            .values()
+            .any(|&process| process == (node_id, process_id))
+}
+
+fn lookup_in_host(key: &str) -> Option<(u64, u64)> {
+    let mut node_id = 0;
+    let mut process_id = 0;
+    let result =
+        unsafe { host::api::registry::get(key.as_ptr(), key.len(), &mut node_id, &mut process_id) };
+    if result == 0 {
+        Some((node_id, process_id))
+    } else {
+        None
+    }
+}
+
+/// Removes `key` from the host registry, if it wasn't taken over by another
+/// process in the meantime.
+fn remove_from_host(key: &str, process: (u64, u64)) {
+    if lookup_in_host(key) == Some(process) {
+        unsafe { host::api::registry::remove(key.as_ptr(), key.len()) };
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Register(Entry);
+
+impl RequestHandler<Register> for ProcessRegistry {
+    type Response = ();
+
+    fn handle(mut state: State<Self>, Register(entry): Register) {
+        insert(&mut state, entry);
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RegisterNew(Entry);
+
+impl RequestHandler<RegisterNew> for ProcessRegistry {
+    type Response = Option<Entry>;
+
+    fn handle(mut state: State<Self>, RegisterNew(entry): RegisterNew) -> Option<Entry> {
+        // The host registry also holds names registered without metadata.
+        if let Some((node_id, process_id)) = lookup_in_host(&entry.key) {
+            return Some(Entry {
+                node_id,
+                process_id,
+                metadata: Metadata::new(),
+                ..entry
+            });
+        }
+        insert(&mut state, entry);
+        None
+    }
+}
+
+/// Adds the entry to the host registry and the registry server.
+fn insert(state: &mut State<ProcessRegistry>, entry: Entry) {
+    let key = &entry.key;
+    unsafe { host::api::registry::put(key.as_ptr(), key.len(), entry.node_id, entry.process_id) };
+    start_monitoring(state, (entry.node_id, entry.process_id));
+    notify_waiters(state, key, (entry.node_id, entry.process_id));
+    let replaced_plain = state.plain.remove(key);
+    let replaced = state.entries.insert(entry.key.clone(), entry);
+    if let Some(replaced) = replaced {
+        stop_monitoring_if_unused(state, (replaced.node_id, replaced.process_id));
+    }
+    if let Some(replaced) = replaced_plain {
+        stop_monitoring_if_unused(state, replaced);
+    }
+}
+
+fn start_monitoring(state: &State<ProcessRegistry>, (node_id, process_id): (u64, u64)) {
+    // Only local processes can be monitored.
+    if node_id == host::node_id() && !monitored(state, node_id, process_id) {
+        state.monitor(unsafe { Process::<()>::new(node_id, process_id) });
+    }
+}
+
+fn stop_monitoring_if_unused(state: &State<ProcessRegistry>, (node_id, process_id): (u64, u64)) {
+    if node_id == host::node_id() && !monitored(state, node_id, process_id) {
+        state.stop_monitoring(unsafe { Process::<()>::new(node_id, process_id) });
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Watch {
+    key: String,
+    node_id: u64,
+    process_id: u64,
+}
+
+impl MessageHandler<Watch> for ProcessRegistry {
+    fn handle(mut state: State<Self>, watch: Watch) {
+        let process = (watch.node_id, watch.process_id);
+        // The server itself is registered with `start_as` too.
+        if process == (host::node_id(), state.self_ref().id()) {
+            return;
+        }
+        start_monitoring(&state, process);
+        notify_waiters(&mut state, &watch.key, process);
+        let replaced = state.plain.insert(watch.key, process);
+        if let Some(replaced) = replaced {
+            stop_monitoring_if_unused(&state, replaced);
+        }
+    }
+}
+
+/// Answers all processes waiting for `key` to be registered.
+fn notify_waiters(state: &mut RegistryState, key: &str, process: (u64, u64)) {
+    for (_, waiter) in state.waiters.remove(key).unwrap_or_default() {
+        waiter.send_response(process);
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct AwaitRegistered {
+    key: String,
+    timeout: Duration,
+}
+
+impl DeferredRequestHandler<AwaitRegistered> for ProcessRegistry {
+    type Response = (u64, u64);
+
+    fn handle(
+        mut state: State<Self>,
+        AwaitRegistered { key, timeout }: AwaitRegistered,
+        waiter: DeferredResponse<(u64, u64), Self>,
+    ) {
+        // The name could have been registered after the caller checked.
+        if let Some(process) = lookup_in_host(&key) {
+            waiter.send_response(process);
+            return;
+        }
+        // Drop waiters that already gave up.
+        let now = Instant::now();
+        state.waiters.retain(|_, waiters| {
+            waiters.retain(|(deadline, _)| *deadline > now);
+            !waiters.is_empty()
+        });
+        state
+            .waiters
+            .entry(key)
+            .or_default()
+            .push((now + timeout, waiter));
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Lookup(String);
+
+impl RequestHandler<Lookup> for ProcessRegistry {
+    type Response = Option<Entry>;
+
+    fn handle(state: State<Self>, Lookup(key): Lookup) -> Option<Entry> {
+        state.entries.get(&key).cloned()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Unregister(String);
+
+impl RequestHandler<Unregister> for ProcessRegistry {
+    type Response = Option<Entry>;
+
+    fn handle(mut state: State<Self>, Unregister(key): Unregister) -> Option<Entry> {
+        if let Some(process) = state.plain.remove(&key) {
+            stop_monitoring_if_unused(&state, process);
+        }
+        let entry = state.entries.remove(&key)?;
+        let process = (entry.node_id, entry.process_id);
+        remove_from_host(&key, process);
+        stop_monitoring_if_unused(&state, process);
+        Some(entry)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct List(String);
+
+impl RequestHandler<List> for ProcessRegistry {
+    type Response = Vec<Entry>;
+
+    fn handle(state: State<Self>, List(prefix): List) -> Vec<Entry> {
+        let mut entries: Vec<Entry> = state
+            .entries
+            .values()
+            .filter(|entry| entry.name.starts_with(&prefix))
+            // Skip names taken over with a plain `register`.
+            .filter(|entry| lookup_in_host(&entry.key) == Some((entry.node_id, entry.process_id)))
+            .cloned()
+            .collect();
+        entries.sort_by(|a, b| (&a.name, &a.key).cmp(&(&b.name, &b.key)));
+        entries
+    }
+}
@@ -83,6 +83,17 @@ pub trait CanSerialize<M> {
     fn decode() -> Result<M, DecodeError>;
 }
 
+/// The `CanSerializeBytes` trait is implemented for serializers that can
+/// encode and decode the type `M` into a standalone buffer.
+///
+/// Contrary to [`CanSerialize`], it doesn't use the message scratch buffer of
+/// the host and can be used to serialize data for other transports, like
+/// [`StreamProtocol`](crate::net::StreamProtocol).
+pub trait CanSerializeBytes<M> {
+    fn encode_bytes(message: &M) -> Result<Vec<u8>, EncodeError>;
+    fn decode_bytes(bytes: &[u8]) -> Result<M, DecodeError>;
+}
+
 /// A `Bincode` serializer.
 ///
 /// It can serialize any message that satisfies the traits:
@@ -110,6 +121,19 @@ where
     }
 }
 
+impl<M> CanSerializeBytes<M> for Bincode
+where
+    M: serde::Serialize + serde::de::DeserializeOwned,
+{
+    fn encode_bytes(message: &M) -> Result<Vec<u8>, EncodeError> {
+        Ok(bincode::serialize(message)?)
+    }
+
+    fn decode_bytes(bytes: &[u8]) -> Result<M, DecodeError> {
+        Ok(bincode::deserialize(bytes)?)
+    }
+}
+
 /// A `MessagePack` serializer.
 ///
 /// It can serialize any message that satisfies the traits:
@@ -140,6 +164,21 @@ where
     }
 }
 
+#[cfg(feature = "msgpack_serializer")]
+#[cfg_attr(docsrs, doc(cfg(feature = "msgpack_serializer")))]
+impl<M> CanSerializeBytes<M> for MessagePack
+where
+    M: serde::Serialize + serde::de::DeserializeOwned,
+{
+    fn encode_bytes(message: &M) -> Result<Vec<u8>, EncodeError> {
+        Ok(rmp_serde::to_vec(message)?)
+    }
+
+    fn decode_bytes(bytes: &[u8]) -> Result<M, DecodeError> {
+        Ok(rmp_serde::from_slice(bytes)?)
+    }
+}
+
 /// A `Json` serializer.
 ///
 /// It can serialize any message that satisfies the traits:
@@ -170,6 +209,21 @@ where
     }
 }
 
+#[cfg(feature = "json_serializer")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json_serializer")))]
+impl<M> CanSerializeBytes<M> for Json
+where
+    M: serde::Serialize + serde::de::DeserializeOwned,
+{
+    fn encode_bytes(message: &M) -> Result<Vec<u8>, EncodeError> {
+        Ok(serde_json::to_vec(message)?)
+    }
+
+    fn decode_bytes(bytes: &[u8]) -> Result<M, DecodeError> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
 /// The `ProtocolBuffers` serializer can serialize any message that satisfies
 /// the trait `protobuf::Message`.
 #[cfg(feature = "protobuf_serializer")]
@@ -195,6 +249,21 @@ where
     }
 }
 
+#[cfg(feature = "protobuf_serializer")]
+#[cfg_attr(docsrs, doc(cfg(feature = "protobuf_serializer")))]
+impl<M> CanSerializeBytes<M> for ProtocolBuffers
+where
+    M: protobuf::Message,
+{
+    fn encode_bytes(message: &M) -> Result<Vec<u8>, EncodeError> {
+        Ok(message.write_to_bytes()?)
+    }
+
+    fn decode_bytes(bytes: &[u8]) -> Result<M, DecodeError> {
+        Ok(M::parse_from_bytes(bytes)?)
+    }
+}
+
 /// A helper struct to read from and write to the message scratch buffer.
 ///
 /// It simplifies streaming serialization/deserialization directly from the host
@@ -562,3 +562,54 @@ fn generics() {
         .unwrap();
     assert_eq!(PI * 2f32, s);
 }
+
+#[test]
+fn high_priority_handlers() {
+    struct Log {
+        entries: Vec<String>,
+    }
+
+    #[abstract_process]
+    impl Log {
+        #[init]
+        fn init(_: Config<Self>, _: ()) -> Result<Self, ()> {
+            Ok(Self {
+                entries: Vec::new(),
+            })
+        }
+
+        #[handle_message]
+        fn slow(&mut self) {
+            sleep(Duration::from_millis(50));
+            self.entries.push("slow".to_owned());
+        }
+
+        #[handle_message]
+        fn normal(&mut self) {
+            self.entries.push("normal".to_owned());
+        }
+
+        #[handle_message(priority = high)]
+        fn urgent(&mut self) {
+            self.entries.push("urgent".to_owned());
+        }
+
+        #[handle_request(priority = high)]
+        fn handled(&self) -> usize {
+            self.entries.len()
+        }
+
+        #[handle_request]
+        fn entries(&self) -> Vec<String> {
+            self.entries.clone()
+        }
+    }
+
+    let log = Log::link().start(()).unwrap();
+    log.slow();
+    log.normal();
+    log.urgent();
+    // Only `slow` and `urgent` were handled, `normal` is still waiting.
+    assert_eq!(log.handled(), 2);
+    assert_eq!(log.entries(), vec!["slow", "urgent", "normal"]);
+}
@@ -0,0 +1,121 @@
+use std::io::Cursor;
+
+use lunatic::net::codec::{
+    CodecError, Decoder, Framed, LengthDelimitedCodec, LinesCodec, MessageCodec,
+};
+use lunatic::serializer::Json;
+use lunatic::test;
+use serde::{Deserialize, Serialize};
+
+type Buffer = Cursor<Vec<u8>>;
+
+/// Writes frames with `codec` and returns a `Framed` reading them back.
+fn roundtrip<C>(codec: C, write: impl FnOnce(&mut Framed<Buffer, C>)) -> Framed<Buffer, C> {
+    let mut framed = Framed::new(Cursor::new(Vec::new()), codec);
+    write(&mut framed);
+    let (mut stream, codec, _) = framed.into_parts();
+    stream.set_position(0);
+    Framed::new(stream, codec)
+}
+
+#[test]
+fn length_delimited_frames() {
+    let mut framed = roundtrip(LengthDelimitedCodec::u16(), |framed| {
+        framed.send(b"hello").unwrap();
+        framed.send(Vec::new()).unwrap();
+        framed.send("world").unwrap();
+    });
+    assert_eq!(framed.get_ref().get_ref()[..2], [0, 5]);
+    let frames: Vec<_> = framed.by_ref().map(Result::unwrap).collect();
+    assert_eq!(frames, vec![b"hello".to_vec(), vec![], b"world".to_vec()]);
+    assert!(framed.receive().unwrap().is_none());
+
+    let mut framed = roundtrip(LengthDelimitedCodec::u32(), |framed| {
+        framed.send(vec![0; 100]).unwrap();
+        *framed.codec_mut() = LengthDelimitedCodec::u32().max_frame_size(10);
+        assert!(matches!(
+            framed.send(vec![0; 100]),
+            Err(CodecError::FrameTooLarge(100))
+        ));
+    });
+    assert!(matches!(
+        framed.receive(),
+        Err(CodecError::FrameTooLarge(100))
+    ));
+
+    // Streams ending in the middle of a frame are reported.
+    let mut framed = Framed::new(
+        Cursor::new(vec![0, 0, 0, 5, 1, 2]),
+        LengthDelimitedCodec::u32(),
+    );
+    assert!(matches!(framed.receive(), Err(CodecError::UnexpectedEof)));
+
+    // The biggest length doesn't overflow with the prefix on 32-bit targets.
+    let mut codec = LengthDelimitedCodec::u32().max_frame_size(u32::MAX as usize);
+    let result = codec.decode(&mut vec![0xff; 4]);
+    if cfg!(target_pointer_width = "32") {
+        assert!(matches!(result, Err(CodecError::FrameTooLarge(_))));
+    } else {
+        assert!(matches!(result, Ok(None)));
+    }
+}
+
+#[test]
+fn lines() {
+    let framed = roundtrip(LinesCodec::crlf(), |framed| {
+        framed.send("PING").unwrap();
+        framed.send(String::from("with\nnewline")).unwrap();
+    });
+    assert_eq!(framed.get_ref().get_ref(), b"PING\r\nwith\nnewline\r\n");
+    let lines: Vec<_> = framed.map(Result::unwrap).collect();
+    assert_eq!(lines, vec!["PING", "with\nnewline"]);
+
+    // The last line doesn't need a delimiter.
+    let framed = Framed::new(Cursor::new(b"a\r\nb\n\nc".to_vec()), LinesCodec::new());
+    let lines: Vec<_> = framed.map(Result::unwrap).collect();
+    assert_eq!(lines, vec!["a\r", "b", "", "c"]);
+
+    let mut framed = Framed::new(
+        Cursor::new(b"short\nway too long\n".to_vec()),
+        LinesCodec::new().max_length(8),
+    );
+    assert_eq!(framed.receive().unwrap().as_deref(), Some("short"));
+    assert!(matches!(
+        framed.receive(),
+        Err(CodecError::FrameTooLarge(12))
+    ));
+
+    let mut framed = Framed::new(Cursor::new(vec![0xff, b'\n']), LinesCodec::new());
+    assert!(matches!(framed.receive(), Err(CodecError::InvalidUtf8)));
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+enum Command {
+    Get(String),
+    Set(String, u64),
+}
+
+#[test]
+fn messages() {
+    let framed = roundtrip(MessageCodec::<Command>::new(), |framed| {
+        framed.send(Command::Set("a".to_owned(), 1)).unwrap();
+        framed.send(&Command::Get("a".to_owned())).unwrap();
+    });
+    let commands: Vec<_> = framed.map(Result::unwrap).collect();
+    assert_eq!(
+        commands,
+        vec![
+            Command::Set("a".to_owned(), 1),
+            Command::Get("a".to_owned())
+        ]
+    );
+
+    let mut framed = roundtrip(MessageCodec::<Command, Json>::new(), |framed| {
+        framed.send(Command::Get("b".to_owned())).unwrap();
+    });
+    assert_eq!(&framed.get_ref().get_ref()[4..], br#"{"Get":"b"}"#);
+    assert_eq!(
+        framed.receive().unwrap(),
+        Some(Command::Get("b".to_owned()))
+    );
+}
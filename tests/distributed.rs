@@ -0,0 +1,225 @@
+use std::time::Duration;
+
+use lunatic::ap::{AbstractProcess, Config};
+use lunatic::distributed::{self, monitor_nodes, rpc, NodeQuery, Placement, Singleton};
+use lunatic::serializer::Bincode;
+use lunatic::time::Timeout;
+use lunatic::{sleep, test, Mailbox, MessageSignal, Process, ProcessDiedSignal};
+
+#[test]
+fn monitor_nodes_without_changes() {
+    let monitor = monitor_nodes();
+    assert_eq!(
+        monitor.receive_timeout(Duration::from_millis(600)),
+        Err(Timeout)
+    );
+}
+
+#[test]
+fn monitor_nodes_keeps_mailbox_clean(mailbox: Mailbox<u64>) {
+    let monitor = monitor_nodes();
+    mailbox.this().send(7);
+    assert_eq!(
+        monitor.receive_timeout(Duration::from_millis(10)),
+        Err(Timeout)
+    );
+    drop(monitor);
+    assert_eq!(mailbox.receive(), 7);
+}
+
+#[test]
+fn monitor_process_on_unreachable_node(mailbox: Mailbox<()>) {
+    let mailbox = mailbox.monitorable();
+    // No node with this ID is connected, so the monitor fires right away.
+    let remote = unsafe { Process::<()>::new(u64::MAX, 7) };
+    mailbox.monitor(remote);
+    match mailbox.receive_timeout(Duration::from_secs(1)) {
+        Ok(MessageSignal::Signal(ProcessDiedSignal(id))) => assert_eq!(id, 7),
+        _ => panic!("expected the monitor to fire"),
+    }
+}
+
+#[test]
+fn link_to_process_on_unreachable_node(mailbox: Mailbox<()>) {
+    let mailbox = mailbox.monitorable();
+    let child = Process::spawn((), |_, mailbox: Mailbox<()>| {
+        let remote = unsafe { Process::<()>::new(u64::MAX, 7) };
+        remote.link();
+        // The broken link kills the process.
+        mailbox.receive();
+    });
+    let child_id = child.id();
+    mailbox.monitor(child);
+    match mailbox.receive_timeout(Duration::from_secs(1)) {
+        Ok(MessageSignal::Signal(ProcessDiedSignal(id))) => assert_eq!(id, child_id),
+        _ => panic!("expected the link to kill the process"),
+    }
+}
+
+#[test]
+fn rpc_call() {
+    let node = distributed::node_id();
+    let sum = rpc::call(node, (2, 3), |(a, b)| a + b, Duration::from_secs(1)).unwrap();
+    assert_eq!(sum, 5);
+}
+
+#[test]
+fn rpc_call_panics() {
+    let node = distributed::node_id();
+    let result = rpc::call(
+        node,
+        (),
+        |_| -> u32 { panic!("fail") },
+        Duration::from_secs(1),
+    );
+    assert!(result.unwrap_err().is_panicked());
+}
+
+#[test]
+fn rpc_call_timeout() {
+    let node = distributed::node_id();
+    let result = rpc::call(
+        node,
+        (),
+        |_| sleep(Duration::from_millis(200)),
+        Duration::from_millis(10),
+    );
+    assert!(result.unwrap_err().is_timed_out());
+}
+
+#[test]
+fn rpc_multicall() {
+    let node = distributed::node_id();
+    let results = rpc::multicall(&[node, node], 21, |n| n * 2, Duration::from_secs(1));
+    assert_eq!(results.len(), 2);
+    for result in results {
+        assert_eq!(result.unwrap(), 42);
+    }
+}
+
+struct Worker;
+
+impl AbstractProcess for Worker {
+    type State = ();
+    type Serializer = Bincode;
+    type Arg = ();
+    type Handlers = ();
+    type StartupError = ();
+
+    fn init(_: Config<Self>, _: ()) -> Result<(), ()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn placement_strategies() {
+    let node = distributed::node_id();
+    for placement in [
+        Placement::RoundRobin,
+        Placement::Random,
+        Placement::LeastLoaded,
+    ] {
+        let worker = Worker::on_nodes(placement).start(()).unwrap();
+        assert_eq!(worker.node_id(), node);
+    }
+}
+
+#[test]
+fn linked_placement(mailbox: Mailbox<()>) {
+    let mailbox = mailbox.monitorable();
+    let child = Process::spawn((), |_, mailbox: Mailbox<()>| {
+        let worker = Worker::link()
+            .on_nodes(Placement::RoundRobin)
+            .start(())
+            .unwrap();
+        worker.kill();
+        // The broken link kills the process.
+        mailbox.receive();
+    });
+    let child_id = child.id();
+    mailbox.monitor(child);
+    match mailbox.receive_timeout(Duration::from_secs(1)) {
+        Ok(MessageSignal::Signal(ProcessDiedSignal(id))) => assert_eq!(id, child_id),
+        _ => panic!("expected the link to kill the process"),
+    }
+}
+
+#[test]
+fn node_query_to_string() {
+    let query = NodeQuery::new()
+        .attribute("role", "worker")
+        .any_of("region", ["eu", "us west"]);
+    assert_eq!(query.to_string(), "role=worker&region=eu&region=us%20west");
+}
+
+#[test]
+fn node_query_lookup() {
+    let mut all = distributed::lookup_nodes("").unwrap();
+    all.sort_unstable();
+    assert_eq!(NodeQuery::new().lookup().unwrap(), all);
+
+    let missing = NodeQuery::new().any_of("region", ["no-such-region", "no-such-region-either"]);
+    assert!(missing.lookup().unwrap().is_empty());
+
+    let missing = NodeQuery::new()
+        .attribute("role", "no-such-role")
+        .any_of("region", ["eu", "us"]);
+    assert!(missing.lookup().unwrap().is_empty());
+}
+
+#[test]
+fn global_registry() {
+    let first = Process::spawn((), |_, mailbox: Mailbox<u32>| {
+        mailbox.receive();
+    });
+    let second = Process::spawn((), |_, mailbox: Mailbox<u32>| {
+        mailbox.receive();
+    });
+
+    first.register_global("global").unwrap();
+    let existing = second.register_global("global").unwrap_err();
+    assert_eq!(existing.id(), first.id());
+    assert_eq!(
+        Process::<u32>::lookup_global("global").unwrap().id(),
+        first.id()
+    );
+
+    let removed = Process::<u32>::unregister_global("global").unwrap();
+    assert_eq!(removed.id(), first.id());
+    second.register_global("global").unwrap();
+
+    // Entries of dead processes are removed.
+    second.send(0);
+    sleep(Duration::from_millis(50));
+    assert!(Process::<u32>::lookup_global("global").is_none());
+    first.send(0);
+}
+
+/// Reports the ID of each started instance.
+struct Reporter;
+
+impl AbstractProcess for Reporter {
+    type State = ();
+    type Serializer = Bincode;
+    type Arg = Process<u64>;
+    type Handlers = ();
+    type StartupError = ();
+
+    fn init(config: Config<Self>, parent: Process<u64>) -> Result<(), ()> {
+        parent.send(config.self_ref().id());
+        Ok(())
+    }
+}
+
+#[test]
+fn singleton_restarts_instance(mailbox: Mailbox<u64>) {
+    let singleton = Singleton::<Reporter>::start("singleton", mailbox.this());
+    let instance = singleton.get().unwrap();
+    assert_eq!(mailbox.receive(), instance.id());
+
+    // The watcher monitors the instance and restarts it once it dies.
+    instance.kill();
+    let restarted = mailbox.receive_timeout(Duration::from_secs(1)).unwrap();
+    assert_ne!(restarted, instance.id());
+    singleton.stop();
+}
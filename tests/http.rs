@@ -0,0 +1,315 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use lunatic::http::websocket::{CloseFrame, Message, WebSocket};
+use lunatic::http::{Client, HttpError, Method, Request, Response, Router, Server, ShutdownHandle};
+use lunatic::net::{TcpStream, TlsConfig, TlsListener, TlsStream};
+use lunatic::{test, Mailbox, Process};
+
+fn router() -> Router {
+    Router::new()
+        .get("/", |_| Response::text(200, "index"))
+        .get("/users/:id", |request| {
+            Response::text(200, format!("user {}", request.param("id").unwrap()))
+        })
+        .post("/echo", |request| {
+            Response::new(200).with_body(request.into_body())
+        })
+        .get("/files/*path", |request| {
+            Response::text(200, request.param("path").unwrap().to_owned())
+        })
+        .get("/panic", |_| panic!("handler panicked"))
+        .get("/redirect", |_| {
+            Response::new(302).with_header("Location", "/users/9")
+        })
+        .get("/loop", |_| {
+            Response::new(307).with_header("Location", "loop")
+        })
+        .websocket("/ws", |_, mut websocket| loop {
+            match websocket.receive() {
+                Ok(message @ (Message::Text(_) | Message::Binary(_))) => {
+                    websocket.send(message).unwrap()
+                }
+                Ok(Message::Close(_)) | Err(_) => break,
+                Ok(_) => {}
+            }
+        })
+}
+
+#[test]
+fn router_matches_routes() {
+    let router = router();
+    let response = router.handle(Request::new(Method::Get, "/"));
+    assert_eq!(response.body(), b"index");
+    let response = router.handle(Request::new(Method::Get, "/users/42?full=true"));
+    assert_eq!(response.body(), b"user 42");
+    let response = router.handle(Request::new(Method::Get, "/files/a/b%20c.txt"));
+    assert_eq!(response.body(), b"a/b c.txt");
+    let response = router.handle(Request::new(Method::Post, "/echo").with_body("hi"));
+    assert_eq!(response.body(), b"hi");
+}
+
+#[test]
+fn router_not_found_and_not_allowed() {
+    let router = router();
+    let response = router.handle(Request::new(Method::Get, "/missing"));
+    assert_eq!(response.status(), 404);
+    let response = router.handle(Request::new(Method::Delete, "/echo"));
+    assert_eq!(response.status(), 405);
+    assert_eq!(response.header("allow"), Some("POST"));
+    let response = router.handle(Request::new(Method::Head, "/"));
+    assert_eq!(response.status(), 200);
+
+    let router = router.fallback(|_| Response::text(418, "teapot"));
+    let response = router.handle(Request::new(Method::Get, "/missing"));
+    assert_eq!(response.status(), 418);
+}
+
+/// Reads a response with a `Content-Length` header and returns the status
+/// and body.
+fn read_response(reader: &mut impl BufRead) -> (u16, String) {
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    let status = line.split(' ').nth(1).unwrap().parse().unwrap();
+    let mut length = 0;
+    loop {
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+        if line == "\r\n" {
+            break;
+        }
+        if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+            length = value.trim().parse().unwrap();
+        }
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).unwrap();
+    (status, String::from_utf8(body).unwrap())
+}
+
+/// Starts a server in a new process and returns its address.
+fn start_server(mailbox: &Mailbox<(SocketAddr, ShutdownHandle)>) -> (SocketAddr, ShutdownHandle) {
+    Process::spawn(mailbox.this(), |parent, _: Mailbox<()>| {
+        let server = Server::bind("127.0.0.1:0", router())
+            .unwrap()
+            .keep_alive_timeout(Duration::from_millis(200));
+        parent.send((
+            server.local_addr().unwrap(),
+            server.shutdown_handle().unwrap(),
+        ));
+        server.serve().unwrap();
+    });
+    mailbox.receive()
+}
+
+#[test]
+fn server_keep_alive_and_shutdown(mailbox: Mailbox<(SocketAddr, ShutdownHandle)>) {
+    let (addr, handle) = start_server(&mailbox);
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let mut reader = BufReader::new(stream.clone());
+    stream
+        .write_all(b"GET /users/7 HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .unwrap();
+    assert_eq!(read_response(&mut reader), (200, "user 7".to_owned()));
+
+    // The connection is kept alive and chunked bodies are supported.
+    stream
+        .write_all(
+            b"POST /echo HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n2\r\nde\r\n0\r\n\r\n",
+        )
+        .unwrap();
+    assert_eq!(read_response(&mut reader), (200, "abcde".to_owned()));
+
+    // Panics are turned into internal server errors.
+    stream.write_all(b"GET /panic HTTP/1.1\r\n\r\n").unwrap();
+    assert_eq!(read_response(&mut reader).0, 500);
+
+    // Idle connections are closed and the server stops.
+    handle.shutdown();
+    let mut rest = Vec::new();
+    reader.read_to_end(&mut rest).unwrap();
+    assert!(rest.is_empty());
+}
+
+#[test]
+fn chunk_size_overflow(mailbox: Mailbox<(SocketAddr, ShutdownHandle)>) {
+    let (addr, handle) = start_server(&mailbox);
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let mut reader = BufReader::new(stream.clone());
+    stream
+        .write_all(
+            b"POST /echo HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n1\r\na\r\nffffffffffffffff\r\n",
+        )
+        .unwrap();
+    assert_eq!(read_response(&mut reader).0, 413);
+
+    handle.shutdown();
+}
+
+#[test]
+fn many_small_chunks(mailbox: Mailbox<(SocketAddr, ShutdownHandle)>) {
+    let (addr, handle) = start_server(&mailbox);
+
+    // The chunk size lines together exceed the head size limit.
+    let mut request = b"POST /echo HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+    for _ in 0..4096 {
+        request.extend_from_slice(b"1\r\na\r\n");
+    }
+    request.extend_from_slice(b"0\r\n\r\n");
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let mut reader = BufReader::new(stream.clone());
+    stream.write_all(&request).unwrap();
+    assert_eq!(read_response(&mut reader), (200, "a".repeat(4096)));
+
+    handle.shutdown();
+}
+
+#[test]
+fn conflicting_content_lengths(mailbox: Mailbox<(SocketAddr, ShutdownHandle)>) {
+    let (addr, handle) = start_server(&mailbox);
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let mut reader = BufReader::new(stream.clone());
+    stream
+        .write_all(b"POST /echo HTTP/1.1\r\nContent-Length: 2, 2\r\nContent-Length: 2\r\n\r\nhi")
+        .unwrap();
+    assert_eq!(read_response(&mut reader), (200, "hi".to_owned()));
+    stream
+        .write_all(b"POST /echo HTTP/1.1\r\nContent-Length: 2\r\nContent-Length: 3\r\n\r\nhey")
+        .unwrap();
+    assert_eq!(read_response(&mut reader).0, 400);
+
+    handle.shutdown();
+}
+
+#[test]
+fn https_server(mailbox: Mailbox<(SocketAddr, ShutdownHandle)>) {
+    Process::spawn(mailbox.this(), |parent, _: Mailbox<()>| {
+        let config = TlsConfig::new().certificate(
+            include_bytes!("fixtures/tls/server.crt").to_vec(),
+            include_bytes!("fixtures/tls/server.key").to_vec(),
+        );
+        let listener = TlsListener::bind_with_config("127.0.0.1:0", &config).unwrap();
+        let server = Server::new(listener, router());
+        parent.send((
+            server.local_addr().unwrap(),
+            server.shutdown_handle().unwrap(),
+        ));
+        server.serve().unwrap();
+    });
+    let (addr, handle) = mailbox.receive();
+
+    let config = TlsConfig::new()
+        .default_roots(false)
+        .add_root_certificate(include_bytes!("fixtures/tls/ca.crt").to_vec())
+        .server_name("internal.test");
+    let stream = TlsStream::connect_with_config("127.0.0.1", addr.port() as u32, &config).unwrap();
+    let mut reader = BufReader::new(stream);
+    reader
+        .get_mut()
+        .write_all(b"GET /users/7 HTTP/1.1\r\nHost: internal.test\r\n\r\n")
+        .unwrap();
+    assert_eq!(read_response(&mut reader), (200, "user 7".to_owned()));
+
+    handle.shutdown();
+}
+
+#[test]
+fn client_requests_and_redirects(mailbox: Mailbox<(SocketAddr, ShutdownHandle)>) {
+    let (addr, handle) = start_server(&mailbox);
+    let client = Client::new();
+
+    let response = client.get(&format!("http://{addr}/users/1")).unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body(), b"user 1");
+    // The pooled connection is reused.
+    let response = client
+        .post(&format!("http://{addr}/echo"), "hello")
+        .unwrap();
+    assert_eq!(response.body(), b"hello");
+
+    let response = client.get(&format!("http://{addr}/redirect")).unwrap();
+    assert_eq!(response.body(), b"user 9");
+    let response = Client::new()
+        .max_redirects(0)
+        .get(&format!("http://{addr}/redirect"))
+        .unwrap();
+    assert_eq!(response.status(), 302);
+    assert!(matches!(
+        client.get(&format!("http://{addr}/loop")),
+        Err(HttpError::TooManyRedirects)
+    ));
+    assert!(matches!(
+        client.get("ftp://example.com"),
+        Err(HttpError::InvalidUrl(_))
+    ));
+
+    handle.shutdown();
+}
+
+#[test]
+fn websocket_echo(mailbox: Mailbox<(SocketAddr, ShutdownHandle)>) {
+    let (addr, handle) = start_server(&mailbox);
+
+    // Plain HTTP requests to WebSocket routes are rejected.
+    let response = Client::new().get(&format!("http://{addr}/ws")).unwrap();
+    assert_eq!(response.status(), 400);
+
+    let mut websocket = WebSocket::connect(&format!("ws://{addr}/ws")).unwrap();
+    websocket.send(Message::Text("hello".to_owned())).unwrap();
+    assert_eq!(
+        websocket.receive().unwrap(),
+        Message::Text("hello".to_owned())
+    );
+
+    // Fragmented messages are reassembled.
+    websocket.set_max_frame_size(3);
+    websocket
+        .send(Message::Binary(vec![1, 2, 3, 4, 5, 6, 7]))
+        .unwrap();
+    assert_eq!(
+        websocket.receive().unwrap(),
+        Message::Binary(vec![1, 2, 3, 4, 5, 6, 7])
+    );
+
+    websocket.send(Message::Ping(b"ping".to_vec())).unwrap();
+    assert_eq!(
+        websocket.receive().unwrap(),
+        Message::Pong(b"ping".to_vec())
+    );
+
+    websocket
+        .close(Some(CloseFrame {
+            code: 1000,
+            reason: "done".to_owned(),
+        }))
+        .unwrap();
+    assert!(websocket.send(Message::Text("late".to_owned())).is_err());
+
+    handle.shutdown();
+}
+
+#[test]
+fn websocket_writer_process(mailbox: Mailbox<(SocketAddr, ShutdownHandle)>) {
+    let (addr, handle) = start_server(&mailbox);
+
+    // The clone in the other process only writes, this process only reads.
+    let mut websocket = WebSocket::connect(&format!("ws://{addr}/ws")).unwrap();
+    Process::spawn(websocket.clone(), |mut writer, _: Mailbox<()>| {
+        for i in 0..3 {
+            writer.send(Message::Text(format!("message {i}"))).unwrap();
+        }
+    });
+    for i in 0..3 {
+        assert_eq!(
+            websocket.receive().unwrap(),
+            Message::Text(format!("message {i}"))
+        );
+    }
+
+    websocket.close(None).unwrap();
+    handle.shutdown();
+}
@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+use lunatic::pg::ProcessGroup;
+use lunatic::{sleep, test, Mailbox, Process};
+
+#[test]
+fn join_leave_members() {
+    let process = Process::spawn((), |_, mailbox: Mailbox<u32>| {
+        mailbox.receive();
+    });
+    let group = ProcessGroup::new("join_leave");
+    assert!(group.members().is_empty());
+
+    group.join(process);
+    group.join(process);
+    let members = group.members();
+    assert_eq!(members.len(), 1);
+    assert_eq!(members[0].id(), process.id());
+
+    // Groups are scoped by the process type.
+    assert!(ProcessGroup::<String>::new("join_leave")
+        .members()
+        .is_empty());
+
+    group.leave(process);
+    assert!(group.members().is_empty());
+    process.send(0);
+}
+
+#[test]
+fn broadcast(mailbox: Mailbox<u32>) {
+    let parent = mailbox.this();
+    let group = ProcessGroup::new("broadcast");
+    for _ in 0..3 {
+        let subscriber = Process::spawn(parent, |parent, mailbox: Mailbox<u32>| {
+            parent.send(mailbox.receive() * 2);
+        });
+        group.join(subscriber);
+    }
+
+    group.broadcast(21);
+    for _ in 0..3 {
+        assert_eq!(mailbox.receive(), 42);
+    }
+}
+
+#[test]
+fn dead_members_are_removed() {
+    let process = Process::spawn((), |_, mailbox: Mailbox<u32>| {
+        mailbox.receive();
+    });
+    let group = ProcessGroup::new("dead_members");
+    group.join(process);
+    assert_eq!(group.members().len(), 1);
+
+    // Let the process finish.
+    process.send(0);
+    sleep(Duration::from_millis(50));
+    assert!(group.members().is_empty());
+}
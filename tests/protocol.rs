@@ -1,3 +1,4 @@
+use lunatic::protocol::{End, Recv, Send};
 use lunatic::Process;
 use lunatic_test::test;
 
@@ -73,3 +74,194 @@ fn recursive_protocols() {
 
     let _end = loop_protocol.select_right();
 }
+
+lunatic::branches! {
+    enum Calculator {
+        Add(Recv<i32, Recv<i32, Send<i32, End>>>),
+        Negate(Recv<i32, Send<i32, End>>),
+        Quit(End),
+    }
+}
+
+#[test]
+fn labelled_choice() {
+    use lunatic::protocol::Case;
+    use lunatic::protocol::Protocol;
+
+    let protocol = Process::spawn_link((), |_, proto: Protocol<Case<Calculator>>| {
+        match proto.offer() {
+            Calculator::Add(proto) => {
+                let (proto, a) = proto.receive();
+                let (proto, b) = proto.receive();
+                let _ = proto.send(a + b);
+            }
+            Calculator::Negate(proto) => {
+                let (proto, a) = proto.receive();
+                let _ = proto.send(-a);
+            }
+            Calculator::Quit(_) => {}
+        }
+    });
+
+    let protocol = protocol.select(CalculatorDual::Negate);
+    let (_, result) = protocol.send(21).receive();
+    assert_eq!(result, -21);
+}
+
+#[test]
+fn receive_timeout() {
+    use lunatic::protocol::{Protocol, ProtocolError};
+    use std::time::Duration;
+
+    let protocol = Process::spawn_link((), |_, proto: Protocol<Send<(), End>>| {
+        // Never send anything back.
+        proto.cancel();
+    });
+
+    let result = protocol.receive_timeout(Duration::from_millis(10));
+    assert!(matches!(result, Err(ProtocolError::TimedOut)));
+}
+
+#[test]
+fn deadline_exceeded() {
+    use lunatic::protocol::{Protocol, ProtocolError};
+    use std::time::Duration;
+
+    let protocol = Process::spawn_link((), |_, proto: Protocol<Send<(), End>>| {
+        proto.cancel();
+    });
+
+    let protocol = protocol.with_deadline(Duration::from_millis(10));
+    let result = protocol.receive_timeout(Duration::from_secs(10));
+    assert!(matches!(result, Err(ProtocolError::DeadlineExceeded)));
+}
+
+#[test]
+fn deadline_covers_task_result() {
+    use lunatic::spawn_link;
+    use std::time::Duration;
+
+    let task = spawn_link!(@task || {
+        lunatic::sleep(Duration::from_secs(10));
+    });
+    let task = task.with_deadline(Duration::from_millis(10));
+    assert!(task.result_timeout(Duration::from_secs(10)).is_err());
+}
+
+#[test]
+fn stream_protocol_over_tcp() {
+    use lunatic::net::{StreamProtocol, TcpListener, TcpStream};
+    use lunatic::protocol::{Branch, Choose, Offer};
+
+    type Server = Offer<Recv<String, Send<usize, End>>, End>;
+    type Client = Choose<Send<String, Recv<usize, End>>, End>;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    Process::spawn_link(addr, |addr, _: lunatic::Mailbox<()>| {
+        let stream = TcpStream::connect(addr).unwrap();
+        let protocol: StreamProtocol<Client, _> = StreamProtocol::new(stream);
+        let protocol = protocol.select_left().unwrap();
+        let protocol = protocol.send("hello".to_owned()).unwrap();
+        let (_, length) = protocol.receive().unwrap();
+        assert_eq!(length, 5);
+    });
+
+    let (stream, _) = listener.accept().unwrap();
+    let protocol: StreamProtocol<Server, _> = StreamProtocol::new(stream);
+    match protocol.offer().unwrap() {
+        Branch::Left(protocol) => {
+            let (protocol, text) = protocol.receive().unwrap();
+            let _ = protocol.send(text.len()).unwrap();
+        }
+        Branch::Right(_) => panic!("expected left branch"),
+    }
+}
+
+#[test]
+fn stream_protocol_uses_length_delimited_frames() {
+    use lunatic::net::codec::{Framed, LengthDelimitedCodec, MessageCodec};
+    use lunatic::net::{StreamProtocol, TcpListener, TcpStream};
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    Process::spawn_link(addr, |addr, _: lunatic::Mailbox<()>| {
+        let stream = TcpStream::connect(addr).unwrap();
+        let mut framed = Framed::new(stream, MessageCodec::<String>::new());
+        framed.send("hello".to_owned()).unwrap();
+        let mut framed = Framed::new(framed.into_inner(), LengthDelimitedCodec::u32());
+        framed.send(b"after the end").unwrap();
+    });
+
+    let (stream, _) = listener.accept().unwrap();
+    let protocol: StreamProtocol<Recv<String, End>, _> = StreamProtocol::new(stream);
+    let (protocol, text) = protocol.receive().unwrap();
+    assert_eq!(text, "hello");
+
+    // Frames that were read ahead are kept after the protocol ended.
+    let mut framed = protocol.into_framed();
+    assert_eq!(
+        framed.receive().unwrap().as_deref(),
+        Some(&b"after the end"[..])
+    );
+}
+
+lunatic::roles! { Coordinator, Worker, Auditor }
+
+#[test]
+fn multiparty_protocol() {
+    use lunatic::protocol::multiparty::{Choice, Invitation, Message, Session};
+    use lunatic::protocol::Branch;
+    use lunatic::Mailbox;
+
+    // The coordinator either sends a job to the worker or lets it report a
+    // default result. The auditor can't tell the difference.
+    type Job = Choice<
+        Coordinator,
+        Worker,
+        Message<
+            Coordinator,
+            Worker,
+            u64,
+            Message<Worker, Auditor, u64, Message<Auditor, Coordinator, bool, End>>,
+        >,
+        Message<Worker, Auditor, u64, Message<Auditor, Coordinator, bool, End>>,
+    >;
+
+    let worker = Process::spawn_link((), |_, mailbox: Mailbox<Invitation<Job>>| {
+        let worker = mailbox.receive().join::<Worker>();
+        match worker.offer() {
+            Branch::Left(worker) => {
+                let (worker, job) = worker.receive();
+                let _ = worker.send(job * 2);
+            }
+            Branch::Right(worker) => {
+                let _ = worker.send(0);
+            }
+        }
+    });
+    let auditor = Process::spawn_link((), |_, mailbox: Mailbox<Invitation<Job>>| {
+        let auditor = mailbox.receive().join::<Auditor>();
+        let (auditor, result) = auditor.receive();
+        let _ = auditor.send(result == 42);
+    });
+
+    let coordinator = Session::<Job>::new()
+        .with_role::<Worker>(worker)
+        .with_role::<Auditor>(auditor)
+        .start::<Coordinator>();
+    let coordinator = coordinator.select_left().send(21);
+    let (_, approved) = coordinator.receive();
+    assert!(approved);
+}
+
+#[test]
+#[should_panic]
+fn multiparty_unassigned_role() {
+    use lunatic::protocol::multiparty::{Message, Session};
+
+    type Report = Message<Coordinator, Auditor, u64, End>;
+
+    // The auditor is the last role and never assigned.
+    let _ = Session::<Report>::new().start::<Coordinator>();
+}
@@ -0,0 +1,193 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use lunatic::ap::{AbstractProcess, Config, ProcessRef, StartupError};
+use lunatic::registry::{self, HostRegistry, Metadata, Registry};
+use lunatic::serializer::Bincode;
+use lunatic::time::Timeout;
+use lunatic::{sleep, test, Mailbox, Process};
+
+fn spawn_waiting() -> Process<u32> {
+    Process::spawn((), |_, mailbox: Mailbox<u32>| {
+        mailbox.receive();
+    })
+}
+
+#[test]
+fn metadata_and_unregister() {
+    let process = spawn_waiting();
+    let mut metadata = Metadata::new();
+    metadata.insert("role".to_owned(), "worker".to_owned());
+    process.register_with_metadata(&"metadata", metadata.clone());
+
+    let (found, found_metadata) = Process::<u32>::lookup_with_metadata("metadata").unwrap();
+    assert_eq!(found.id(), process.id());
+    assert_eq!(found_metadata, metadata);
+
+    let removed = Process::<u32>::unregister("metadata").unwrap();
+    assert_eq!(removed.id(), process.id());
+    assert!(Process::<u32>::lookup("metadata").is_none());
+    process.send(0);
+}
+
+#[test]
+fn plain_register_takes_over_metadata() {
+    let process = spawn_waiting();
+    let mut metadata = Metadata::new();
+    metadata.insert("role".to_owned(), "worker".to_owned());
+    process.register_with_metadata(&"taken_over", metadata);
+
+    let other = spawn_waiting();
+    other.register(&"taken_over");
+    let (found, found_metadata) = Process::<u32>::lookup_with_metadata("taken_over").unwrap();
+    assert_eq!(found.id(), other.id());
+    assert!(found_metadata.is_empty());
+    assert!(registry::list("taken_over").is_empty());
+
+    process.send(0);
+    other.send(0);
+}
+
+#[test]
+fn list_prefix() {
+    let first = spawn_waiting();
+    let second = spawn_waiting();
+    let other = spawn_waiting();
+    let plain = spawn_waiting();
+    second.register_with_metadata(&"list/b", Metadata::new());
+    first.register_with_metadata(&"list/a", Metadata::new());
+    other.register_with_metadata(&"other", Metadata::new());
+    // Names registered without metadata are not listed.
+    plain.register(&"list/c");
+
+    let entries = registry::list("list/");
+    let names: Vec<&str> = entries.iter().map(|entry| entry.name()).collect();
+    assert_eq!(names, ["list/a", "list/b"]);
+    let found: Option<Process<u32>> = entries[0].process();
+    assert_eq!(found.map(|process| process.id()), Some(first.id()));
+    // The type of the process needs to match.
+    let found: Option<Process<String>> = entries[0].process();
+    assert!(found.is_none());
+
+    for process in [first, second, other, plain] {
+        process.send(0);
+    }
+}
+
+#[test]
+fn dead_processes_are_removed() {
+    let process = spawn_waiting();
+    process.register_with_metadata(&"dead", Metadata::new());
+    assert!(Process::<u32>::lookup("dead").is_some());
+
+    // Let the process finish.
+    process.send(0);
+    sleep(Duration::from_millis(50));
+    assert!(Process::<u32>::lookup("dead").is_none());
+    assert!(registry::list("dead").is_empty());
+}
+
+#[test]
+fn dead_plain_registrations_are_removed() {
+    let process = spawn_waiting();
+    process.register(&"dead_plain");
+    let service = Service::start_as(&"dead_plain", ()).unwrap();
+
+    process.send(0);
+    service.kill();
+    sleep(Duration::from_millis(50));
+    assert!(Process::<u32>::lookup("dead_plain").is_none());
+    assert!(ProcessRef::<Service>::lookup("dead_plain").is_none());
+}
+
+struct Service;
+
+impl AbstractProcess for Service {
+    type State = ();
+    type Serializer = Bincode;
+    type Arg = ();
+    type Handlers = ();
+    type StartupError = ();
+
+    fn init(_: Config<Self>, _: ()) -> Result<(), ()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn await_registered() {
+    Process::spawn((), |_, _: Mailbox<()>| {
+        sleep(Duration::from_millis(20));
+        Service::start_as(&"await_registered", ()).unwrap();
+    });
+
+    let service =
+        ProcessRef::<Service>::await_registered("await_registered", Duration::from_secs(1))
+            .unwrap();
+    assert!(service.is_alive());
+}
+
+#[test]
+fn await_registered_timeout() {
+    let result = ProcessRef::<Service>::await_registered("never", Duration::from_millis(10));
+    assert_eq!(result.map(|_| ()), Err(Timeout));
+}
+
+#[derive(Default)]
+struct LocalRegistry(RefCell<HashMap<String, (u64, u64)>>);
+
+impl Registry for LocalRegistry {
+    fn register(&self, name: &str, node_id: u64, process_id: u64) -> Result<(), (u64, u64)> {
+        let mut names = self.0.borrow_mut();
+        if let Some(existing) = names.get(name) {
+            return Err(*existing);
+        }
+        names.insert(name.to_owned(), (node_id, process_id));
+        Ok(())
+    }
+
+    fn lookup(&self, name: &str) -> Option<(u64, u64)> {
+        self.0.borrow().get(name).copied()
+    }
+
+    fn unregister(&self, name: &str) {
+        self.0.borrow_mut().remove(name);
+    }
+}
+
+#[test]
+fn start_via_custom_registry() {
+    let local = LocalRegistry::default();
+    let service = Service::via(&local).start_as(&"via", ()).unwrap();
+    let found = ProcessRef::<Service>::lookup_via(&local, "via").unwrap();
+    assert_eq!(found.id(), service.id());
+    // Not visible in the built-in registry.
+    assert!(ProcessRef::<Service>::lookup("via").is_none());
+
+    match Service::via(&local).start_as(&"via", ()) {
+        Err(StartupError::NameAlreadyRegistered(existing)) => {
+            assert_eq!(existing.id(), service.id())
+        }
+        _ => panic!("name should be taken"),
+    }
+}
+
+#[test]
+fn register_via_host_registry() {
+    let process = spawn_waiting();
+    process.register_via(&HostRegistry, "via_host").unwrap();
+    assert_eq!(
+        Process::<u32>::lookup("via_host").unwrap().id(),
+        process.id()
+    );
+
+    let other = spawn_waiting();
+    let existing = other.register_via(&HostRegistry, "via_host").unwrap_err();
+    assert_eq!(existing.id(), process.id());
+    assert_eq!(registry::list("via_host")[0].name(), "via_host");
+
+    assert!(Process::<u32>::unregister("via_host").is_some());
+    process.send(0);
+    other.send(0);
+}
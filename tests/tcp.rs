@@ -0,0 +1,245 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr};
+use std::time::Duration;
+
+use lunatic::net::{
+    read_proxy_header, BufTcpStream, ProxyHeader, TcpListener, TcpServer, TcpStream,
+};
+use lunatic::{test, Mailbox, Process, ProcessConfig};
+
+/// Echoes lines back and panics on `crash`.
+fn echo(_: (), mut stream: TcpStream) {
+    let mut reader = BufReader::new(stream.clone());
+    let mut line = String::new();
+    while reader.read_line(&mut line).unwrap() > 0 {
+        if line.trim() == "crash" {
+            panic!("connection crashed");
+        }
+        stream.write_all(line.as_bytes()).unwrap();
+        line.clear();
+    }
+}
+
+fn start_server(mailbox: &Mailbox<SocketAddr>, max_connections: usize) -> SocketAddr {
+    Process::spawn(
+        (mailbox.this(), max_connections),
+        |(parent, max_connections), _: Mailbox<()>| {
+            let server = TcpServer::bind("127.0.0.1:0")
+                .unwrap()
+                .acceptors(2)
+                .max_connections(max_connections);
+            parent.send(server.local_addr().unwrap());
+            server.serve((), echo);
+        },
+    );
+    mailbox.receive()
+}
+
+fn send_line(stream: &mut TcpStream, line: &str) {
+    stream.write_all(format!("{line}\n").as_bytes()).unwrap();
+}
+
+#[test]
+fn tcp_server_survives_crashing_handler(mailbox: Mailbox<SocketAddr>) {
+    let addr = start_server(&mailbox, 10);
+
+    let mut crashing = TcpStream::connect(addr).unwrap();
+    send_line(&mut crashing, "crash");
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let mut reader = BufReader::new(stream.clone());
+    send_line(&mut stream, "hello");
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    assert_eq!(line, "hello\n");
+}
+
+#[test]
+fn tcp_server_limits_connections(mailbox: Mailbox<SocketAddr>) {
+    let addr = start_server(&mailbox, 1);
+
+    let mut first = TcpStream::connect(addr).unwrap();
+    send_line(&mut first, "first");
+    let mut line = String::new();
+    BufReader::new(first.clone()).read_line(&mut line).unwrap();
+    assert_eq!(line, "first\n");
+
+    // The second connection waits in the backlog.
+    let mut second = TcpStream::connect(addr).unwrap();
+    second
+        .set_read_timeout(Some(Duration::from_millis(100)))
+        .unwrap();
+    let mut reader = BufReader::new(second.clone());
+    send_line(&mut second, "second");
+    assert!(reader.read_line(&mut String::new()).is_err());
+
+    // Closing the first connection lets the second one through.
+    send_line(&mut first, "crash");
+    second.set_read_timeout(None).unwrap();
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    assert_eq!(line, "second\n");
+}
+
+/// Answers with the `GREETING` environment variable.
+fn greet(_: (), mut stream: TcpStream) {
+    let greeting = std::env::var("GREETING").unwrap_or_default();
+    send_line(&mut stream, &greeting);
+}
+
+#[test]
+fn tcp_server_connection_config(mailbox: Mailbox<SocketAddr>) {
+    Process::spawn(mailbox.this(), |parent, _: Mailbox<()>| {
+        let mut config = ProcessConfig::new().unwrap();
+        config.add_environment_variable("GREETING", "hello from the config");
+        let server = TcpServer::bind("127.0.0.1:0")
+            .unwrap()
+            .connection_config(config);
+        parent.send(server.local_addr().unwrap());
+        server.serve((), greet);
+    });
+    let addr = mailbox.receive();
+
+    // Connection processes are spawned with the config.
+    let stream = TcpStream::connect(addr).unwrap();
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).unwrap();
+    assert_eq!(line, "hello from the config\n");
+}
+
+#[test]
+fn tcp_stream_socket_options(mailbox: Mailbox<SocketAddr>) {
+    let addr = start_server(&mailbox, 10);
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    assert!(stream.local_addr().unwrap().ip().is_loopback());
+    stream.set_nodelay(true).unwrap();
+    assert!(stream.nodelay().unwrap());
+    stream.set_keepalive(Some(Duration::from_secs(60))).unwrap();
+    assert!(stream.keepalive().unwrap().is_some());
+    stream.set_keepalive(None).unwrap();
+    assert_eq!(stream.keepalive().unwrap(), None);
+    stream.set_linger(Some(Duration::from_secs(1))).unwrap();
+    assert_eq!(stream.linger().unwrap(), Some(Duration::from_secs(1)));
+
+    // Shutting down the write half still lets us read the echoed data.
+    send_line(&mut stream, "bye");
+    stream.shutdown(Shutdown::Write).unwrap();
+    let mut rest = String::new();
+    stream.read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, "bye\n");
+}
+
+#[test]
+fn buf_tcp_stream_keeps_buffer_across_processes(mailbox: Mailbox<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    Process::spawn(listener, |listener, _: Mailbox<()>| {
+        let (mut stream, _) = listener.accept().unwrap();
+        stream.write_all(b"first\nsecond\n").unwrap();
+    });
+
+    let mut stream = BufTcpStream::new(TcpStream::connect(addr).unwrap());
+    let mut line = String::new();
+    stream.read_line(&mut line).unwrap();
+    assert_eq!(line, "first\n");
+    assert_eq!(stream.buffer(), b"second\n");
+
+    Process::spawn(
+        (stream, mailbox.this()),
+        |(mut stream, parent), _: Mailbox<()>| {
+            let mut line = String::new();
+            stream.read_line(&mut line).unwrap();
+            parent.send(line);
+        },
+    );
+    assert_eq!(mailbox.receive(), "second\n");
+}
+
+/// Accepts one proxied connection and sends back the client address, the
+/// header and the first line after it.
+fn accept_proxied(mailbox: &Mailbox<(SocketAddr, ProxyHeader, String)>) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    Process::spawn(
+        (listener, mailbox.this()),
+        |(listener, parent), _: Mailbox<()>| {
+            let (stream, client, header) = listener.accept_proxied().unwrap();
+            let mut line = String::new();
+            BufReader::new(stream).read_line(&mut line).unwrap();
+            parent.send((client, header, line));
+        },
+    );
+    addr
+}
+
+#[test]
+fn tcp_listener_proxy_protocol_v1(mailbox: Mailbox<(SocketAddr, ProxyHeader, String)>) {
+    let addr = accept_proxied(&mailbox);
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .write_all(b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\nhello\n")
+        .unwrap();
+
+    let (client, header, line) = mailbox.receive();
+    assert_eq!(client, "192.0.2.1:56324".parse().unwrap());
+    assert_eq!(header.version(), 1);
+    assert_eq!(
+        header.destination(),
+        Some("198.51.100.1:443".parse().unwrap())
+    );
+    assert_eq!(line, "hello\n");
+}
+
+#[test]
+fn tcp_listener_proxy_protocol_v2(mailbox: Mailbox<(SocketAddr, ProxyHeader, String)>) {
+    let addr = accept_proxied(&mailbox);
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let mut header = b"\r\n\r\n\0\r\nQUIT\n".to_vec();
+    // Version 2, PROXY command, TCP over IPv4.
+    header.extend_from_slice(&[0x21, 0x11, 0, 26]);
+    header.extend_from_slice(&[192, 0, 2, 1, 198, 51, 100, 1]);
+    header.extend_from_slice(&56324u16.to_be_bytes());
+    header.extend_from_slice(&443u16.to_be_bytes());
+    // Authority TLV.
+    header.extend_from_slice(&[0x02, 0, 11]);
+    header.extend_from_slice(b"example.com");
+    header.extend_from_slice(b"hello\n");
+    stream.write_all(&header).unwrap();
+
+    let (client, header, line) = mailbox.receive();
+    assert_eq!(client, "192.0.2.1:56324".parse().unwrap());
+    assert_eq!(header.version(), 2);
+    assert_eq!(header.authority(), Some("example.com"));
+    assert_eq!(line, "hello\n");
+}
+
+#[test]
+fn read_proxy_header_in_connection_process(mailbox: Mailbox<(SocketAddr, ProxyHeader, String)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    Process::spawn(
+        (listener, mailbox.this()),
+        |(listener, parent), _: Mailbox<()>| {
+            while let Ok((stream, _)) = listener.accept() {
+                Process::spawn((stream, parent), |(mut stream, parent), _: Mailbox<()>| {
+                    let header = read_proxy_header(&mut stream).unwrap();
+                    let mut line = String::new();
+                    BufReader::new(stream).read_line(&mut line).unwrap();
+                    parent.send((header.source().unwrap(), header, line));
+                });
+            }
+        },
+    );
+
+    // A client that doesn't send its header doesn't hold up others.
+    let _silent = TcpStream::connect(addr).unwrap();
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .write_all(b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\nhello\n")
+        .unwrap();
+
+    let (client, _, line) = mailbox.receive_timeout(Duration::from_secs(1)).unwrap();
+    assert_eq!(client, "192.0.2.1:56324".parse().unwrap());
+    assert_eq!(line, "hello\n");
+}
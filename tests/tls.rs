@@ -0,0 +1,212 @@
+use std::io::{Read, Write};
+use std::net::SocketAddr;
+
+use lunatic::net::{
+    ClientAuth, ProxyHeader, TcpListener, TcpStream, TlsConfig, TlsListener, TlsStream,
+};
+use lunatic::{test, Mailbox, Process};
+use serde::{Deserialize, Serialize};
+
+// A self-signed CA with a server certificate for `internal.test` and a client
+// certificate, both signed by the CA.
+const CA: &[u8] = include_bytes!("fixtures/tls/ca.crt");
+const SERVER_CERT: &[u8] = include_bytes!("fixtures/tls/server.crt");
+const SERVER_KEY: &[u8] = include_bytes!("fixtures/tls/server.key");
+const SERVER_DER: &[u8] = include_bytes!("fixtures/tls/server.der");
+const CLIENT_CERT: &[u8] = include_bytes!("fixtures/tls/client.crt");
+const CLIENT_KEY: &[u8] = include_bytes!("fixtures/tls/client.key");
+const CLIENT_DER: &[u8] = include_bytes!("fixtures/tls/client.der");
+
+#[derive(Serialize, Deserialize, Debug)]
+enum Event {
+    Bound(SocketAddr),
+    Accepted {
+        alpn_protocol: Option<Vec<u8>>,
+        peer_certificates: Vec<Vec<u8>>,
+    },
+    Proxied {
+        client: SocketAddr,
+        header: ProxyHeader,
+    },
+}
+
+fn server_config() -> TlsConfig {
+    TlsConfig::new()
+        .default_roots(false)
+        .add_root_certificate(CA.to_vec())
+        .certificate(SERVER_CERT.to_vec(), SERVER_KEY.to_vec())
+}
+
+fn client_config() -> TlsConfig {
+    TlsConfig::new()
+        .default_roots(false)
+        .add_root_certificate(CA.to_vec())
+        .server_name("internal.test")
+}
+
+/// Starts a server that answers `ping` with `pong` and reports every
+/// successful handshake to the parent.
+fn start_server(mailbox: &Mailbox<Event>, config: TlsConfig) -> SocketAddr {
+    Process::spawn(
+        (mailbox.this(), config),
+        |(parent, config), _: Mailbox<()>| {
+            let listener = TlsListener::bind_with_config("127.0.0.1:0", &config).unwrap();
+            parent.send(Event::Bound(listener.local_addr().unwrap()));
+            loop {
+                let mut stream = match listener.accept() {
+                    Ok((stream, _)) => stream,
+                    Err(_) => continue,
+                };
+                let mut ping = [0; 4];
+                if stream.read_exact(&mut ping).is_err() {
+                    continue;
+                }
+                parent.send(Event::Accepted {
+                    alpn_protocol: stream.alpn_protocol(),
+                    peer_certificates: stream.peer_certificates(),
+                });
+                let _ = stream.write_all(b"pong");
+            }
+        },
+    );
+    bound(mailbox)
+}
+
+/// Waits for a spawned process to report its address.
+fn bound(mailbox: &Mailbox<Event>) -> SocketAddr {
+    match mailbox.receive() {
+        Event::Bound(addr) => addr,
+        event => panic!("unexpected event {event:?}"),
+    }
+}
+
+/// Forwards one connection to `server` and sends `header` first, like a load
+/// balancer speaking the PROXY protocol.
+fn start_proxy(mailbox: &Mailbox<Event>, server: SocketAddr, header: &[u8]) -> SocketAddr {
+    Process::spawn(
+        (mailbox.this(), server, header.to_vec()),
+        |(parent, server, header), _: Mailbox<()>| {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            parent.send(Event::Bound(listener.local_addr().unwrap()));
+            let (mut client, _) = listener.accept().unwrap();
+            let mut upstream = TcpStream::connect(server).unwrap();
+            upstream.write_all(&header).unwrap();
+            Process::spawn(
+                (client.clone(), upstream.clone()),
+                |(mut client, mut upstream), _: Mailbox<()>| {
+                    let _ = std::io::copy(&mut client, &mut upstream);
+                },
+            );
+            let _ = std::io::copy(&mut upstream, &mut client);
+        },
+    );
+    bound(mailbox)
+}
+
+/// Connects to the server and exchanges `ping` and `pong`.
+fn ping(addr: SocketAddr, config: &TlsConfig) -> std::io::Result<TlsStream> {
+    let mut stream = TlsStream::connect_with_config("127.0.0.1", addr.port() as u32, config)?;
+    stream.write_all(b"ping")?;
+    let mut pong = [0; 4];
+    stream.read_exact(&mut pong)?;
+    assert_eq!(&pong, b"pong");
+    Ok(stream)
+}
+
+#[test]
+fn mutual_tls_with_alpn(mailbox: Mailbox<Event>) {
+    let config = server_config()
+        .client_auth(ClientAuth::Required)
+        .alpn_protocols(["h2", "http/1.1"]);
+    let addr = start_server(&mailbox, config);
+
+    let config = client_config()
+        .certificate(CLIENT_CERT.to_vec(), CLIENT_KEY.to_vec())
+        .alpn_protocols(["http/1.1"]);
+    let stream = ping(addr, &config).unwrap();
+    assert_eq!(stream.alpn_protocol().as_deref(), Some(&b"http/1.1"[..]));
+    assert_eq!(stream.peer_certificates()[0], SERVER_DER);
+
+    match mailbox.receive() {
+        Event::Accepted {
+            alpn_protocol,
+            peer_certificates,
+        } => {
+            assert_eq!(alpn_protocol.as_deref(), Some(&b"http/1.1"[..]));
+            assert_eq!(peer_certificates[0], CLIENT_DER);
+        }
+        event => panic!("unexpected event {event:?}"),
+    }
+}
+
+#[test]
+fn required_client_auth_rejects_missing_certificate(mailbox: Mailbox<Event>) {
+    let addr = start_server(&mailbox, server_config().client_auth(ClientAuth::Required));
+
+    assert!(ping(addr, &client_config()).is_err());
+}
+
+#[test]
+fn optional_client_auth(mailbox: Mailbox<Event>) {
+    let addr = start_server(&mailbox, server_config().client_auth(ClientAuth::Optional));
+
+    let stream = ping(addr, &client_config()).unwrap();
+    assert_eq!(stream.alpn_protocol(), None);
+    match mailbox.receive() {
+        Event::Accepted {
+            alpn_protocol,
+            peer_certificates,
+        } => {
+            assert_eq!(alpn_protocol, None);
+            assert!(peer_certificates.is_empty());
+        }
+        event => panic!("unexpected event {event:?}"),
+    }
+}
+
+#[test]
+fn server_name_override(mailbox: Mailbox<Event>) {
+    let addr = start_server(&mailbox, server_config());
+
+    // The certificate is only valid for `internal.test`, not the address.
+    let config = TlsConfig::new()
+        .default_roots(false)
+        .add_root_certificate(CA.to_vec());
+    assert!(ping(addr, &config).is_err());
+
+    assert!(ping(addr, &config.server_name("internal.test")).is_ok());
+}
+
+#[test]
+fn tls_listener_proxy_protocol(mailbox: Mailbox<Event>) {
+    Process::spawn(mailbox.this(), |parent, _: Mailbox<()>| {
+        let config = server_config().proxy_protocol(true);
+        let listener = TlsListener::bind_with_config("127.0.0.1:0", &config).unwrap();
+        parent.send(Event::Bound(listener.local_addr().unwrap()));
+        let (mut stream, client, header) = listener.accept_proxied().unwrap();
+        let mut ping = [0; 4];
+        stream.read_exact(&mut ping).unwrap();
+        stream.write_all(b"pong").unwrap();
+        parent.send(Event::Proxied { client, header });
+    });
+    let server = bound(&mailbox);
+
+    // The header is read before the handshake.
+    let proxy = start_proxy(
+        &mailbox,
+        server,
+        b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\n",
+    );
+    ping(proxy, &client_config()).unwrap();
+    match mailbox.receive() {
+        Event::Proxied { client, header } => {
+            assert_eq!(client, "192.0.2.1:56324".parse().unwrap());
+            assert_eq!(header.version(), 1);
+            assert_eq!(
+                header.destination(),
+                Some("198.51.100.1:443".parse().unwrap())
+            );
+        }
+        event => panic!("unexpected event {event:?}"),
+    }
+}
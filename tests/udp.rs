@@ -1,5 +1,6 @@
 use std::io::ErrorKind;
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::Duration;
 
 use lunatic::net;
 use lunatic_test::test;
@@ -127,3 +128,60 @@ fn udp_broadcast_setter_getter_false() {
 
     assert_eq!(cur_broadcast, false);
 }
+
+#[test]
+fn udp_read_timeout() {
+    let receiver = net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    assert_eq!(receiver.read_timeout().unwrap(), None);
+    receiver
+        .set_read_timeout(Some(Duration::from_millis(50)))
+        .unwrap();
+    assert_eq!(
+        receiver.read_timeout().unwrap(),
+        Some(Duration::from_millis(50))
+    );
+    assert!(receiver.set_read_timeout(Some(Duration::ZERO)).is_err());
+
+    let mut buf = [0; 4];
+    let err = receiver.recv_from(&mut buf).unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        ErrorKind::WouldBlock | ErrorKind::TimedOut
+    ));
+}
+
+#[test]
+fn udp_nonblocking() {
+    let sender = net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let receiver = net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    receiver.set_nonblocking(true).unwrap();
+
+    let mut buf = [0; 4];
+    let err = receiver.recv(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::WouldBlock);
+    assert!(receiver.take_error().unwrap().is_none());
+
+    receiver.set_nonblocking(false).unwrap();
+    sender
+        .send_to("P3NG".as_bytes(), receiver.local_addr().unwrap())
+        .unwrap();
+    let len_in = receiver.recv(&mut buf).unwrap();
+    assert_eq!(len_in, 4);
+    assert_eq!(buf, "P3NG".as_bytes());
+}
+
+#[test]
+fn udp_join_leave_multicast_v4() {
+    let socket = net::UdpSocket::bind("0.0.0.0:0").unwrap();
+    let group = Ipv4Addr::new(224, 0, 0, 123);
+    socket
+        .join_multicast_v4(&group, &Ipv4Addr::UNSPECIFIED)
+        .unwrap();
+    socket
+        .leave_multicast_v4(&group, &Ipv4Addr::UNSPECIFIED)
+        .unwrap();
+    // Leaving a group twice fails.
+    assert!(socket
+        .leave_multicast_v4(&group, &Ipv4Addr::UNSPECIFIED)
+        .is_err());
+}